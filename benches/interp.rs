@@ -0,0 +1,226 @@
+//! Batch and grid evaluation against the scalar path.
+//!
+//! `cargo bench --bench interp`, with `--features rayon` for the parallel variants.
+//! After the criterion runs, the speedup of each batch path over its scalar loop is printed.
+
+use std::time::{Duration, Instant};
+
+use criterion::{black_box, criterion_group, BenchmarkId, Criterion};
+use numerics::{
+    interp::{
+        bicubic_spline::Spline2D, bilinear::Bilinear, linear::LinearInterp, spline1d::Spline1D,
+        Interp,
+    },
+    Matrix,
+};
+
+fn linspace(a: f64, b: f64, n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| a + (b - a) * i as f64 / (n - 1) as f64)
+        .collect()
+}
+
+fn bench_interp1d(c: &mut Criterion) {
+    let xx = linspace(0.0, 10.0, 1000);
+    let yy: Vec<f64> = xx.iter().map(|x| x.sin()).collect();
+    let spline = Spline1D::new(&xx, &yy).unwrap();
+    let linear = LinearInterp::new(&xx, &yy).unwrap();
+
+    let mut group = c.benchmark_group("interp1d");
+    for &n in &[1_000, 100_000] {
+        let xs = linspace(0.0, 10.0, n);
+        group.bench_with_input(BenchmarkId::new("spline_eval", n), &xs, |b, xs| {
+            b.iter(|| {
+                xs.iter()
+                    .map(|&x| spline.eval(x).unwrap())
+                    .collect::<Vec<f64>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("spline_interp_many", n), &xs, |b, xs| {
+            b.iter(|| spline.interp_many(black_box(xs)).unwrap())
+        });
+        #[cfg(feature = "rayon")]
+        group.bench_with_input(
+            BenchmarkId::new("spline_par_interp_many", n),
+            &xs,
+            |b, xs| b.iter(|| spline.par_interp_many(black_box(xs)).unwrap()),
+        );
+        group.bench_with_input(BenchmarkId::new("linear_eval", n), &xs, |b, xs| {
+            b.iter(|| {
+                xs.iter()
+                    .map(|&x| linear.eval(x).unwrap())
+                    .collect::<Vec<f64>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("linear_interp_many", n), &xs, |b, xs| {
+            b.iter(|| linear.interp_many(black_box(xs)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_interp2d(c: &mut Criterion) {
+    let x1 = linspace(0.0, 10.0, 50);
+    let x2 = linspace(-5.0, 5.0, 40);
+    let data: Vec<f64> = x1
+        .iter()
+        .flat_map(|&a| x2.iter().map(move |&b| a.sin() * b.cos()))
+        .collect();
+    let y = Matrix::new(x1.len(), x2.len(), data);
+
+    let mut group = c.benchmark_group("interp2d");
+    for &n in &[100, 500] {
+        let x1s = linspace(0.0, 10.0, n);
+        let x2s = linspace(-5.0, 5.0, n);
+        let mut bilinear = Bilinear::new(&x1, &x2, &y).unwrap();
+        group.bench_function(BenchmarkId::new("bilinear_interp", n), |b| {
+            b.iter(|| {
+                let mut sum = 0.0;
+                for &a in &x1s {
+                    for &c in &x2s {
+                        sum += bilinear.interp(a, c).unwrap();
+                    }
+                }
+                sum
+            })
+        });
+        let bilinear = Bilinear::new(&x1, &x2, &y).unwrap();
+        group.bench_function(BenchmarkId::new("bilinear_interp_grid", n), |b| {
+            b.iter(|| {
+                bilinear
+                    .interp_grid(black_box(&x1s), black_box(&x2s))
+                    .unwrap()
+            })
+        });
+
+        let mut spline = Spline2D::new(&x1, &x2, &y).unwrap();
+        group.bench_function(BenchmarkId::new("spline_interp", n), |b| {
+            b.iter(|| {
+                let mut sum = 0.0;
+                for &a in &x1s {
+                    for &c in &x2s {
+                        sum += spline.interp(a, c).unwrap();
+                    }
+                }
+                sum
+            })
+        });
+        group.bench_function(BenchmarkId::new("spline_interp_grid", n), |b| {
+            b.iter(|| {
+                spline
+                    .interp_grid(black_box(&x1s), black_box(&x2s))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Median wall time of `f` over a few runs.
+fn median_time<R>(mut f: impl FnMut() -> R) -> Duration {
+    let mut runs: Vec<Duration> = (0..11)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    runs.sort();
+    runs[runs.len() / 2]
+}
+
+fn print_speedup(name: &str, scalar: Duration, batch: Duration) {
+    println!(
+        "{:<40} scalar {:>10.3?}  batch {:>10.3?}  speedup {:>5.2}x",
+        name,
+        scalar,
+        batch,
+        scalar.as_secs_f64() / batch.as_secs_f64()
+    );
+}
+
+/// Scalar loop against the batch path for each interpolator, as a ratio.
+fn speedups() {
+    let xx = linspace(0.0, 10.0, 1000);
+    let yy: Vec<f64> = xx.iter().map(|x| x.sin()).collect();
+    let spline = Spline1D::new(&xx, &yy).unwrap();
+    let linear = LinearInterp::new(&xx, &yy).unwrap();
+    let xs = linspace(0.0, 10.0, 100_000);
+    let scalar = median_time(|| {
+        xs.iter()
+            .map(|&x| spline.eval(x).unwrap())
+            .collect::<Vec<f64>>()
+    });
+    print_speedup(
+        "Spline1D interp_many (100000)",
+        scalar,
+        median_time(|| spline.interp_many(&xs).unwrap()),
+    );
+    #[cfg(feature = "rayon")]
+    print_speedup(
+        "Spline1D par_interp_many (100000)",
+        scalar,
+        median_time(|| spline.par_interp_many(&xs).unwrap()),
+    );
+    let scalar = median_time(|| {
+        xs.iter()
+            .map(|&x| linear.eval(x).unwrap())
+            .collect::<Vec<f64>>()
+    });
+    print_speedup(
+        "LinearInterp interp_many (100000)",
+        scalar,
+        median_time(|| linear.interp_many(&xs).unwrap()),
+    );
+
+    let x1 = linspace(0.0, 10.0, 50);
+    let x2 = linspace(-5.0, 5.0, 40);
+    let data: Vec<f64> = x1
+        .iter()
+        .flat_map(|&a| x2.iter().map(move |&b| a.sin() * b.cos()))
+        .collect();
+    let y = Matrix::new(x1.len(), x2.len(), data);
+    let x1s = linspace(0.0, 10.0, 500);
+    let x2s = linspace(-5.0, 5.0, 500);
+    let mut bilinear = Bilinear::new(&x1, &x2, &y).unwrap();
+    let scalar = median_time(|| {
+        let mut sum = 0.0;
+        for &a in &x1s {
+            for &b in &x2s {
+                sum += bilinear.interp(a, b).unwrap();
+            }
+        }
+        sum
+    });
+    let bilinear = Bilinear::new(&x1, &x2, &y).unwrap();
+    print_speedup(
+        "Bilinear interp_grid (500 x 500)",
+        scalar,
+        median_time(|| bilinear.interp_grid(&x1s, &x2s).unwrap()),
+    );
+    let mut spline = Spline2D::new(&x1, &x2, &y).unwrap();
+    let scalar = median_time(|| {
+        let mut sum = 0.0;
+        for &a in &x1s {
+            for &b in &x2s {
+                sum += spline.interp(a, b).unwrap();
+            }
+        }
+        sum
+    });
+    print_speedup(
+        "Spline2D interp_grid (500 x 500)",
+        scalar,
+        median_time(|| spline.interp_grid(&x1s, &x2s).unwrap()),
+    );
+}
+
+criterion_group!(benches, bench_interp1d, bench_interp2d);
+
+fn main() {
+    benches();
+    if std::env::args().any(|arg| arg == "--bench") {
+        speedups();
+    }
+    Criterion::default().configure_from_args().final_summary();
+}
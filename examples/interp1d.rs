@@ -2,7 +2,11 @@
 use std::{env, path::PathBuf};
 
 use numerics::interp::{
-    barycentric_1d::BaryRat1D, poly_1d::Poly1D, rational_1d::Rational1D, spline1d::Spline1D, Interp,
+    barycentric_1d::{BaryRat1D, BaryWeights},
+    poly_1d::Poly1D,
+    rational_1d::Rational1D,
+    spline1d::Spline1D,
+    Interp,
 };
 
 fn interp_poly(mm: usize) -> Vec<(f64, f64)> {
@@ -19,7 +23,7 @@ fn interp_poly(mm: usize) -> Vec<(f64, f64)> {
         func(11.0),
         func(12.0),
     ];
-    let mut interp = Poly1D::new(&xx, &yy, mm);
+    let mut interp = Poly1D::new(&xx, &yy, mm).unwrap();
     let xy: Vec<(f64, f64)> = (0..120)
         .map(|i| {
             let x = i as f64 / 10.0;
@@ -43,7 +47,7 @@ fn interp_rati() -> Vec<(f64, f64)> {
         func(11.0),
         func(12.0),
     ];
-    let mut ra1d = Rational1D::new(&xx, &yy, 4);
+    let mut ra1d = Rational1D::new(&xx, &yy, 4).unwrap();
     let xy: Vec<(f64, f64)> = (0..120)
         .map(|i| {
             let x = i as f64 / 10.0;
@@ -67,7 +71,11 @@ fn interp_barycentric(mm: usize, calc: usize) -> Vec<(f64, f64)> {
         func(11.0),
         func(12.0),
     ];
-    let mut inter = BaryRat1D::new(&xx, &yy, mm, calc);
+    let weights = match calc {
+        1 => BaryWeights::FloaterHormann(mm),
+        _ => BaryWeights::Polynomial,
+    };
+    let mut inter = BaryRat1D::new(&xx, &yy, weights).unwrap();
     let xy: Vec<(f64, f64)> = (0..120)
         .map(|i| {
             let x = i as f64 / 10.0;
@@ -19,16 +19,17 @@ fn spline2d() -> Vec<(f64, f64, f64)> {
         .collect();
     let y = Matrix::new(x1.len(), x2.len(), y);
 
-    let mut interp = Spline2D::new(&x1, &x2, &y);
-    (0..100)
-        .flat_map(|i| {
-            (0..100)
-                .map(|j| {
-                    let yy = i as f64 * 0.1;
-                    let xx = j as f64 * 0.1;
-                    let zz = interp.interp(yy, xx);
-                    (xx, yy, zz)
-                })
+    let interp = Spline2D::new(&x1, &x2, &y).unwrap();
+    let ticks: Vec<f64> = (0..100).map(|i| i as f64 * 0.1).collect();
+    let zz = interp.interp_grid(&ticks, &ticks).unwrap();
+    ticks
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &yy)| {
+            ticks
+                .iter()
+                .enumerate()
+                .map(|(j, &xx)| (xx, yy, zz[i][j]))
                 .collect::<Vec<(f64, f64, f64)>>()
         })
         .collect()
@@ -0,0 +1,290 @@
+use anyhow::Result;
+
+use super::{
+    interp1d::poly_coeff::{ipcshft, pcshft},
+    InterpError, InterpFloat,
+};
+
+/// Chebyshev approximation on \[a, b\]:
+/// f(x) ~ sum_{k < m} c\[k\] T_k(y) - c\[0\] / 2, y = (2x - a - b) / (b - a).
+#[derive(Debug, Clone)]
+pub struct Chebyshev<T>
+where
+    T: InterpFloat,
+{
+    m: usize,
+    a: T,
+    b: T,
+    coeffs: Vec<T>,
+}
+
+impl<T> Chebyshev<T>
+where
+    T: InterpFloat,
+{
+    /// Approximation of degree n - 1 interpolating `func` at the n zeros of T_n.
+    pub fn new<F>(func: F, a: T, b: T, n: usize) -> Result<Self>
+    where
+        F: Fn(T) -> T,
+    {
+        if n == 0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let bma = T::lit(0.5) * (b - a);
+        let bpa = T::lit(0.5) * (b + a);
+        let nf = T::from_usize(n).unwrap();
+        let pi = T::lit(std::f64::consts::PI);
+        let half = T::lit(0.5);
+        let f: Vec<T> = (0..n)
+            .map(|k| {
+                let y = (pi * (T::from_usize(k).unwrap() + half) / nf).cos();
+                func(y * bma + bpa)
+            })
+            .collect();
+        let fac = T::lit(2.0) / nf;
+        let coeffs = (0..n)
+            .map(|j| {
+                let jf = T::from_usize(j).unwrap();
+                let sum = f.iter().enumerate().fold(T::zero(), |acc, (k, &fk)| {
+                    acc + fk * (pi * jf * (T::from_usize(k).unwrap() + half) / nf).cos()
+                });
+                fac * sum
+            })
+            .collect();
+        Self::from_coeffs(coeffs, a, b)
+    }
+
+    /// Approximation with the given Chebyshev coefficients (c\[0\] counted twice as above).
+    pub fn from_coeffs(coeffs: Vec<T>, a: T, b: T) -> Result<Self> {
+        if coeffs.is_empty() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if a == b {
+            return Err(InterpError::IdenticalX().into());
+        }
+        Ok(Self {
+            m: coeffs.len(),
+            a,
+            b,
+            coeffs,
+        })
+    }
+
+    /// Approximation equal to the polynomial sum_k d\[k\] x^k on \[a, b\].
+    pub fn from_monomial(d: &[T], a: T, b: T) -> Result<Self> {
+        let mut d = d.to_vec();
+        ipcshft(a, b, &mut d);
+        let n = d.len();
+        let mut coeffs = vec![T::zero(); n];
+        let mut pow = T::one();
+        if n > 0 {
+            coeffs[0] = T::lit(2.0) * d[0];
+        }
+        for (k, &dk) in d.iter().enumerate().skip(1) {
+            let mut fac = dk / pow;
+            let mut jm = k;
+            for (jp, j) in (1..).zip((0..=k).rev().step_by(2)) {
+                coeffs[j] = coeffs[j] + fac;
+                fac = fac * T::from_usize(jm).unwrap() / T::from_usize(jp).unwrap();
+                jm = jm.saturating_sub(1);
+            }
+            pow = pow + pow;
+        }
+        Self::from_coeffs(coeffs, a, b)
+    }
+
+    /// Economized power series: the polynomial sum_k d\[k\] x^k is converted to Chebyshev form
+    /// on \[a, b\], truncated at `tol` and converted back. The result has a maximum deviation
+    /// from the original of about `tol` on \[a, b\].
+    pub fn economize(d: &[T], a: T, b: T, tol: T) -> Result<Vec<T>> {
+        let mut cheb = Self::from_monomial(d, a, b)?;
+        cheb.truncate(tol);
+        Ok(cheb.to_monomial())
+    }
+
+    pub fn a(&self) -> T {
+        self.a
+    }
+
+    pub fn b(&self) -> T {
+        self.b
+    }
+
+    /// Number of coefficients used in evaluation.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// All the coefficients, including the ones dropped by `truncate`.
+    pub fn coeffs(&self) -> &[T] {
+        &self.coeffs
+    }
+
+    /// Drops the trailing coefficients smaller than `tol` in magnitude. The error introduced
+    /// is bounded by the sum of the dropped ones. Returns the number of coefficients kept.
+    pub fn truncate(&mut self, tol: T) -> usize {
+        let mut m = self.coeffs.len();
+        while m > 1 && self.coeffs[m - 1].abs() < tol {
+            m -= 1;
+        }
+        self.m = m;
+        m
+    }
+
+    /// Value at `x` by Clenshaw's recurrence.
+    pub fn eval(&self, x: T) -> T {
+        let y = (T::lit(2.0) * x - self.a - self.b) / (self.b - self.a);
+        let y2 = y + y;
+        let (d, dd) = self.coeffs[1..self.m]
+            .iter()
+            .rev()
+            .fold((T::zero(), T::zero()), |(d, dd), &cj| (y2 * d - dd + cj, d));
+        y * d - dd + T::lit(0.5) * self.coeffs[0]
+    }
+
+    /// Approximation of the derivative.
+    pub fn derivative(&self) -> Self {
+        let n = self.m;
+        let mut cder = vec![T::zero(); n];
+        if n > 1 {
+            cder[n - 2] = T::lit(2.0) * T::from_usize(n - 1).unwrap() * self.coeffs[n - 1];
+            for j in (1..(n - 1)).rev() {
+                cder[j - 1] =
+                    cder[j + 1] + T::lit(2.0) * T::from_usize(j).unwrap() * self.coeffs[j];
+            }
+        }
+        let con = T::lit(2.0) / (self.b - self.a);
+        Self {
+            m: n,
+            a: self.a,
+            b: self.b,
+            coeffs: cder.into_iter().map(|v| v * con).collect(),
+        }
+    }
+
+    /// Approximation of the indefinite integral which is zero at a.
+    pub fn integral(&self) -> Self {
+        let n = self.m;
+        let c = &self.coeffs;
+        let con = T::lit(0.25) * (self.b - self.a);
+        let mut cint = vec![T::zero(); n + 1];
+        let mut sum = T::zero();
+        let mut fac = T::one();
+        for j in 1..=n {
+            let cm = c[j - 1];
+            let cp = if j + 1 < n { c[j + 1] } else { T::zero() };
+            cint[j] = con * (cm - cp) / T::from_usize(j).unwrap();
+            sum = sum + fac * cint[j];
+            fac = -fac;
+        }
+        cint[0] = T::lit(2.0) * sum;
+        Self {
+            m: n + 1,
+            a: self.a,
+            b: self.b,
+            coeffs: cint,
+        }
+    }
+
+    /// Coefficients d of the polynomial sum_k d\[k\] x^k equal to the approximation.
+    pub fn to_monomial(&self) -> Vec<T> {
+        let n = self.m;
+        let c = &self.coeffs;
+        let mut d = vec![T::zero(); n];
+        let mut dd = vec![T::zero(); n];
+        d[0] = c[n - 1];
+        for j in (1..(n.saturating_sub(1))).rev() {
+            for k in (1..=(n - j)).rev() {
+                let sv = dd[k];
+                dd[k] = d[k];
+                d[k] = T::lit(2.0) * d[k - 1] - sv;
+            }
+            let sv = dd[0];
+            dd[0] = d[0];
+            d[0] = -sv + c[j];
+        }
+        for j in (1..n).rev() {
+            d[j] = d[j - 1] - dd[j];
+        }
+        d[0] = -dd[0] + T::lit(0.5) * c[0];
+        pcshft(self.a, self.b, &mut d);
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interp::interp1d::poly_coeff::poly_coeff;
+
+    #[test]
+    fn test_chebyshev() {
+        let cheb = Chebyshev::new(f64::exp, -1.0, 2.0, 20).unwrap();
+        for x in [-1.0, -0.3, 0.5, 1.7, 2.0] {
+            assert!((cheb.eval(x) - f64::exp(x)).abs() < 1e-13, "x = {}", x);
+        }
+        let mut trunc = cheb.clone();
+        let m = trunc.truncate(1e-8);
+        assert!(m < 20 && m > 5, "m = {}", m);
+        for x in [-0.7, 0.1, 1.9] {
+            assert!((trunc.eval(x) - f64::exp(x)).abs() < 1e-7, "x = {}", x);
+        }
+
+        let cheb = Chebyshev::new(f64::sin, 0.0, 3.0, 24).unwrap();
+        let der = cheb.derivative();
+        let int = cheb.integral();
+        for x in [0.0, 0.4, 1.5, 2.9] {
+            assert!((der.eval(x) - x.cos()).abs() < 1e-11, "x = {}", x);
+            assert!((int.eval(x) - (1.0 - x.cos())).abs() < 1e-13, "x = {}", x);
+        }
+    }
+
+    #[test]
+    fn test_chebyshev_monomial() {
+        // 1 - 2x + 3x^3 is reproduced by 4 terms
+        let func = |x: f64| 1.0 - 2.0 * x + 3.0 * x * x * x;
+        let cheb = Chebyshev::new(func, 0.5, 2.0, 6).unwrap();
+        assert!(cheb.coeffs()[4].abs() < 1e-12 && cheb.coeffs()[5].abs() < 1e-12);
+        let d = cheb.to_monomial();
+        let expected = [1.0, -2.0, 0.0, 3.0, 0.0, 0.0];
+        for (v, e) in d.iter().zip(expected.iter()) {
+            assert!((v - e).abs() < 1e-10, "d = {:?}", d);
+        }
+
+        // the same coefficients as interpolating at the Chebyshev nodes
+        let xx: Vec<f64> = (0..4)
+            .map(|k| 1.25 + 0.75 * (std::f64::consts::PI * (k as f64 + 0.5) / 4.0).cos())
+            .collect();
+        let yy: Vec<f64> = xx.iter().map(|&x| f64::exp(x)).collect();
+        let mut coeff = vec![0.0; 4];
+        poly_coeff(&xx, &yy, &mut coeff);
+        let d = Chebyshev::new(f64::exp, 0.5, 2.0, 4).unwrap().to_monomial();
+        for (v, e) in d.iter().zip(coeff.iter()) {
+            assert!((v - e).abs() < 1e-10, "d = {:?}, coeff = {:?}", d, coeff);
+        }
+
+        let back = Chebyshev::from_monomial(&expected, 0.5, 2.0).unwrap();
+        for (v, e) in back.coeffs().iter().zip(cheb.coeffs().iter()) {
+            assert!((v - e).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_economize() {
+        // Taylor series of exp to x^10, economized on [-1, 1]
+        let d: Vec<f64> = (0..11)
+            .scan(1.0, |fac, k| {
+                if k > 0 {
+                    *fac *= k as f64;
+                }
+                Some(1.0 / *fac)
+            })
+            .collect();
+        let e = Chebyshev::economize(&d, -1.0, 1.0, 1e-6).unwrap();
+        assert!(e.len() < d.len(), "e = {:?}", e);
+        for x in [-1.0, -0.5, 0.2, 1.0] {
+            let y = e.iter().rev().fold(0.0, |acc, c| acc * x + c);
+            assert!((y - f64::exp(x)).abs() < 2e-6, "x = {}, y = {}", x, y);
+        }
+    }
+}
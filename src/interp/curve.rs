@@ -0,0 +1,508 @@
+use anyhow::Result;
+
+use super::{
+    interp1d::{
+        hermite_1d::Hermite1D,
+        ppoly::PiecewisePoly,
+        spline1d::{Spline1D, SplineBoundary},
+        Interp,
+    },
+    Extrapolation, InterpError,
+};
+use crate::Matrix;
+
+/// Parameter increments between consecutive points.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Parametrization {
+    /// Unit increments.
+    Uniform,
+    /// Distance between the points.
+    #[default]
+    ChordLength,
+    /// Square root of the distance, which avoids cusps and self-intersections of Catmull-Rom
+    /// curves.
+    Centripetal,
+}
+
+impl Parametrization {
+    fn exponent(&self) -> f64 {
+        match self {
+            Parametrization::Uniform => 0.0,
+            Parametrization::ChordLength => 1.0,
+            Parametrization::Centripetal => 0.5,
+        }
+    }
+}
+
+/// Interpolation of each coordinate against the parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CurveMethod {
+    /// C2 cubic spline, natural for open curves and periodic for closed ones.
+    #[default]
+    Spline,
+    /// C1 cubic Hermite with the slopes of the parabolas through three consecutive points.
+    CatmullRom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CurveOptions {
+    pub parametrization: Parametrization,
+    pub method: CurveMethod,
+    /// Join the last point back to the first one.
+    pub closed: bool,
+}
+
+/// Parametric curve through an ordered sequence of points in any dimension.
+#[derive(Debug, Clone)]
+pub struct ParametricCurve {
+    closed: bool,
+    /// parameter of each point, the first point repeated at the end for a closed curve
+    knots: Vec<f64>,
+    coords: Vec<PiecewisePoly<f64>>,
+    /// arc length from the start to each knot
+    arc: Vec<f64>,
+}
+
+impl ParametricCurve {
+    /// Open cubic spline curve with the chord-length parametrization.
+    pub fn new(pts: &Matrix<f64>) -> Result<Self> {
+        Self::with_options(pts, CurveOptions::default())
+    }
+
+    /// `pts` holds a point in each row. \[n_point, n_dimension\]
+    pub fn with_options(pts: &Matrix<f64>, opts: CurveOptions) -> Result<Self> {
+        let n = pts.rows();
+        let min_points = if opts.closed { 3 } else { 2 };
+        if n < min_points || pts.cols() == 0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let mut rows: Vec<&[f64]> = (0..n).map(|i| &pts[i]).collect();
+        if opts.closed {
+            rows.push(&pts[0]);
+        }
+        let mut knots = vec![0.0];
+        for w in rows.windows(2) {
+            let dist = w[0]
+                .iter()
+                .zip(w[1].iter())
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f64>()
+                .sqrt();
+            if dist == 0.0 {
+                return Err(InterpError::IdenticalX().into());
+            }
+            knots.push(knots[knots.len() - 1] + dist.powf(opts.parametrization.exponent()));
+        }
+
+        let extrapolation = if opts.closed {
+            // the parameter is wrapped into the period first
+            Extrapolation::Clamp
+        } else {
+            Extrapolation::Error
+        };
+        let coords = (0..pts.cols())
+            .map(|d| {
+                let ys: Vec<f64> = rows.iter().map(|r| r[d]).collect();
+                let poly = match opts.method {
+                    CurveMethod::Spline => {
+                        let boundary = if opts.closed {
+                            SplineBoundary::Periodic
+                        } else {
+                            SplineBoundary::Natural
+                        };
+                        Spline1D::with_boundary(&knots, &ys, boundary)?.to_ppoly()
+                    }
+                    CurveMethod::CatmullRom => {
+                        let dy = catmull_rom_slopes(&knots, &ys, opts.closed);
+                        Hermite1D::new(&knots, &ys, &dy)?.to_ppoly()
+                    }
+                };
+                Ok(poly.with_extrapolation(extrapolation))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut curve = Self {
+            closed: opts.closed,
+            knots,
+            coords,
+            arc: vec![0.0],
+        };
+        for j in 0..(curve.knots.len() - 1) {
+            let len = curve.arc[j] + curve.segment_length(curve.knots[j], curve.knots[j + 1]);
+            curve.arc.push(len);
+        }
+        Ok(curve)
+    }
+
+    pub fn dim(&self) -> usize {
+        self.coords.len()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Parameter of each point, with the first point repeated at the end for a closed curve.
+    pub fn knots(&self) -> &[f64] {
+        &self.knots
+    }
+
+    /// Range of the parameter. A closed curve is periodic over it.
+    pub fn domain(&self) -> (f64, f64) {
+        (self.knots[0], self.knots[self.knots.len() - 1])
+    }
+
+    /// Total arc length.
+    pub fn length(&self) -> f64 {
+        self.arc[self.arc.len() - 1]
+    }
+
+    /// Parameter wrapped into the period for a closed curve, checked for an open one.
+    fn param(&self, t: f64) -> Result<f64> {
+        let (lo, hi) = self.domain();
+        if self.closed {
+            Ok(lo + (t - lo).rem_euclid(hi - lo))
+        } else if t < lo || t > hi {
+            Err(InterpError::OutOfRange().into())
+        } else {
+            Ok(t)
+        }
+    }
+
+    /// Index j of the knot interval \[knots\[j\], knots\[j+1\]\] containing t.
+    fn segment(&self, t: f64) -> usize {
+        self.knots
+            .partition_point(|&k| k <= t)
+            .saturating_sub(1)
+            .min(self.knots.len() - 2)
+    }
+
+    pub fn eval(&self, t: f64) -> Result<Vec<f64>> {
+        let t = self.param(t)?;
+        self.coords.iter().map(|c| c.eval(t)).collect()
+    }
+
+    /// `eval` at each of `ts`, one point per row. \[ts.len(), n_dimension\]
+    pub fn eval_many(&self, ts: &[f64]) -> Result<Matrix<f64>> {
+        let ts = ts
+            .iter()
+            .map(|&t| self.param(t))
+            .collect::<Result<Vec<f64>>>()?;
+        let columns = self
+            .coords
+            .iter()
+            .map(|c| c.interp_many(&ts))
+            .collect::<Result<Vec<Vec<f64>>>>()?;
+        let data = (0..ts.len())
+            .flat_map(|k| columns.iter().map(move |col| col[k]))
+            .collect();
+        Ok(Matrix::new(ts.len(), self.dim(), data))
+    }
+
+    /// `order`-th derivative with respect to the parameter.
+    pub fn derivative(&self, t: f64, order: usize) -> Result<Vec<f64>> {
+        let t = self.param(t)?;
+        self.coords.iter().map(|c| c.derivative(t, order)).collect()
+    }
+
+    fn speed(&self, t: f64) -> f64 {
+        self.coords
+            .iter()
+            .map(|c| c.derivative(t, 1).unwrap().powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// Arc length between two parameters of the same knot interval, by Gauss-Legendre
+    /// quadrature on four subintervals.
+    fn segment_length(&self, a: f64, b: f64) -> f64 {
+        const NODES: [f64; 5] = [
+            0.0,
+            0.538_469_310_105_683_1,
+            -0.538_469_310_105_683_1,
+            0.906_179_845_938_664,
+            -0.906_179_845_938_664,
+        ];
+        const WEIGHTS: [f64; 5] = [
+            0.568_888_888_888_888_9,
+            0.478_628_670_499_366_5,
+            0.478_628_670_499_366_5,
+            0.236_926_885_056_189_1,
+            0.236_926_885_056_189_1,
+        ];
+        let nsub = 4;
+        let h = (b - a) / nsub as f64;
+        (0..nsub)
+            .map(|k| {
+                let mid = a + (k as f64 + 0.5) * h;
+                NODES
+                    .iter()
+                    .zip(WEIGHTS.iter())
+                    .map(|(x, w)| w * self.speed(mid + 0.5 * h * x))
+                    .sum::<f64>()
+                    * 0.5
+                    * h
+            })
+            .sum()
+    }
+
+    /// Arc length from the start of the curve to the parameter `t`.
+    pub fn arc_length(&self, t: f64) -> Result<f64> {
+        let t = self.param(t)?;
+        let j = self.segment(t);
+        Ok(self.arc[j] + self.segment_length(self.knots[j], t))
+    }
+
+    /// Parameter at the arc length `s` from the start, the inverse of `arc_length`.
+    pub fn param_at_length(&self, s: f64) -> Result<f64> {
+        let total = self.length();
+        let s = if self.closed {
+            s.rem_euclid(total)
+        } else if s < 0.0 || s > total {
+            return Err(InterpError::OutOfRange().into());
+        } else {
+            s
+        };
+        let j = self
+            .arc
+            .partition_point(|&a| a <= s)
+            .saturating_sub(1)
+            .min(self.knots.len() - 2);
+        // Newton's method on the arc length, safeguarded by bisection
+        let (mut lo, mut hi) = (self.knots[j], self.knots[j + 1]);
+        let mut t = lo + (hi - lo) * (s - self.arc[j]) / (self.arc[j + 1] - self.arc[j]);
+        for _ in 0..100 {
+            let f = self.arc[j] + self.segment_length(self.knots[j], t) - s;
+            if f.abs() <= 1e-12 * total {
+                break;
+            }
+            if f > 0.0 {
+                hi = t;
+            } else {
+                lo = t;
+            }
+            let speed = self.speed(t);
+            let next = t - f / speed;
+            t = if speed > 0.0 && next > lo && next < hi {
+                next
+            } else {
+                0.5 * (lo + hi)
+            };
+        }
+        Ok(t)
+    }
+
+    /// Point at the arc length `s` from the start.
+    pub fn eval_at_length(&self, s: f64) -> Result<Vec<f64>> {
+        self.eval(self.param_at_length(s)?)
+    }
+
+    /// `n` points equally spaced in arc length, from the start to the end of an open curve and
+    /// around a closed one without repeating the start. \[n, n_dimension\]
+    pub fn resample(&self, n: usize) -> Result<Matrix<f64>> {
+        let step = if self.closed {
+            self.length() / n as f64
+        } else {
+            self.length() / (n.max(2) - 1) as f64
+        };
+        let ts = (0..n)
+            .map(|k| self.param_at_length((k as f64 * step).min(self.length())))
+            .collect::<Result<Vec<f64>>>()?;
+        self.eval_many(&ts)
+    }
+}
+
+/// Slope at `x` of the parabola through three points.
+fn parabola_slope(t: [f64; 3], y: [f64; 3], x: f64) -> f64 {
+    (0..3)
+        .map(|i| {
+            let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+            y[i] * ((x - t[j]) + (x - t[k])) / ((t[i] - t[j]) * (t[i] - t[k]))
+        })
+        .sum()
+}
+
+/// Slopes of the (non-uniform) Catmull-Rom spline. For a closed curve the last knot is the
+/// first point repeated.
+fn catmull_rom_slopes(knots: &[f64], ys: &[f64], closed: bool) -> Vec<f64> {
+    let m = knots.len();
+    if m == 2 {
+        let slope = (ys[1] - ys[0]) / (knots[1] - knots[0]);
+        return vec![slope; 2];
+    }
+    let mut dy: Vec<f64> = (0..m)
+        .map(|i| {
+            let c = i.clamp(1, m - 2);
+            parabola_slope(
+                [knots[c - 1], knots[c], knots[c + 1]],
+                [ys[c - 1], ys[c], ys[c + 1]],
+                knots[i],
+            )
+        })
+        .collect();
+    if closed {
+        let period = knots[m - 1] - knots[0];
+        let slope = parabola_slope(
+            [knots[m - 2] - period, knots[0], knots[1]],
+            [ys[m - 2], ys[0], ys[1]],
+            knots[0],
+        );
+        dy[0] = slope;
+        dy[m - 1] = slope;
+    }
+    dy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(n: usize) -> Matrix<f64> {
+        let data = (0..n)
+            .flat_map(|i| {
+                let a = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+                vec![a.cos(), a.sin()]
+            })
+            .collect();
+        Matrix::new(n, 2, data)
+    }
+
+    fn norm(v: &[f64]) -> f64 {
+        v.iter().map(|x| x * x).sum::<f64>().sqrt()
+    }
+
+    #[test]
+    fn test_closed_curve() {
+        let pts = circle(16);
+        for method in [CurveMethod::Spline, CurveMethod::CatmullRom] {
+            let opts = CurveOptions {
+                method,
+                closed: true,
+                ..CurveOptions::default()
+            };
+            let curve = ParametricCurve::with_options(&pts, opts).unwrap();
+            let (lo, hi) = curve.domain();
+            for (i, &t) in curve.knots()[..16].iter().enumerate() {
+                let p = curve.eval(t).unwrap();
+                assert!((p[0] - pts[i][0]).abs() < 1e-12 && (p[1] - pts[i][1]).abs() < 1e-12);
+            }
+            let tol = if method == CurveMethod::Spline {
+                1e-4
+            } else {
+                2e-3
+            };
+            for k in 0..100 {
+                let t = lo + (hi - lo) * (k as f64 + 0.5) / 100.0;
+                assert!((norm(&curve.eval(t).unwrap()) - 1.0).abs() < tol);
+            }
+            // periodic, with a continuous tangent at the seam
+            let (a, b) = (curve.eval(lo + 0.1).unwrap(), curve.eval(hi + 0.1).unwrap());
+            assert!((a[0] - b[0]).abs() < 1e-12 && (a[1] - b[1]).abs() < 1e-12);
+            let (d0, d1) = (
+                curve.derivative(lo, 1).unwrap(),
+                curve.derivative(hi - 1e-9, 1).unwrap(),
+            );
+            assert!((d0[0] - d1[0]).abs() < 1e-6 && (d0[1] - d1[1]).abs() < 1e-6);
+            let ts = [lo - 0.3, lo + 0.7, hi + 0.1];
+            let many = curve.eval_many(&ts).unwrap();
+            for (k, &t) in ts.iter().enumerate() {
+                assert_eq!(many[k], curve.eval(t).unwrap()[..]);
+            }
+            let length_tol = if method == CurveMethod::Spline {
+                1e-3
+            } else {
+                1e-2
+            };
+            assert!((curve.length() - 2.0 * std::f64::consts::PI).abs() < length_tol);
+        }
+    }
+
+    #[test]
+    fn test_catmull_rom() {
+        // points along a line in 3d, unevenly spaced
+        let ts = [0.0, 0.5, 0.7, 2.0, 2.2, 3.5];
+        let data: Vec<f64> = ts
+            .iter()
+            .flat_map(|&t| vec![1.0 + t, 2.0 * t, -t])
+            .collect();
+        let pts = Matrix::new(ts.len(), 3, data);
+        for parametrization in [
+            Parametrization::Uniform,
+            Parametrization::ChordLength,
+            Parametrization::Centripetal,
+        ] {
+            let opts = CurveOptions {
+                parametrization,
+                method: CurveMethod::CatmullRom,
+                closed: false,
+            };
+            let curve = ParametricCurve::with_options(&pts, opts).unwrap();
+            let (lo, hi) = curve.domain();
+            for k in 0..=50 {
+                let p = curve.eval(lo + (hi - lo) * k as f64 / 50.0).unwrap();
+                // stays on the line
+                assert!((p[1] - 2.0 * (p[0] - 1.0)).abs() < 1e-12);
+                assert!((p[2] + (p[0] - 1.0)).abs() < 1e-12);
+            }
+            assert!(curve.eval(hi + 0.1).is_err());
+            if parametrization == Parametrization::ChordLength {
+                // monotone along the line
+                assert!((curve.length() - 3.5 * 6f64.sqrt()).abs() < 1e-10);
+            }
+        }
+        // chord length parameters on a line are the arc length
+        let curve = ParametricCurve::new(&pts).unwrap();
+        for s in [0.3, 1.7, 5.0] {
+            assert!((curve.param_at_length(s).unwrap() - s).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_arc_length() {
+        // ellipse, where the speed varies along the curve
+        let n = 24;
+        let data = (0..n)
+            .flat_map(|i| {
+                let a = 2.0 * std::f64::consts::PI * i as f64 / n as f64;
+                vec![3.0 * a.cos(), a.sin()]
+            })
+            .collect();
+        let pts = Matrix::new(n, 2, data);
+        let opts = CurveOptions {
+            parametrization: Parametrization::Centripetal,
+            closed: true,
+            ..CurveOptions::default()
+        };
+        let curve = ParametricCurve::with_options(&pts, opts).unwrap();
+        // perimeter of the ellipse with a = 3, b = 1
+        assert!((curve.length() - 13.364_893_220_555_258).abs() < 1e-3);
+        for t in [0.1, 1.3, 2.9] {
+            let s = curve.arc_length(t).unwrap();
+            assert!((curve.param_at_length(s).unwrap() - t).abs() < 1e-9);
+        }
+        let m = 40;
+        let samples = curve.resample(m).unwrap();
+        let step = curve.length() / m as f64;
+        for k in 0..m {
+            let (p, q) = (&samples[k], &samples[(k + 1) % m]);
+            let chord = norm(&[p[0] - q[0], p[1] - q[1]]);
+            // chords are slightly shorter than the arcs
+            assert!(
+                chord <= step + 1e-9 && chord > 0.9 * step,
+                "{} {}",
+                chord,
+                step
+            );
+            let t = curve.param_at_length(k as f64 * step).unwrap();
+            assert!((curve.arc_length(t).unwrap() - k as f64 * step).abs() < 1e-9);
+            let p = curve.eval(t).unwrap();
+            assert!((p[0] - samples[k][0]).abs() < 1e-12 && (p[1] - samples[k][1]).abs() < 1e-12);
+        }
+
+        let open = ParametricCurve::new(&pts).unwrap();
+        assert!(open.eval_many(&[0.5, open.domain().1 + 0.1]).is_err());
+
+        let dup = Matrix::new(3, 2, vec![0.0, 0.0, 1.0, 1.0, 1.0, 1.0]);
+        assert!(ParametricCurve::new(&dup).is_err());
+    }
+}
@@ -1,55 +1,137 @@
-use crate::accessor_impl;
+use crate::{
+    accessor_impl,
+    interp::{strictly_monotone, InterpError},
+};
+use anyhow::Result;
 
-use super::Interp;
+use super::{ppoly::factorial, Cursor, Extrapolation, Interp, InterpFloat};
 
-pub struct BaryRat1D<'a> {
-    n: usize,
+/// Weights of the barycentric rational interpolant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaryWeights {
+    /// Floater-Hormann weights (`calc_w`), blending the interpolating polynomials of degree d
+    /// through d + 1 consecutive points. Free of real poles, with approximation order d + 1.
+    /// d is reduced to n - 1 for n points.
+    FloaterHormann(usize),
+    /// Berrut's weights (-1)^i, the Floater-Hormann weights with d = 0.
+    Berrut,
+    /// Weights of the interpolating polynomial of degree n - 1 (`calc_w2`).
+    Polynomial,
+}
+
+/// Barycentric rational interpolation
+/// (Section 3.4.1 of Numerical Recipes in C)
+#[derive(Debug, Clone)]
+pub struct BaryRat1D<T>
+where
+    T: InterpFloat,
+{
     mm: usize,
-    jsav: usize,
-    dj: usize,
-    cor: usize,
-    xx: &'a Vec<f64>,
-    yy: &'a Vec<f64>,
-    w: Vec<f64>,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    xx: Vec<T>,
+    yy: Vec<T>,
+    w: Vec<T>,
+    weights: BaryWeights,
 }
 
-impl<'a> BaryRat1D<'a> {
-    pub fn new(xx: &'a Vec<f64>, yy: &'a Vec<f64>, mm: usize, calc: usize) -> Self {
+impl<T> BaryRat1D<T>
+where
+    T: InterpFloat,
+{
+    /// `xx` must be strictly monotone. If the weights are not finite, nonzero and of
+    /// alternating sign (e.g. polynomial weights overflowing for many points),
+    /// Berrut's weights are used instead, see `weights`.
+    pub fn new(xx: &[T], yy: &[T], weights: BaryWeights) -> Result<Self> {
         let n = xx.len();
-        let w = match calc {
-            1 => BaryRat1D::calc_w(xx, mm),
-            _ => BaryRat1D::calc_w2(xx, mm),
+        if n == 0 || yy.len() != n {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if !strictly_monotone(xx) {
+            return Err(InterpError::IdenticalX().into());
+        }
+        let mut w = match weights {
+            BaryWeights::FloaterHormann(d) => BaryRat1D::calc_w(xx, d.min(n - 1)),
+            BaryWeights::Berrut => BaryRat1D::calc_w(xx, 0),
+            BaryWeights::Polynomial => BaryRat1D::calc_w2(xx),
         };
-        Self {
-            n,
-            mm,
-            jsav: 0,
-            dj: 0,
-            cor: 0,
-            xx,
-            yy,
+        let mut weights = weights;
+        if !valid_weights(&w) {
+            weights = BaryWeights::Berrut;
+            w = BaryRat1D::calc_w(xx, 0);
+        }
+        Ok(Self {
+            mm: 2,
+            cursor: Cursor::new(n),
+            extrapolation: Extrapolation::default(),
+            xx: xx.to_vec(),
+            yy: yy.to_vec(),
             w,
+            weights,
+        })
+    }
+
+    /// Floater-Hormann interpolant with the degree selected by `select_order`.
+    pub fn cross_validated(xx: &[T], yy: &[T], max_d: usize) -> Result<Self> {
+        let (d, _) = BaryRat1D::select_order(xx, yy, max_d)?;
+        BaryRat1D::new(xx, yy, BaryWeights::FloaterHormann(d))
+    }
+
+    /// Floater-Hormann degree d in 0..=max_d minimizing the leave-one-out error at the interior
+    /// points, as (d, root mean square error).
+    pub fn select_order(xx: &[T], yy: &[T], max_d: usize) -> Result<(usize, T)> {
+        let n = xx.len();
+        if n < 3 || yy.len() != n {
+            return Err(InterpError::SizeNotMatch().into());
         }
+        let mut best: Option<(usize, T)> = None;
+        for d in 0..=max_d.min(n - 2) {
+            let mut sum = T::zero();
+            for k in 1..(n - 1) {
+                let xs: Vec<T> = (0..n).filter(|&i| i != k).map(|i| xx[i]).collect();
+                let ys: Vec<T> = (0..n).filter(|&i| i != k).map(|i| yy[i]).collect();
+                let w = BaryRat1D::calc_w(&xs, d);
+                let err = barycentric(&xs, &ys, &w, xx[k]) - yy[k];
+                sum = sum + err * err;
+            }
+            let rms = (sum / T::from_usize(n - 2).unwrap()).sqrt();
+            let better = match best {
+                Some((_, e)) => rms < e,
+                None => true,
+            };
+            if better {
+                best = Some((d, rms));
+            }
+        }
+        best.ok_or_else(|| InterpError::NotConverged().into())
+    }
+
+    /// Scheme of the weights in use.
+    pub fn weights(&self) -> BaryWeights {
+        self.weights
     }
 
-    fn calc_w(xx: &Vec<f64>, mm: usize) -> Vec<f64> {
+    pub fn w(&self) -> &[T] {
+        &self.w
+    }
+
+    fn calc_w(xx: &[T], d: usize) -> Vec<T> {
         let n = xx.len();
-        let mut w = vec![0.0; n];
+        let mut w = vec![T::zero(); n];
         for k in 0..n {
-            let mini = if k < mm { 0 } else { k - mm };
-            let maxi = std::cmp::min(n - mm, k);
-            // println!("k = {}, mini = {}, maxi = {}", k, mini, maxi);
-            let mut sum = 0.0;
-            let mut temp = if (mini & 1) == 1 { -1.0 } else { 1.0 };
-            for i in mini..maxi {
-                let maxj = std::cmp::min(i + mm, n - 1);
-                let mut term = 1.0;
+            let mini = k.saturating_sub(d);
+            let maxi = std::cmp::min(n - 1 - d, k);
+            let mut sum = T::zero();
+            let mut temp = if (mini & 1) == 1 { -T::one() } else { T::one() };
+            for i in mini..=maxi {
+                let maxj = std::cmp::min(i + d, n - 1);
+                let mut term = T::one();
                 for j in i..=maxj {
                     if j != k {
-                        term *= xx[k] - xx[j];
+                        term = term * (xx[k] - xx[j]);
                     }
                 }
-                sum += temp / term;
+                sum = sum + temp / term;
                 temp = -temp;
             }
             w[k] = sum;
@@ -57,45 +139,217 @@ impl<'a> BaryRat1D<'a> {
         w
     }
 
-    fn calc_w2(xx: &Vec<f64>, _mm: usize) -> Vec<f64> {
+    /// `order`-th derivative at `x` by the recurrence of the divided differences
+    /// r\[x, ..., x\] (Schneider and Werner), which also holds at the nodes.
+    pub fn derivative(&self, x: T, order: usize) -> anyhow::Result<T> {
+        self.check_range(x)?;
+        // a node within rounding errors is taken as the node itself to avoid the cancellation
+        // in the divided differences
+        let tol = T::epsilon() * T::lit(16.0);
+        let node =
+            (0..self.n()).find(|&i| (x - self.xx[i]).abs() <= tol * x.abs().max(self.xx[i].abs()));
+        let x = node.map_or(x, |j| self.xx[j]);
+        let mut r = self.rawinterp(self.locate(x), x)?;
+        // g[i] = r[xx[i], x, ..., x]
+        let mut g = self.yy.clone();
+        for _ in 0..order {
+            let mut nume = T::zero();
+            let mut deno = T::zero();
+            for (i, gi) in g.iter_mut().enumerate() {
+                if Some(i) == node {
+                    continue;
+                }
+                let dx = x - self.xx[i];
+                *gi = (r - *gi) / dx;
+                match node {
+                    Some(_) => nume = nume + self.w[i] * *gi,
+                    None => {
+                        nume = nume + self.w[i] / dx * *gi;
+                        deno = deno + self.w[i] / dx;
+                    }
+                }
+            }
+            r = match node {
+                Some(j) => -nume / self.w[j],
+                None => nume / deno,
+            };
+        }
+        Ok(r * factorial::<T>(order))
+    }
+
+    /// Antiderivative which is zero at xx\[0\], by Gauss-Legendre quadrature between the nodes.
+    pub fn antiderivative(&self) -> BaryRatAntiderivative<T> {
+        let mut cum = vec![T::zero(); self.n()];
+        for i in 1..self.n() {
+            cum[i] = cum[i - 1] + self.quadrature(self.xx[i - 1], self.xx[i]);
+        }
+        BaryRatAntiderivative {
+            mm: 2,
+            cursor: Cursor::new(self.n()),
+            extrapolation: self.extrapolation,
+            base: self.clone(),
+            cum,
+        }
+    }
+
+    /// Definite integral over \[a, b\]. Outside the data range the interpolant is continued
+    /// naturally.
+    pub fn integral(&self, a: T, b: T) -> Result<T> {
+        self.check_range(a)?;
+        self.check_range(b)?;
+        let anti = self.antiderivative();
+        Ok(anti.rawinterp(anti.locate(b), b)? - anti.rawinterp(anti.locate(a), a)?)
+    }
+
+    /// 8 point Gauss-Legendre rule over \[a, b\], which must not contain a node in its interior.
+    fn quadrature(&self, a: T, b: T) -> T {
+        const NODES: [f64; 4] = [
+            0.183_434_642_495_649_8,
+            0.525_532_409_916_329,
+            0.796_666_477_413_626_7,
+            0.960_289_856_497_536_3,
+        ];
+        const WEIGHTS: [f64; 4] = [
+            0.362_683_783_378_362,
+            0.313_706_645_877_887_3,
+            0.222_381_034_453_374_5,
+            0.101_228_536_290_376_3,
+        ];
+        let mid = (a + b) * T::lit(0.5);
+        let half = (b - a) * T::lit(0.5);
+        let f = |x: T| barycentric(&self.xx, &self.yy, &self.w, x);
+        NODES
+            .iter()
+            .zip(WEIGHTS.iter())
+            .fold(T::zero(), |acc, (&x, &w)| {
+                let dx = half * T::lit(x);
+                acc + T::lit(w) * (f(mid - dx) + f(mid + dx))
+            })
+            * half
+    }
+
+    fn calc_w2(xx: &[T]) -> Vec<T> {
         let n = xx.len();
-        let mut w = vec![0.0; n];
+        let mut w = vec![T::zero(); n];
         for i in 0..n {
-            let mut term = 1.0;
+            let mut term = T::one();
             for j in 0..n {
                 if i != j {
-                    term *= xx[i] - xx[j];
+                    term = term * (xx[i] - xx[j]);
                 }
             }
-            w[i] = 1.0 / term;
+            w[i] = T::one() / term;
         }
         w
     }
 }
 
-impl<'a> Interp for BaryRat1D<'a> {
-    accessor_impl!((get = n, set = set_n): usize);
-    accessor_impl!((get = mm, set = set_mm): usize);
-    accessor_impl!((get = jsav, set = set_jsav): usize);
-    accessor_impl!((get = dj, set = set_dj): usize);
-    accessor_impl!((get = cor, set = set_cor): usize);
+/// Finite, nonzero and alternating in sign.
+fn valid_weights<T>(w: &[T]) -> bool
+where
+    T: InterpFloat,
+{
+    w.iter().all(|v| v.is_finite() && !v.is_zero())
+        && w.windows(2)
+            .all(|p| p[0].is_sign_positive() != p[1].is_sign_positive())
+}
+
+/// Second (true) form of the barycentric formula. If it overflows next to a node, the sums
+/// are multiplied through by the distance to the nearest node as in the first form.
+fn barycentric<T>(xx: &[T], yy: &[T], w: &[T], x: T) -> T
+where
+    T: InterpFloat,
+{
+    let mut nume = T::zero();
+    let mut deno = T::zero();
+    for i in 0..xx.len() {
+        if x == xx[i] {
+            return yy[i];
+        }
+        let tmp = w[i] / (x - xx[i]);
+        nume = nume + tmp * yy[i];
+        deno = deno + tmp;
+    }
+    let r = nume / deno;
+    if r.is_finite() {
+        return r;
+    }
+    let k = (0..xx.len())
+        .min_by(|&i, &j| (x - xx[i]).abs().total_order(&(x - xx[j]).abs()))
+        .unwrap();
+    let dx = x - xx[k];
+    let mut nume = w[k] * yy[k];
+    let mut deno = w[k];
+    for i in (0..xx.len()).filter(|&i| i != k) {
+        let tmp = w[i] * (dx / (x - xx[i]));
+        nume = nume + tmp * yy[i];
+        deno = deno + tmp;
+    }
+    nume / deno
+}
+
+/// Antiderivative of a `BaryRat1D`, given by the integrals up to each node and a quadrature
+/// from the node on the left.
+#[derive(Debug, Clone)]
+pub struct BaryRatAntiderivative<T>
+where
+    T: InterpFloat,
+{
+    mm: usize,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    base: BaryRat1D<T>,
+    /// integral from xx\[0\] to each node
+    cum: Vec<T>,
+}
+
+impl<T> Interp<T> for BaryRatAntiderivative<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
+
+    fn xx(&self) -> &[T] {
+        &self.base.xx
+    }
+
+    fn rawinterp(&self, jlo: usize, x: T) -> Result<T> {
+        let r = self.cum[jlo] + self.base.quadrature(self.base.xx[jlo], x);
+        if r.is_finite() {
+            Ok(r)
+        } else {
+            Err(InterpError::Pole().into())
+        }
+    }
+}
+
+impl<T> Interp<T> for BaryRat1D<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
 
-    fn xx(&self) -> &Vec<f64> {
-        self.xx
+    fn xx(&self) -> &[T] {
+        &self.xx
     }
 
-    fn rawinterp(&self, _jlo: usize, x: f64) -> anyhow::Result<f64> {
-        let mut nume = 0.0;
-        let mut deno = 0.0;
-        for i in 0..self.n {
-            if (x - self.xx[i]).abs() < 1e-7 {
+    fn rawinterp(&self, jlo: usize, x: T) -> anyhow::Result<T> {
+        // x can only coincide with one of the bracketing nodes
+        for i in jlo..std::cmp::min(jlo + 2, self.n()) {
+            if x == self.xx[i] {
                 return Ok(self.yy[i]);
             }
-            let tmp = self.w[i] / (x - self.xx[i]);
-            nume += tmp * self.yy[i];
-            deno += tmp;
         }
-        Ok(nume / deno)
+        let r = barycentric(&self.xx, &self.yy, &self.w, x);
+        if r.is_finite() || x.is_nan() {
+            Ok(r)
+        } else {
+            Err(InterpError::Pole().into())
+        }
     }
 }
 
@@ -103,17 +357,155 @@ impl<'a> Interp for BaryRat1D<'a> {
 mod tests {
     use super::*;
 
+    fn runge(x: f64) -> f64 {
+        1.0 / (1.0 + 25.0 * x * x)
+    }
+
     #[test]
     fn test_linear_interp() {
-        let xx = vec![1.0, 2.0, 3.0, 5.0, 7.0, 10.0];
+        let xx: Vec<f64> = vec![1.0, 2.0, 3.0, 5.0, 7.0, 10.0];
         let yy = vec![1.0, 4.0, 9.0, 25.0, 49.0, 100.0];
-        let mut by1d = BaryRat1D::new(&xx, &yy, 2, 1);
-        let res = by1d.interp(1.5);
-        assert!(res.is_ok());
-        // assert!((res - 2.5).abs() < 1e-5, "res = {}", res);
+        let mut by1d = BaryRat1D::new(&xx, &yy, BaryWeights::FloaterHormann(2)).unwrap();
+        // Floater-Hormann interpolants reproduce polynomials of degree d
+        let res = by1d.interp(1.5).unwrap();
+        assert!((res - 2.25).abs() < 1e-12, "res = {}", res);
+        for (&x, &y) in xx.iter().zip(yy.iter()) {
+            assert_eq!(by1d.interp(x).unwrap(), y);
+        }
 
-        let mut by1d = BaryRat1D::new(&xx, &yy, 2, 2);
+        let mut by1d = BaryRat1D::new(&xx, &yy, BaryWeights::Polynomial).unwrap();
         let res = by1d.interp(1.5);
         assert!(res.is_ok());
+
+        // with the polynomial weights the interpolant is the polynomial x^2
+        for x in [1.5, 3.0, 8.0] {
+            let res = by1d.derivative(x, 1).unwrap();
+            assert!((res - 2.0 * x).abs() < 1e-8, "x = {}, res = {}", x, res);
+            let res = by1d.derivative(x, 2).unwrap();
+            assert!((res - 2.0).abs() < 1e-6, "x = {}, res = {}", x, res);
+        }
+    }
+
+    #[test]
+    fn test_bary_weights() {
+        let xx: Vec<f64> = (0..6).map(|i| i as f64).collect();
+        let yy = vec![0.0; 6];
+        let berrut = BaryRat1D::new(&xx, &yy, BaryWeights::Berrut).unwrap();
+        assert_eq!(berrut.w(), &[1.0, -1.0, 1.0, -1.0, 1.0, -1.0]);
+        // equispaced weights with d = 1 are proportional to 1, 2, ..., 2, 1
+        let fh = BaryRat1D::new(&xx, &yy, BaryWeights::FloaterHormann(1)).unwrap();
+        let scale = fh.w()[0];
+        for (i, w) in fh.w().iter().enumerate() {
+            let expected = if i == 0 || i == 5 { 1.0 } else { 2.0 };
+            assert!((w / scale - expected * (-1f64).powi(i as i32)).abs() < 1e-12);
+        }
+        // the largest degree is the polynomial
+        let fh = BaryRat1D::new(&xx, &yy, BaryWeights::FloaterHormann(10)).unwrap();
+        let poly = BaryRat1D::new(&xx, &yy, BaryWeights::Polynomial).unwrap();
+        let ratio = fh.w()[0] / poly.w()[0];
+        for (a, b) in fh.w().iter().zip(poly.w().iter()) {
+            assert!((a / b - ratio).abs() < 1e-12);
+        }
+
+        // polynomial weights underflow for many points
+        let n = 2000;
+        let xx: Vec<f64> = (0..n)
+            .map(|i| -1.0 + 2.0 * i as f64 / (n - 1) as f64)
+            .collect();
+        let yy: Vec<f64> = xx.iter().map(|&x| runge(x)).collect();
+        let bary = BaryRat1D::new(&xx, &yy, BaryWeights::Polynomial).unwrap();
+        assert_eq!(bary.weights(), BaryWeights::Berrut);
+
+        let bad = vec![0.0, 2.0, 1.0];
+        assert!(BaryRat1D::new(&bad, &[0.0; 3], BaryWeights::Berrut).is_err());
+        assert!(BaryRat1D::new(&xx, &yy[1..], BaryWeights::Berrut).is_err());
+    }
+
+    #[test]
+    fn test_bary_runge() {
+        let n = 41;
+        let xx: Vec<f64> = (0..n)
+            .map(|i| -1.0 + 2.0 * i as f64 / (n - 1) as f64)
+            .collect();
+        let yy: Vec<f64> = xx.iter().map(|&x| runge(x)).collect();
+        let max_error = |bary: &BaryRat1D<f64>| {
+            (0..1000)
+                .map(|i| {
+                    let x = -1.0 + 2.0 * (i as f64 + 0.5) / 1000.0;
+                    (bary.eval(x).unwrap() - runge(x)).abs()
+                })
+                .fold(0.0, f64::max)
+        };
+        // Runge's phenomenon
+        let poly = BaryRat1D::new(&xx, &yy, BaryWeights::Polynomial).unwrap();
+        assert!(max_error(&poly) > 1.0);
+        let fh = BaryRat1D::new(&xx, &yy, BaryWeights::FloaterHormann(3)).unwrap();
+        assert!(max_error(&fh) < 1e-3, "{}", max_error(&fh));
+
+        let (d, cv) = BaryRat1D::select_order(&xx, &yy, 10).unwrap();
+        assert!(d > 0 && d < 10, "d = {}", d);
+        let berrut = BaryRat1D::new(&xx, &yy, BaryWeights::Berrut).unwrap();
+        let cv_berrut = BaryRat1D::select_order(&xx, &yy, 0).unwrap().1;
+        assert!(cv < cv_berrut);
+        let best = BaryRat1D::cross_validated(&xx, &yy, 10).unwrap();
+        assert_eq!(best.weights(), BaryWeights::FloaterHormann(d));
+        assert!(max_error(&best) < max_error(&berrut));
+
+        // derivatives
+        let drunge = |x: f64| -50.0 * x * runge(x) * runge(x);
+        let d2runge = |x: f64| (3750.0 * x * x - 50.0) * runge(x).powi(3);
+        for x in [-0.73, -0.2, 0.0, 0.41, 0.9] {
+            let d1 = fh.derivative(x, 1).unwrap();
+            let d2 = fh.derivative(x, 2).unwrap();
+            assert!((d1 - drunge(x)).abs() < 2e-2, "x = {}, d1 = {}", x, d1);
+            assert!((d2 - d2runge(x)).abs() < 1.0, "x = {}, d2 = {}", x, d2);
+        }
+
+        // on and right next to a node, where the second form overflows
+        assert_eq!(fh.eval(xx[7]).unwrap(), yy[7]);
+        let tiny = f64::from_bits(1);
+        assert!((fh.eval(tiny).unwrap() - 1.0).abs() < 1e-12);
+        assert!(fh.eval(f64::NAN).unwrap().is_nan());
+
+        // integral of the Runge function over [-1, 1] is 2 atan(5) / 5
+        let exact = 0.4 * 5.0f64.atan();
+        let res = fh.integral(-1.0, 1.0).unwrap();
+        assert!((res - exact).abs() < 1e-4, "res = {}", res);
+        let anti = fh.antiderivative();
+        assert_eq!(anti.eval(-1.0).unwrap(), 0.0);
+        for x in [-0.55, 0.0, 0.37] {
+            let res = anti.eval(x).unwrap();
+            let exact = 0.2 * ((5.0 * x).atan() + 5.0f64.atan());
+            assert!((res - exact).abs() < 1e-4, "x = {}, res = {}", x, res);
+        }
+    }
+
+    #[test]
+    fn test_bary_integral() {
+        // cubics are reproduced with d = 3, and integrated exactly
+        let xx: Vec<f64> = vec![3.0, 2.5, 1.0, 0.2, -0.5, -2.0];
+        let func = |x: f64| x * x * x - 2.0 * x + 1.0;
+        let prim = |x: f64| 0.25 * x.powi(4) - x * x + x;
+        let yy: Vec<f64> = xx.iter().map(|&x| func(x)).collect();
+        let bary = BaryRat1D::new(&xx, &yy, BaryWeights::FloaterHormann(3)).unwrap();
+        for (a, b) in [(-2.0, 3.0), (0.7, -1.3), (0.3, 0.4), (2.5, 2.5)] {
+            let res = bary.integral(a, b).unwrap();
+            assert!((res - (prim(b) - prim(a))).abs() < 1e-12, "res = {}", res);
+        }
+        let anti = bary.antiderivative();
+        for x in [3.0, 1.7, -0.5, -1.9] {
+            let res = anti.eval(x).unwrap();
+            assert!((res - (prim(x) - prim(3.0))).abs() < 1e-12, "res = {}", res);
+        }
+        let bary = bary.with_extrapolation(Extrapolation::Error);
+        assert!(bary.integral(0.0, 3.5).is_err());
+        assert!(bary.antiderivative().eval(-2.5).is_err());
+        let bary = bary.with_extrapolation(Extrapolation::Natural);
+        let res = bary.integral(-2.5, 3.5).unwrap();
+        assert!(
+            (res - (prim(3.5) - prim(-2.5))).abs() < 1e-10,
+            "res = {}",
+            res
+        );
     }
 }
@@ -0,0 +1,323 @@
+use anyhow::Result;
+
+use crate::{accessor_impl, interp::InterpError, linalg::banddiagonal::BandMatrix, MatLinAlgBound};
+
+use super::{Cursor, Extrapolation, Interp, InterpFloat};
+
+/// Spline of the given degree in the B-spline basis:
+/// s(x) = sum_i coeffs\[i\] B_i,p(x) on the knot vector `knots`.
+/// The domain is \[knots\[p\], knots\[n\]\], n being the number of coefficients.
+#[derive(Debug, Clone)]
+pub struct BSpline<T>
+where
+    T: InterpFloat,
+{
+    mm: usize,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    degree: usize,
+    knots: Vec<T>,
+    coeffs: Vec<T>,
+}
+
+impl<T> BSpline<T>
+where
+    T: InterpFloat,
+{
+    /// `knots` must be non-decreasing and have coeffs.len() + degree + 1 elements.
+    pub fn new(degree: usize, knots: &[T], coeffs: &[T]) -> Result<Self> {
+        if coeffs.len() < degree + 1 || knots.len() != coeffs.len() + degree + 1 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if knots.windows(2).any(|w| w[1] < w[0]) || knots[degree] == knots[coeffs.len()] {
+            return Err(InterpError::IdenticalX().into());
+        }
+        Ok(Self {
+            mm: 2,
+            cursor: Cursor::new(coeffs.len() - degree + 1),
+            extrapolation: Extrapolation::default(),
+            degree,
+            knots: knots.to_vec(),
+            coeffs: coeffs.to_vec(),
+        })
+    }
+
+    /// Clamped knot vector: the ends of `breaks` repeated degree + 1 times, the inner breaks once.
+    pub fn clamped_knots(degree: usize, breaks: &[T]) -> Vec<T> {
+        let n = breaks.len();
+        let mut knots = vec![breaks[0]; degree];
+        knots.extend_from_slice(breaks);
+        knots.resize(n + 2 * degree, breaks[n - 1]);
+        knots
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    pub fn knots(&self) -> &[T] {
+        &self.knots
+    }
+
+    pub fn coeffs(&self) -> &[T] {
+        &self.coeffs
+    }
+
+    /// Index k of the knot span knots\[k\] <= x < knots\[k+1\] containing `x`, within the domain.
+    pub fn span(&self, x: T) -> usize {
+        self.locate(x) + self.degree
+    }
+
+    /// Values of the degree + 1 basis functions B_k-p,p .. B_k,p which are non-zero on the span `k`.
+    pub fn basis(&self, k: usize, x: T) -> Vec<T> {
+        basis_funs(&self.knots, self.degree, k, x)
+    }
+
+    /// Spline of the derivative, of degree p - 1 on the knots without the first and the last ones.
+    pub fn derivative_spline(&self) -> Result<Self> {
+        let p = self.degree;
+        if p == 0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let n = self.coeffs.len();
+        let pt = T::from_usize(p).unwrap();
+        let coeffs: Vec<T> = (0..(n - 1))
+            .map(|i| {
+                let dt = self.knots[i + p + 1] - self.knots[i + 1];
+                if dt == T::zero() {
+                    T::zero()
+                } else {
+                    pt * (self.coeffs[i + 1] - self.coeffs[i]) / dt
+                }
+            })
+            .collect();
+        let mut obj = BSpline::new(p - 1, &self.knots[1..(self.knots.len() - 1)], &coeffs)?;
+        obj.extrapolation = self.extrapolation;
+        Ok(obj)
+    }
+
+    /// `order`-th derivative at `x`.
+    pub fn derivative(&self, x: T, order: usize) -> Result<T> {
+        self.check_range(x)?;
+        if order > self.degree {
+            return Ok(T::zero());
+        }
+        let mut spl = self.clone();
+        for _ in 0..order {
+            spl = spl.derivative_spline()?;
+        }
+        spl.rawinterp(spl.locate(x), x)
+    }
+
+    /// Insert the knot `x` by Boehm's algorithm, keeping the curve unchanged.
+    pub fn insert_knot(&mut self, x: T) -> Result<()> {
+        let p = self.degree;
+        let n = self.coeffs.len();
+        if x < self.knots[p] || x > self.knots[n] {
+            return Err(InterpError::OutOfRange().into());
+        }
+        // span with knots[k] <= x < knots[k+1], so that x is inserted after the existing equal knots
+        let mut k = self.span(x);
+        while k < n - 1 && self.knots[k + 1] <= x {
+            k += 1;
+        }
+        let t = &self.knots;
+        let mut coeffs = Vec::with_capacity(n + 1);
+        coeffs.extend_from_slice(&self.coeffs[..=(k - p)]);
+        for i in (k - p + 1)..=k {
+            let a = (x - t[i]) / (t[i + p] - t[i]);
+            coeffs.push((T::one() - a) * self.coeffs[i - 1] + a * self.coeffs[i]);
+        }
+        coeffs.extend_from_slice(&self.coeffs[k..]);
+        self.knots.insert(k + 1, x);
+        self.coeffs = coeffs;
+        self.cursor = Cursor::new(self.coeffs.len() - p + 1);
+        Ok(())
+    }
+}
+
+impl<T> BSpline<T>
+where
+    T: InterpFloat + MatLinAlgBound,
+{
+    /// Weighted least-squares fit of the coefficients to the data (xx\[i\], yy\[i\]) on the given knots.
+    /// The normal equations are banded with the bandwidth `degree` and solved by the banded Cholesky
+    /// decomposition, which fails unless every basis function is supported by enough data
+    /// (Schoenberg-Whitney). Every xx\[i\] must lie in the domain.
+    pub fn fit(degree: usize, knots: &[T], xx: &[T], yy: &[T], w: Option<&[T]>) -> Result<Self> {
+        if xx.len() != yy.len() || w.is_some_and(|w| w.len() != xx.len()) {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let n = knots.len().saturating_sub(degree + 1);
+        let mut spl = BSpline::new(degree, knots, &vec![T::zero(); n])?;
+        let (lo, hi) = (knots[degree], knots[n]);
+        if xx.iter().any(|&x| x < lo || x > hi) {
+            return Err(InterpError::OutOfRange().into());
+        }
+        let mut ata = BandMatrix::new(n, degree, degree);
+        let mut aty = vec![T::zero(); n];
+        for (i, (&x, &y)) in xx.iter().zip(yy.iter()).enumerate() {
+            let wi = w.map_or(T::one(), |w| w[i]);
+            let k = spl.span(x);
+            let b = spl.basis(k, x);
+            for (r, &br) in b.iter().enumerate() {
+                let row = k - degree + r;
+                aty[row] += wi * br * y;
+                for (c, &bc) in b.iter().enumerate() {
+                    let col = k - degree + c;
+                    ata.set(row, col, ata.get(row, col) + wi * br * bc)?;
+                }
+            }
+        }
+        let mut coeffs = vec![T::zero(); n];
+        ata.cholesky()?.solve(&aty, &mut coeffs)?;
+        spl.coeffs = coeffs;
+        Ok(spl)
+    }
+}
+
+impl<T> Interp<T> for BSpline<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
+
+    /// Knots spanning the domain.
+    fn xx(&self) -> &[T] {
+        &self.knots[self.degree..=self.coeffs.len()]
+    }
+
+    /// de Boor's algorithm on the span j + degree.
+    fn rawinterp(&self, j: usize, x: T) -> Result<T> {
+        let p = self.degree;
+        let k = j + p;
+        let t = &self.knots;
+        let mut d: Vec<T> = self.coeffs[(k - p)..=k].to_vec();
+        for r in 1..=p {
+            for i in (r..=p).rev() {
+                let lo = t[i + k - p];
+                let hi = t[i + 1 + k - r];
+                let a = if hi == lo {
+                    T::zero()
+                } else {
+                    (x - lo) / (hi - lo)
+                };
+                d[i] = (T::one() - a) * d[i - 1] + a * d[i];
+            }
+        }
+        Ok(d[p])
+    }
+}
+
+/// Cox-de Boor recurrence for the non-zero basis functions on the span `k`.
+fn basis_funs<T>(t: &[T], p: usize, k: usize, x: T) -> Vec<T>
+where
+    T: InterpFloat,
+{
+    let mut n = vec![T::zero(); p + 1];
+    let mut left = vec![T::zero(); p + 1];
+    let mut right = vec![T::zero(); p + 1];
+    n[0] = T::one();
+    for j in 1..=p {
+        left[j] = x - t[k + 1 - j];
+        right[j] = t[k + j] - x;
+        let mut saved = T::zero();
+        for r in 0..j {
+            let tmp = n[r] / (right[r + 1] + left[j - r]);
+            n[r] = saved + right[r + 1] * tmp;
+            saved = left[j - r] * tmp;
+        }
+        n[j] = saved;
+    }
+    n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bspline_eval() {
+        // cubic Bezier curve: Bernstein polynomials
+        let knots: Vec<f64> = vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+        let c = [1.0, -2.0, 3.0, 0.5];
+        let bez = BSpline::new(3, &knots, &c).unwrap();
+        for x in [0.0, 0.3, 0.75, 1.0] {
+            let u = 1.0 - x;
+            let expect = c[0] * u * u * u
+                + 3.0 * c[1] * x * u * u
+                + 3.0 * c[2] * x * x * u
+                + c[3] * x * x * x;
+            assert!((bez.eval(x).unwrap() - expect).abs() < 1e-12, "x = {}", x);
+        }
+
+        // partition of unity
+        let knots = BSpline::clamped_knots(3, &[0.0, 0.5, 2.0, 2.5, 4.0]);
+        let spl = BSpline::new(3, &knots, &[1.0; 7]).unwrap();
+        for x in [0.1, 0.5, 1.7, 3.99] {
+            let k = spl.span(x);
+            let b = spl.basis(k, x);
+            assert!((b.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            assert!((spl.eval(x).unwrap() - 1.0).abs() < 1e-12);
+        }
+
+        assert!(BSpline::new(3, &knots, &[1.0; 6]).is_err());
+        assert!(BSpline::new(2, &[0.0, 1.0, 0.5, 2.0, 3.0, 4.0], &[1.0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_bspline_fit() {
+        // a cubic spline space reproduces cubic polynomials
+        let p = |x: f64| 0.5 * x * x * x - x * x + 2.0;
+        let xx: Vec<f64> = (0..41).map(|i| i as f64 * 0.1).collect();
+        let yy: Vec<f64> = xx.iter().map(|&x| p(x)).collect();
+        let knots = BSpline::clamped_knots(3, &[0.0, 0.7, 1.5, 2.0, 3.1, 4.0]);
+        let spl = BSpline::fit(3, &knots, &xx, &yy, None).unwrap();
+        for x in [0.05, 1.0, 2.55, 3.95] {
+            assert!((spl.eval(x).unwrap() - p(x)).abs() < 1e-10, "x = {}", x);
+            let dp = 1.5 * x * x - 2.0 * x;
+            assert!((spl.derivative(x, 1).unwrap() - dp).abs() < 1e-9);
+            assert!((spl.derivative(x, 2).unwrap() - (3.0 * x - 2.0)).abs() < 1e-8);
+            assert!((spl.derivative(x, 3).unwrap() - 3.0).abs() < 1e-7);
+            assert!(spl.derivative(x, 4).unwrap().abs() < 1e-12);
+        }
+
+        // smooth data with fewer coefficients than points
+        let yy: Vec<f64> = xx.iter().map(|x| x.sin()).collect();
+        let w = vec![2.0; xx.len()];
+        let spl = BSpline::fit(3, &knots, &xx, &yy, Some(&w)).unwrap();
+        for x in [0.05, 1.0, 2.55, 3.95] {
+            assert!((spl.eval(x).unwrap() - x.sin()).abs() < 5e-3, "x = {}", x);
+        }
+
+        // not enough data in a knot span
+        let xs = [0.1, 0.2, 0.3];
+        assert!(BSpline::fit(3, &knots, &xs, &xs, None).is_err());
+
+        // data outside the domain
+        let mut xs = xx.clone();
+        xs.push(4.5);
+        let ys: Vec<f64> = xs.iter().map(|&x| p(x)).collect();
+        assert!(BSpline::fit(3, &knots, &xs, &ys, None).is_err());
+    }
+
+    #[test]
+    fn test_bspline_insert_knot() {
+        let knots = BSpline::clamped_knots(2, &[0.0, 1.0, 3.0, 4.0]);
+        let mut spl = BSpline::new(2, &knots, &[0.0, 1.0, -1.0, 2.0, 0.5]).unwrap();
+        let xs: Vec<f64> = (0..=40).map(|i| i as f64 * 0.1).collect();
+        let before = spl.interp_many(&xs).unwrap();
+        for x in [2.0, 1.0, 1.0, 0.5] {
+            spl.insert_knot(x).unwrap();
+            let after = spl.interp_many(&xs).unwrap();
+            for (a, b) in after.iter().zip(before.iter()) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+        assert_eq!(spl.coeffs().len(), 9);
+        assert_eq!(spl.knots().len(), 12);
+        assert!(spl.insert_knot(5.0).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{accessor_impl, interp::InterpError, Matrix};
+use anyhow::Result;
+
+use super::{
+    ppoly::{taylor, PiecewisePoly},
+    Cursor, Extrapolation, Interp, InterpFloat,
+};
+
+/// Piecewise cubic Hermite interpolation with given or estimated slopes at the nodes.
+#[derive(Debug, Clone)]
+pub struct Hermite1D<T>
+where
+    T: InterpFloat,
+{
+    mm: usize,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    xx: Vec<T>,
+    yy: Vec<T>,
+    dy: Vec<T>,
+}
+
+impl<T> Hermite1D<T>
+where
+    T: InterpFloat,
+{
+    /// Interpolate the values `yy` and the first derivatives `dy`.
+    pub fn new(xx: &[T], yy: &[T], dy: &[T]) -> Result<Self> {
+        if xx.len() < 2 || yy.len() != xx.len() || dy.len() != xx.len() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
+            mm: 2,
+            cursor: Cursor::new(xx.len()),
+            extrapolation: Extrapolation::default(),
+            xx: xx.to_vec(),
+            yy: yy.to_vec(),
+            dy: dy.to_vec(),
+        })
+    }
+
+    /// PCHIP: the slopes are the weighted harmonic means of the secants of Fritsch and Carlson,
+    /// set to zero at local extrema, so that monotone data give a monotone interpolant.
+    pub fn pchip(xx: &[T], yy: &[T]) -> Result<Self> {
+        let (h, d) = secants(xx, yy)?;
+        let n = xx.len();
+        let mut dy = vec![d[0]; n];
+        if n > 2 {
+            for k in 1..(n - 1) {
+                dy[k] = if sign(d[k - 1]) * sign(d[k]) <= T::zero() {
+                    T::zero()
+                } else {
+                    let w1 = T::lit(2.0) * h[k] + h[k - 1];
+                    let w2 = h[k] + T::lit(2.0) * h[k - 1];
+                    (w1 + w2) / (w1 / d[k - 1] + w2 / d[k])
+                };
+            }
+            dy[0] = pchip_end(h[0], h[1], d[0], d[1]);
+            dy[n - 1] = pchip_end(h[n - 2], h[n - 3], d[n - 2], d[n - 3]);
+        }
+        Hermite1D::new(xx, yy, &dy)
+    }
+
+    /// Akima's method: the slopes are weighted by the changes of the neighbouring secants,
+    /// which avoids wiggles around outliers and keeps straight segments straight.
+    pub fn akima(xx: &[T], yy: &[T]) -> Result<Self> {
+        let (_, d) = secants(xx, yy)?;
+        let n = xx.len();
+        if n == 2 {
+            return Hermite1D::new(xx, yy, &[d[0], d[0]]);
+        }
+        // secants extended by two on each side
+        let mut m = vec![T::zero(); n + 3];
+        m[2..(n + 1)].copy_from_slice(&d);
+        m[1] = T::lit(2.0) * m[2] - m[3];
+        m[0] = T::lit(2.0) * m[1] - m[2];
+        m[n + 1] = T::lit(2.0) * m[n] - m[n - 1];
+        m[n + 2] = T::lit(2.0) * m[n + 1] - m[n];
+        let dy: Vec<T> = (0..n)
+            .map(|i| {
+                let w1 = (m[i + 3] - m[i + 2]).abs();
+                let w2 = (m[i + 1] - m[i]).abs();
+                if w1 + w2 == T::zero() {
+                    (m[i + 1] + m[i + 2]) / T::lit(2.0)
+                } else {
+                    (w1 * m[i + 1] + w2 * m[i + 2]) / (w1 + w2)
+                }
+            })
+            .collect();
+        Hermite1D::new(xx, yy, &dy)
+    }
+
+    /// Steffen's method: the slopes of the parabolas through three neighbouring points,
+    /// limited so that the interpolant is monotone between the nodes.
+    pub fn steffen(xx: &[T], yy: &[T]) -> Result<Self> {
+        let (h, d) = secants(xx, yy)?;
+        let n = xx.len();
+        let mut dy = vec![d[0]; n];
+        if n > 2 {
+            let half = T::lit(0.5);
+            for i in 1..(n - 1) {
+                let p = (d[i - 1] * h[i] + d[i] * h[i - 1]) / (h[i - 1] + h[i]);
+                let lim = d[i - 1].abs().min(d[i].abs()).min(half * p.abs());
+                dy[i] = (sign(d[i - 1]) + sign(d[i])) * lim;
+            }
+            dy[0] = steffen_end(h[0], h[1], d[0], d[1]);
+            dy[n - 1] = steffen_end(h[n - 2], h[n - 3], d[n - 2], d[n - 3]);
+        }
+        Hermite1D::new(xx, yy, &dy)
+    }
+
+    /// Slopes at the nodes.
+    pub fn dy(&self) -> &[T] {
+        &self.dy
+    }
+
+    /// Coefficients of the cubic on \[xx\[j\], xx\[j+1\]\] in powers of (x - xx\[j\]).
+    fn local_coeffs(&self, j: usize) -> [T; 4] {
+        let h = self.xx[j + 1] - self.xx[j];
+        if h == T::zero() {
+            return [self.yy[j], T::zero(), T::zero(), T::zero()];
+        }
+        let d = (self.yy[j + 1] - self.yy[j]) / h;
+        let (m0, m1) = (self.dy[j], self.dy[j + 1]);
+        [
+            self.yy[j],
+            m0,
+            (T::lit(3.0) * d - T::lit(2.0) * m0 - m1) / h,
+            (m0 + m1 - T::lit(2.0) * d) / (h * h),
+        ]
+    }
+
+    pub fn to_ppoly(&self) -> PiecewisePoly<T> {
+        let data = (0..(self.n() - 1))
+            .flat_map(|j| self.local_coeffs(j))
+            .collect();
+        PiecewisePoly::new(&self.xx, Matrix::new(self.n() - 1, 4, data))
+            .unwrap()
+            .with_extrapolation(self.extrapolation)
+    }
+
+    /// `order`-th derivative at `x`.
+    pub fn derivative(&self, x: T, order: usize) -> Result<T> {
+        self.check_range(x)?;
+        let j = self.locate(x);
+        Ok(taylor(&self.local_coeffs(j), x - self.xx[j], order))
+    }
+}
+
+impl<T> Interp<T> for Hermite1D<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
+
+    fn xx(&self) -> &[T] {
+        &self.xx
+    }
+
+    fn rawinterp(&self, j: usize, x: T) -> Result<T> {
+        Ok(taylor(&self.local_coeffs(j), x - self.xx[j], 0))
+    }
+}
+
+/// Widths and slopes of the intervals.
+fn secants<T>(xx: &[T], yy: &[T]) -> Result<(Vec<T>, Vec<T>)>
+where
+    T: InterpFloat,
+{
+    if xx.len() < 2 || yy.len() != xx.len() {
+        return Err(InterpError::SizeNotMatch().into());
+    }
+    let h: Vec<T> = xx.windows(2).map(|w| w[1] - w[0]).collect();
+    if h.iter().any(|&v| v == T::zero()) {
+        return Err(InterpError::IdenticalX().into());
+    }
+    let d = yy
+        .windows(2)
+        .zip(&h)
+        .map(|(w, &hi)| (w[1] - w[0]) / hi)
+        .collect();
+    Ok((h, d))
+}
+
+/// Sign with sign(0) = 0.
+fn sign<T>(val: T) -> T
+where
+    T: InterpFloat,
+{
+    if val == T::zero() {
+        T::zero()
+    } else {
+        val.signum()
+    }
+}
+
+/// Shape-preserving three-point estimate at an end, where `h0` and `d0` belong to the end interval.
+fn pchip_end<T>(h0: T, h1: T, d0: T, d1: T) -> T
+where
+    T: InterpFloat,
+{
+    let m = ((T::lit(2.0) * h0 + h1) * d0 - h0 * d1) / (h0 + h1);
+    if sign(m) != sign(d0) {
+        T::zero()
+    } else if sign(d0) != sign(d1) && m.abs() > T::lit(3.0) * d0.abs() {
+        T::lit(3.0) * d0
+    } else {
+        m
+    }
+}
+
+fn steffen_end<T>(h0: T, h1: T, d0: T, d1: T) -> T
+where
+    T: InterpFloat,
+{
+    let p = d0 * (T::one() + h0 / (h0 + h1)) - d1 * h0 / (h0 + h1);
+    if p * d0 <= T::zero() {
+        T::zero()
+    } else if p.abs() > T::lit(2.0) * d0.abs() {
+        T::lit(2.0) * d0
+    } else {
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_monotone(interp: &Hermite1D<f64>, x0: f64, x1: f64, ascend: bool) -> bool {
+        let xs: Vec<f64> = (0..=2000)
+            .map(|i| x0 + (x1 - x0) * i as f64 / 2000.0)
+            .collect();
+        let ys = interp.interp_many(&xs).unwrap();
+        ys.windows(2).all(|w| {
+            if ascend {
+                w[1] >= w[0] - 1e-12
+            } else {
+                w[1] <= w[0] + 1e-12
+            }
+        })
+    }
+
+    #[test]
+    fn test_monotone() {
+        // cumulative data with flat parts and steep steps
+        let xx: Vec<f64> = vec![0.0, 1.0, 1.5, 2.0, 4.0, 4.2, 5.0, 7.0, 8.0, 10.0];
+        let yy: Vec<f64> = vec![0.0, 0.0, 0.1, 0.1, 0.15, 0.9, 0.95, 1.0, 1.0, 1.0];
+        let spline = crate::interp::spline1d::Spline1D::new(&xx, &yy).unwrap();
+        let ys = spline.interp_many(&[4.5, 7.5]).unwrap();
+        assert!(
+            ys[0] > 0.95 || ys[1] > 1.0,
+            "the natural spline should overshoot"
+        );
+
+        let ydesc: Vec<f64> = yy.iter().map(|v| 1.0 - 2.0 * v).collect();
+        for ctor in [Hermite1D::pchip, Hermite1D::steffen] {
+            let interp = ctor(&xx, &yy).unwrap();
+            assert!(is_monotone(&interp, 0.0, 10.0, true), "{:?}", interp.dy());
+            for (x, y) in xx.iter().zip(yy.iter()) {
+                assert!((interp.eval(*x).unwrap() - y).abs() < 1e-12);
+            }
+            let interp = ctor(&xx, &ydesc).unwrap();
+            assert!(is_monotone(&interp, 0.0, 10.0, false));
+        }
+        // Akima is not monotone in general, but keeps the flat tail flat
+        let interp = Hermite1D::akima(&xx, &yy).unwrap();
+        for x in [8.3, 9.0, 9.9] {
+            assert!((interp.eval(x).unwrap() - 1.0).abs() < 1e-12);
+        }
+        assert!(is_monotone(&interp, 2.0, 10.0, true));
+
+        // pseudo random monotone data
+        let mut seed = 12345u64;
+        let mut xx = vec![0.0];
+        let mut yy = vec![0.0];
+        for _ in 0..200 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let r = (seed >> 33) as f64 / (1u64 << 31) as f64;
+            xx.push(xx.last().unwrap() + 0.1 + r);
+            let step = if r < 0.3 { 0.0 } else { r * r * 10.0 };
+            yy.push(yy.last().unwrap() + step);
+        }
+        let last = *xx.last().unwrap();
+        for ctor in [Hermite1D::pchip, Hermite1D::steffen] {
+            let interp = ctor(&xx, &yy).unwrap();
+            assert!(is_monotone(&interp, 0.0, last, true));
+        }
+    }
+
+    #[test]
+    fn test_hermite() {
+        // slopes of a straight line are exact for all methods
+        let xx: Vec<f64> = vec![0.0, 0.5, 2.0, 3.0, 4.5];
+        let yy: Vec<f64> = xx.iter().map(|x| 2.0 * x - 1.0).collect();
+        for ctor in [Hermite1D::pchip, Hermite1D::steffen, Hermite1D::akima] {
+            let interp = ctor(&xx, &yy).unwrap();
+            for x in [0.2, 1.3, 4.0] {
+                assert!((interp.eval(x).unwrap() - (2.0 * x - 1.0)).abs() < 1e-12);
+                assert!((interp.derivative(x, 1).unwrap() - 2.0).abs() < 1e-12);
+            }
+        }
+
+        // cubic Hermite reproduces cubic polynomials with exact slopes
+        let p = |x: f64| x * x * x - x;
+        let yy: Vec<f64> = xx.iter().map(|&x| p(x)).collect();
+        let dy: Vec<f64> = xx.iter().map(|x| 3.0 * x * x - 1.0).collect();
+        let mut interp = Hermite1D::new(&xx, &yy, &dy).unwrap();
+        assert!((interp.interp(1.7).unwrap() - p(1.7)).abs() < 1e-12);
+        assert!((interp.derivative(1.7, 2).unwrap() - 6.0 * 1.7).abs() < 1e-10);
+        let res = interp.to_ppoly().integral(0.0, 4.5).unwrap();
+        assert!((res - (4.5f64.powi(4) / 4.0 - 4.5 * 4.5 / 2.0)).abs() < 1e-10);
+
+        assert!(Hermite1D::pchip(&[0.0, 1.0, 1.0], &[0.0, 1.0, 2.0]).is_err());
+        assert!(Hermite1D::new(&xx, &yy, &dy[1..]).is_err());
+    }
+}
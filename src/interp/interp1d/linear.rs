@@ -1,45 +1,96 @@
-use crate::accessor_impl;
+use crate::{accessor_impl, interp::InterpError, Matrix};
 use anyhow::Result;
 
-use super::Interp;
+use super::{ppoly::PiecewisePoly, Cursor, Extrapolation, Interp, InterpFloat};
 
-pub struct LinearInterp<'a> {
-    n: usize,
+#[derive(Debug, Clone)]
+pub struct LinearInterp<T>
+where
+    T: InterpFloat,
+{
     mm: usize,
-    jsav: usize,
-    dj: usize,
-    cor: usize,
-    xx: &'a Vec<f64>,
-    yy: &'a Vec<f64>,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    xx: Vec<T>,
+    yy: Vec<T>,
 }
 
-impl<'a> LinearInterp<'a> {
-    pub fn new(xx: &'a Vec<f64>, yy: &'a Vec<f64>) -> Self {
-        Self {
-            n: xx.len(),
+impl<T> LinearInterp<T>
+where
+    T: InterpFloat,
+{
+    pub fn new(xx: &[T], yy: &[T]) -> Result<Self> {
+        if xx.len() != yy.len() || xx.len() < 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
             mm: 2,
-            jsav: 0,
-            cor: 0,
-            dj: 0,
-            xx,
-            yy,
+            cursor: Cursor::new(xx.len()),
+            extrapolation: Extrapolation::default(),
+            xx: xx.to_vec(),
+            yy: yy.to_vec(),
+        })
+    }
+
+    pub fn yy(&self) -> &[T] {
+        &self.yy
+    }
+
+    pub fn to_ppoly(&self) -> PiecewisePoly<T> {
+        let n = self.n();
+        let data = (0..(n - 1))
+            .flat_map(|j| {
+                let h = self.xx[j + 1] - self.xx[j];
+                let slope = if h == T::zero() {
+                    T::zero()
+                } else {
+                    (self.yy[j + 1] - self.yy[j]) / h
+                };
+                [self.yy[j], slope]
+            })
+            .collect();
+        PiecewisePoly::new(&self.xx, Matrix::new(n - 1, 2, data))
+            .unwrap()
+            .with_extrapolation(self.extrapolation)
+    }
+
+    /// `order`-th derivative at `x`. The first derivative at a node is the one of the interval on its right.
+    pub fn derivative(&self, x: T, order: usize) -> Result<T> {
+        self.check_range(x)?;
+        let j = self.locate(x);
+        match order {
+            0 => self.rawinterp(j, x),
+            1 if self.xx[j] != self.xx[j + 1] => {
+                Ok((self.yy[j + 1] - self.yy[j]) / (self.xx[j + 1] - self.xx[j]))
+            }
+            _ => Ok(T::zero()),
         }
     }
+
+    /// Definite integral over \[a, b\].
+    pub fn integral(&self, a: T, b: T) -> Result<T> {
+        self.to_ppoly().integral(a, b)
+    }
+
+    /// Antiderivative which is zero at xx\[0\].
+    pub fn antiderivative(&self) -> PiecewisePoly<T> {
+        self.to_ppoly().antiderivative()
+    }
 }
 
-impl<'a> Interp for LinearInterp<'a> {
-    accessor_impl!((get = n, set = set_n): usize);
-    accessor_impl!((get = mm, set = set_mm): usize);
-    accessor_impl!((get = jsav, set = set_jsav): usize);
-    accessor_impl!((get = dj, set = set_dj): usize);
-    accessor_impl!((get = cor, set = set_cor): usize);
+impl<T> Interp<T> for LinearInterp<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
 
-    fn xx(&self) -> &Vec<f64> {
-        self.xx
+    fn xx(&self) -> &[T] {
+        &self.xx
     }
 
-    fn rawinterp(&self, j: usize, x: f64) -> Result<f64> {
-        println!("j = {}, x = {}", j, x);
+    fn rawinterp(&self, j: usize, x: T) -> Result<T> {
         if self.xx[j] == self.xx[j + 1] {
             return Ok(self.yy[j]);
         }
@@ -55,9 +106,9 @@ mod tests {
 
     #[test]
     fn test_linear_interp() {
-        let xx = vec![1.0, 2.0, 3.0, 4.0, 5.0, 10.0, 20.0];
+        let xx: Vec<f64> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 10.0, 20.0];
         let yy = vec![10.0, 15.0, 30.0, 10.0, 0.0, 5.0, -5.0];
-        let mut lint = LinearInterp::new(&xx, &yy);
+        let mut lint = LinearInterp::new(&xx, &yy).unwrap();
         let res = lint.interp(1.0).unwrap();
         assert!((res - 10.0).abs() < 1e-5);
         assert!((lint.interp(1.5).unwrap() - 12.5).abs() < 1e-5);
@@ -67,5 +118,21 @@ mod tests {
         assert!((res - 0.0).abs() < 1e-5, "res = {}", res);
         let res = lint.interp(6.0).unwrap();
         assert!((res - 1.0).abs() < 1e-5, "res = {}", res);
+
+        assert!(LinearInterp::new(&[1.0], &[2.0]).is_err());
+        assert!(LinearInterp::new(&xx, &yy[1..]).is_err());
+    }
+
+    #[test]
+    fn test_linear_interp_f32() {
+        let xx: Vec<f32> = vec![0.0, 1.0, 2.0];
+        let yy: Vec<f32> = vec![0.0, 2.0, 3.0];
+        let lint = LinearInterp::new(&xx, &yy).unwrap();
+        assert!((lint.eval(1.5).unwrap() - 2.5).abs() < 1e-5);
+        assert!((lint.derivative(0.5, 1).unwrap() - 2.0).abs() < 1e-5);
+        assert!((lint.derivative(1.5, 1).unwrap() - 1.0).abs() < 1e-5);
+        assert!(lint.derivative(1.5, 2).unwrap().abs() < 1e-5);
+        assert!((lint.integral(0.0, 2.0).unwrap() - 3.5).abs() < 1e-5);
+        assert!((lint.antiderivative().eval(1.5).unwrap() - 2.125).abs() < 1e-5);
     }
 }
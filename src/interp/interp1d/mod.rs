@@ -1,117 +1,353 @@
 use crate::accessor;
 use anyhow::Result;
 
+use super::{Extrapolation, InterpError, InterpFloat};
+
 pub mod barycentric_1d;
+pub mod bspline;
+pub mod hermite_1d;
 pub mod linear;
 pub mod poly_1d;
 pub mod poly_coeff;
+pub mod ppoly;
 pub mod rational_1d;
+pub mod smoothing_spline;
 pub mod spline1d;
 
-pub trait Interp {
-    accessor!((get = n, set = set_n): usize);
-    accessor!((get = mm, set = set_mm): usize);
-    accessor!((get = jsav, set = set_jsav): usize);
-    accessor!((get = dj, set = set_dj): usize);
-    accessor!((get = cor, set = set_cor): usize);
-    fn xx(&self) -> &Vec<f64>;
-
-    fn interp(&mut self, x: f64) -> Result<f64> {
-        let jlo = if self.cor() > 0 {
-            self.hunt(x)
-        } else {
-            self.locate(x)
-        };
-        self.rawinterp(jlo, x)
+/// Number of points evaluated per task by the parallel batch methods.
+#[cfg(feature = "rayon")]
+pub(crate) const PAR_CHUNK: usize = 1024;
+
+/// Bracketing state carried between correlated lookups (`jsav`, `dj` and `cor` of NR's `Base_interp`).
+/// Each thread evaluating a shared interpolator keeps its own cursor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cursor {
+    jsav: usize,
+    dj: usize,
+    cor: bool,
+}
+
+impl Cursor {
+    /// Cursor for a table of `n` points.
+    pub fn new(n: usize) -> Self {
+        Cursor {
+            jsav: 0,
+            dj: std::cmp::max(1, (n as f64).powf(0.25) as usize),
+            cor: false,
+        }
     }
 
-    fn locate(&mut self, x: f64) -> usize {
-        let mut min = 0;
-        let mut max = self.n();
-        let xx = self.xx();
-        let ascend = xx[max - 1] >= xx[0];
-        while max - min > 1 {
-            let mid = (min + max) >> 1;
-            if (xx[mid] < x) == ascend {
-                min = mid;
-            } else {
-                max = mid;
-            }
+    /// Whether the last two lookups were close to each other, so that `hunt` is worthwhile.
+    pub fn correlated(&self) -> bool {
+        self.cor
+    }
+
+    fn update(&mut self, j: usize) {
+        self.cor = j.abs_diff(self.jsav) <= self.dj;
+        self.jsav = j;
+    }
+}
+
+pub trait Interp<T>
+where
+    T: InterpFloat,
+{
+    accessor!((get = cursor, set = set_cursor): Cursor);
+    accessor!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
+    fn xx(&self) -> &[T];
+    fn mm(&self) -> usize;
+
+    fn rawinterp(&self, jlo: usize, x: T) -> Result<T>;
+
+    fn n(&self) -> usize {
+        self.xx().len()
+    }
+
+    fn with_extrapolation(mut self, extrapolation: Extrapolation<T>) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_extrapolation(extrapolation);
+        self
+    }
+
+    /// Evaluate at `x` without touching any state.
+    fn eval(&self, x: T) -> Result<T> {
+        match self.extrapolate(x)? {
+            Some(y) => Ok(y),
+            None => self.rawinterp(self.locate(x), x),
+        }
+    }
+
+    /// Evaluate at `x`, using `cursor` to speed up correlated lookups.
+    fn eval_with(&self, cursor: &mut Cursor, x: T) -> Result<T> {
+        match self.extrapolate(x)? {
+            Some(y) => Ok(y),
+            None => self.rawinterp(self.hunt(cursor, x), x),
         }
-        let cor = if min > self.jsav() {
-            (min - self.jsav() > self.dj()) as usize
+    }
+
+    /// Fails if `x` is outside the data range and the policy is `Extrapolation::Error`.
+    fn check_range(&self, x: T) -> Result<()> {
+        if self.extrapolation() == Extrapolation::Error {
+            self.extrapolate(x)?;
+        }
+        Ok(())
+    }
+
+    /// Value at `x` given by the extrapolation policy,
+    /// `None` if `x` is inside the data range or the policy is `Natural`.
+    fn extrapolate(&self, x: T) -> Result<Option<T>> {
+        let xx = self.xx();
+        let n = xx.len();
+        let (first, last) = if xx[n - 1] >= xx[0] {
+            (0, n - 1)
         } else {
-            (self.jsav() - min > self.dj()) as usize
+            (n - 1, 0)
         };
-        self.set_cor(cor);
-        self.set_jsav(min);
-        if min < (self.mm() - 2) >> 1 || self.n() < self.mm() {
-            0
+        let (i, k) = if x < xx[first] {
+            (first, if first == 0 { 1 } else { n - 2 })
+        } else if x > xx[last] {
+            (last, if last == 0 { 1 } else { n - 2 })
         } else {
-            std::cmp::min(self.n() - self.mm(), min - ((self.mm() - 2) >> 1))
+            return Ok(None);
+        };
+        match self.extrapolation() {
+            Extrapolation::Natural => Ok(None),
+            Extrapolation::Error => Err(InterpError::OutOfRange().into()),
+            Extrapolation::Fill(val) => Ok(Some(val)),
+            Extrapolation::Clamp => Ok(Some(self.rawinterp(self.locate(xx[i]), xx[i])?)),
+            Extrapolation::Linear => {
+                let yi = self.rawinterp(self.locate(xx[i]), xx[i])?;
+                let yk = self.rawinterp(self.locate(xx[k]), xx[k])?;
+                Ok(Some(yi + (x - xx[i]) * (yk - yi) / (xx[k] - xx[i])))
+            }
         }
     }
 
-    fn hunt(&mut self, x: f64) -> usize {
-        let mut jl = self.jsav();
-        let mut inc = 1;
-        let mut ju;
-        let n = self.n();
-        if n < 2 || self.mm() < 2 || self.mm() > n {
-            return self.locate(x);
+    /// Evaluate at `x` with the cursor kept in the interpolator.
+    fn interp(&mut self, x: T) -> Result<T> {
+        let mut cursor = self.cursor();
+        let y = self.eval_with(&mut cursor, x);
+        self.set_cursor(cursor);
+        y
+    }
+
+    /// Evaluate at each point of `xs`. Sorted queries are bracketed by hunting from the
+    /// previous interval.
+    fn interp_many(&self, xs: &[T]) -> Result<Vec<T>> {
+        let mut out = vec![T::zero(); xs.len()];
+        self.interp_into(xs, &mut out)?;
+        Ok(out)
+    }
+
+    /// Same as `interp_many`, writing into `out` instead of allocating.
+    fn interp_into(&self, xs: &[T], out: &mut [T]) -> Result<()> {
+        if xs.len() != out.len() {
+            return Err(InterpError::SizeNotMatch().into());
         }
+        let mut cursor = Cursor::new(self.n());
+        for (y, &x) in out.iter_mut().zip(xs.iter()) {
+            *y = self.eval_with(&mut cursor, x)?;
+        }
+        Ok(())
+    }
+
+    /// Same as `interp_many`, splitting `xs` into chunks evaluated in parallel, each with
+    /// its own cursor.
+    #[cfg(feature = "rayon")]
+    fn par_interp_many(&self, xs: &[T]) -> Result<Vec<T>>
+    where
+        Self: Sync,
+    {
+        use rayon::prelude::*;
+        let mut out = vec![T::zero(); xs.len()];
+        out.par_chunks_mut(PAR_CHUNK)
+            .zip(xs.par_chunks(PAR_CHUNK))
+            .try_for_each(|(out, xs)| self.interp_into(xs, out))?;
+        Ok(out)
+    }
+
+    /// Index of the first of the `mm` points used to interpolate at `x`, found by bisection.
+    fn locate(&self, x: T) -> usize {
+        window(self.n(), self.mm(), bisect(self.xx(), x))
+    }
+
+    /// Same as `locate`, but starts hunting from the previous position when `cursor`
+    /// reports correlated lookups.
+    fn hunt(&self, cursor: &mut Cursor, x: T) -> usize {
         let xx = self.xx();
-        let ascend = xx[n - 1] >= xx[0];
-        if (x >= xx[jl]) == ascend {
-            loop {
-                ju = jl + inc;
-                if ju > n - 1 {
-                    ju = n - 1;
-                    break;
-                } else if (x < xx[ju]) == ascend {
-                    break;
-                } else {
-                    jl = ju;
-                    inc += inc;
-                }
-            }
+        let j = if cursor.cor && self.n() >= 2 {
+            hunt_from(xx, cursor.jsav, x)
         } else {
-            ju = jl;
-            loop {
-                if jl < inc {
-                    jl = 0;
-                    break;
-                }
-                jl -= inc;
-                if (x >= xx[jl]) == ascend {
-                    break;
-                } else {
-                    ju = jl;
-                    inc += inc;
-                }
+            bisect(xx, x)
+        };
+        cursor.update(j);
+        window(self.n(), self.mm(), j)
+    }
+}
+
+/// Index j such that x lies between xx\[j\] and xx\[j + 1\] (monotonic `xx`).
+fn bisect<T>(xx: &[T], x: T) -> usize
+where
+    T: InterpFloat,
+{
+    let mut min = 0;
+    let mut max = xx.len();
+    let ascend = xx[max - 1] >= xx[0];
+    while max - min > 1 {
+        let mid = (min + max) >> 1;
+        if (xx[mid] < x) == ascend {
+            min = mid;
+        } else {
+            max = mid;
+        }
+    }
+    min
+}
+
+/// Same as `bisect`, but expands the bracket from `jsav` first.
+fn hunt_from<T>(xx: &[T], jsav: usize, x: T) -> usize
+where
+    T: InterpFloat,
+{
+    let n = xx.len();
+    let mut jl = std::cmp::min(jsav, n - 1);
+    let mut inc = 1;
+    let mut ju;
+    let ascend = xx[n - 1] >= xx[0];
+    if (x >= xx[jl]) == ascend {
+        loop {
+            ju = jl + inc;
+            if ju > n - 1 {
+                ju = n - 1;
+                break;
+            } else if (x < xx[ju]) == ascend {
+                break;
+            } else {
+                jl = ju;
+                inc += inc;
             }
         }
-        while ju - jl > 1 {
-            let jm = (ju + jl) >> 1;
-            if (x >= xx[jm]) == ascend {
-                jl = jm;
+    } else {
+        ju = jl;
+        loop {
+            if jl < inc {
+                jl = 0;
+                break;
+            }
+            jl -= inc;
+            if (x >= xx[jl]) == ascend {
+                break;
             } else {
-                ju = jm;
+                ju = jl;
+                inc += inc;
             }
         }
-        let cor = if jl > self.jsav() {
-            (jl - self.jsav() > self.dj()) as usize
-        } else {
-            (self.jsav() - jl > self.dj()) as usize
-        };
-        self.set_cor(cor);
-        self.set_jsav(jl);
-        if jl < ((self.mm() - 2) >> 1) || self.n() < self.mm() {
-            0
+    }
+    while ju - jl > 1 {
+        let jm = (ju + jl) >> 1;
+        if (x >= xx[jm]) == ascend {
+            jl = jm;
         } else {
-            std::cmp::min(self.n() - self.mm(), jl - ((self.mm() - 2) >> 1))
+            ju = jm;
         }
     }
+    jl
+}
+
+/// First index of the `mm` consecutive points centered around the bracket `j`.
+pub(crate) fn window(n: usize, mm: usize, j: usize) -> usize {
+    if j < (mm - 2) >> 1 || n < mm {
+        0
+    } else {
+        std::cmp::min(n - mm, j - ((mm - 2) >> 1))
+    }
+}
 
-    fn rawinterp(&self, jlo: usize, x: f64) -> Result<f64>;
+#[cfg(test)]
+mod tests {
+    use super::{linear::LinearInterp, *};
+
+    #[test]
+    fn test_cursor() {
+        let xx: Vec<f64> = (0..100).map(|i| (i * i) as f64).collect();
+        let yy: Vec<f64> = xx.iter().map(|x| 2.0 * x).collect();
+        let mut lint = LinearInterp::new(&xx, &yy).unwrap();
+        let xs: Vec<f64> = (0..1000).map(|i| i as f64 * 9.5).collect();
+
+        let many = lint.interp_many(&xs).unwrap();
+        let mut cursor = Cursor::new(xx.len());
+        for (x, y) in xs.iter().zip(many.iter()) {
+            let expect = lint.eval(*x).unwrap();
+            assert!((y - expect).abs() < 1e-10);
+            assert!((lint.eval_with(&mut cursor, *x).unwrap() - expect).abs() < 1e-10);
+            assert!((lint.interp(*x).unwrap() - expect).abs() < 1e-10);
+        }
+        assert!(cursor.correlated());
+        assert!(lint.cursor().correlated());
+
+        let mut out = vec![0.0; xs.len()];
+        lint.interp_into(&xs, &mut out).unwrap();
+        assert_eq!(out, many);
+        assert!(lint.interp_into(&xs, &mut out[1..]).is_err());
+        #[cfg(feature = "rayon")]
+        {
+            let long: Vec<f64> = xs.iter().cycle().take(5 * PAR_CHUNK).cloned().collect();
+            assert_eq!(
+                lint.par_interp_many(&long).unwrap(),
+                lint.interp_many(&long).unwrap()
+            );
+        }
+
+        // jump far away and come back
+        assert_eq!(lint.hunt(&mut cursor, 0.5), 0);
+        assert!(!cursor.correlated());
+        assert_eq!(lint.hunt(&mut cursor, 1.5), 1);
+        assert!(cursor.correlated());
+        assert_eq!(lint.hunt(&mut cursor, 9000.0), 94);
+
+        // descending table
+        let xx: Vec<f32> = (0..10).map(|i| 10.0 - i as f32).collect();
+        let lint = LinearInterp::new(&xx, &xx).unwrap();
+        assert_eq!(lint.locate(7.5), 2);
+        let mut cursor = Cursor::new(xx.len());
+        assert_eq!(lint.hunt(&mut cursor, 7.5), 2);
+        assert_eq!(lint.hunt(&mut cursor, 6.5), 3);
+        assert_eq!(lint.hunt(&mut cursor, 9.5), 0);
+    }
+
+    #[test]
+    fn test_extrapolation() {
+        let xx: Vec<f64> = vec![1.0, 2.0, 4.0];
+        let yy = vec![1.0, 3.0, 4.0];
+        let lint = LinearInterp::new(&xx, &yy).unwrap();
+        assert!((lint.eval(5.0).unwrap() - 4.5).abs() < 1e-10);
+        assert!((lint.eval(0.0).unwrap() + 1.0).abs() < 1e-10);
+
+        let lint = lint.with_extrapolation(Extrapolation::Error);
+        assert!(lint.eval(0.5).is_err());
+        assert!(lint.eval(4.5).is_err());
+        assert!((lint.eval(4.0).unwrap() - 4.0).abs() < 1e-10);
+        assert!(lint.interp_many(&[1.0, 5.0]).is_err());
+        #[cfg(feature = "rayon")]
+        assert!(lint.par_interp_many(&[1.0, 5.0]).is_err());
+
+        let lint = lint.with_extrapolation(Extrapolation::Clamp);
+        assert!((lint.eval(0.0).unwrap() - 1.0).abs() < 1e-10);
+        assert!((lint.eval(10.0).unwrap() - 4.0).abs() < 1e-10);
+
+        let mut lint = lint.with_extrapolation(Extrapolation::Fill(-7.0));
+        assert!((lint.interp(0.0).unwrap() + 7.0).abs() < 1e-10);
+        assert!((lint.interp(3.0).unwrap() - 3.5).abs() < 1e-10);
+
+        // descending table
+        let xx: Vec<f64> = xx.into_iter().rev().collect();
+        let yy: Vec<f64> = yy.into_iter().rev().collect();
+        let lint = LinearInterp::new(&xx, &yy)
+            .unwrap()
+            .with_extrapolation(Extrapolation::Linear);
+        assert!((lint.eval(5.0).unwrap() - 4.5).abs() < 1e-10);
+        assert!((lint.eval(0.0).unwrap() + 1.0).abs() < 1e-10);
+    }
 }
@@ -1,90 +1,183 @@
-use crate::{accessor_impl, interp::InterpError};
+use crate::{accessor_impl, interp::InterpError, linalg::vander::polyfit, MatLinAlgBound, Matrix};
 use anyhow::Result;
 
-use super::Interp;
+use super::{
+    ppoly::{factorial, PiecewisePoly},
+    window, Cursor, Extrapolation, Interp, InterpFloat,
+};
 
-pub struct Poly1D<'a> {
-    n: usize,
+#[derive(Debug, Clone)]
+pub struct Poly1D<T>
+where
+    T: InterpFloat,
+{
     mm: usize,
-    jsav: usize,
-    dj: usize,
-    cor: usize,
-    _dy: f64,
-    xx: &'a Vec<f64>,
-    yy: &'a [f64],
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    xx: Vec<T>,
+    yy: Vec<T>,
 }
 
-impl<'a> Poly1D<'a> {
-    pub fn new(xx: &'a Vec<f64>, yy: &'a Vec<f64>, mm: usize) -> Self {
-        Self {
-            n: xx.len(),
+impl<T> Poly1D<T>
+where
+    T: InterpFloat,
+{
+    /// Interpolates through the `mm` nodes around each point.
+    pub fn new(xx: &[T], yy: &[T], mm: usize) -> Result<Self> {
+        if xx.len() != yy.len() || xx.len() < 2 || mm == 0 || mm > xx.len() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
             mm,
-            jsav: 0,
-            dj: 0,
-            cor: 0,
-            _dy: 0.0,
-            xx,
-            yy,
+            cursor: Cursor::new(xx.len()),
+            extrapolation: Extrapolation::default(),
+            xx: xx.to_vec(),
+            yy: yy.to_vec(),
+        })
+    }
+
+    pub fn yy(&self) -> &[T] {
+        &self.yy
+    }
+
+    /// Value at `x` and its error estimate. Points handled by the extrapolation policy
+    /// have a zero error estimate.
+    pub fn eval_with_error(&self, x: T) -> Result<(T, T)> {
+        if let Some(y) = self.extrapolate(x)? {
+            return Ok((y, T::zero()));
         }
+        let j = self.locate(x);
+        neville_with_error(&self.xx[j..(j + self.mm)], &self.yy[j..(j + self.mm)], x)
     }
 
-    pub fn set_yy(&mut self, yy: &'a [f64]) {
-        self.yy = yy;
+    /// Same as `eval_with_error`, with the cursor kept in the interpolator.
+    pub fn interp_with_error(&mut self, x: T) -> Result<(T, T)> {
+        if let Some(y) = self.extrapolate(x)? {
+            return Ok((y, T::zero()));
+        }
+        let mut cursor = self.cursor;
+        let j = self.hunt(&mut cursor, x);
+        self.cursor = cursor;
+        neville_with_error(&self.xx[j..(j + self.mm)], &self.yy[j..(j + self.mm)], x)
     }
 }
 
-impl<'a> Interp for Poly1D<'a> {
-    accessor_impl!((get = n, set = set_n): usize);
-    accessor_impl!((get = mm, set = set_mm): usize);
-    accessor_impl!((get = jsav, set = set_jsav): usize);
-    accessor_impl!((get = dj, set = set_dj): usize);
-    accessor_impl!((get = cor, set = set_cor): usize);
-
-    fn xx(&self) -> &Vec<f64> {
-        self.xx
-    }
-
-    fn rawinterp(&self, j: usize, x: f64) -> Result<f64> {
-        let xx = self.xx();
-        let yy = self.yy;
-        let mut c = vec![0.0; self.mm()];
-        let mut d = vec![0.0; self.mm()];
-        let mut ns: isize = 0;
-        let mut dif = x - xx[j];
-        for i in 0..self.mm() {
-            let tmp = (x - xx[j + i]).abs();
-            if tmp < dif {
-                ns = i as isize;
-                dif = tmp;
-            }
-            c[i] = yy[j + i];
-            d[i] = yy[j + i];
+impl<T> Poly1D<T>
+where
+    T: InterpFloat + MatLinAlgBound,
+{
+    /// Coefficients in powers of (x - x0) of the polynomial through the `mm` points from `jlo`.
+    fn local_coeffs(&self, jlo: usize, x0: T) -> Result<Vec<T>> {
+        let mm = self.mm;
+        let xa: Vec<T> = self.xx[jlo..(jlo + mm)].iter().map(|&v| v - x0).collect();
+        polyfit(&xa, &self.yy[jlo..(jlo + mm)])
+    }
+
+    /// Each interval between the nodes gets the polynomial used by `interp` there.
+    pub fn to_ppoly(&self) -> Result<PiecewisePoly<T>> {
+        let n = self.n();
+        let mut data = Vec::with_capacity((n - 1) * self.mm);
+        for j in 0..(n - 1) {
+            data.extend(self.local_coeffs(window(n, self.mm, j), self.xx[j])?);
+        }
+        Ok(
+            PiecewisePoly::new(&self.xx, Matrix::new(n - 1, self.mm, data))?
+                .with_extrapolation(self.extrapolation),
+        )
+    }
+
+    /// `order`-th derivative at `x`.
+    pub fn derivative(&self, x: T, order: usize) -> Result<T> {
+        self.check_range(x)?;
+        if order >= self.mm {
+            return Ok(T::zero());
+        }
+        let c = self.local_coeffs(self.locate(x), x)?;
+        Ok(c[order] * factorial::<T>(order))
+    }
+
+    /// Definite integral over \[a, b\].
+    pub fn integral(&self, a: T, b: T) -> Result<T> {
+        self.to_ppoly()?.integral(a, b)
+    }
+
+    /// Antiderivative which is zero at xx\[0\].
+    pub fn antiderivative(&self) -> Result<PiecewisePoly<T>> {
+        Ok(self.to_ppoly()?.antiderivative())
+    }
+}
+
+impl<T> Interp<T> for Poly1D<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
+
+    fn xx(&self) -> &[T] {
+        &self.xx
+    }
+
+    fn rawinterp(&self, j: usize, x: T) -> Result<T> {
+        let mm = self.mm;
+        neville(&self.xx[j..(j + mm)], &self.yy[j..(j + mm)], x)
+    }
+}
+
+/// Neville's algorithm: value at `x` of the polynomial through all the points (xa\[i\], ya\[i\]).
+pub fn neville<T>(xa: &[T], ya: &[T], x: T) -> Result<T>
+where
+    T: InterpFloat,
+{
+    Ok(neville_with_error(xa, ya, x)?.0)
+}
+
+/// Same as `neville`, also returning the last correction as the error estimate.
+pub fn neville_with_error<T>(xa: &[T], ya: &[T], x: T) -> Result<(T, T)>
+where
+    T: InterpFloat,
+{
+    let mm = xa.len();
+    if ya.len() != mm {
+        return Err(InterpError::SizeNotMatch().into());
+    }
+    let mut c = ya.to_vec();
+    let mut d = ya.to_vec();
+    let mut ns: isize = 0;
+    let mut dif = (x - xa[0]).abs();
+    for (i, &xi) in xa.iter().enumerate() {
+        let tmp = (x - xi).abs();
+        if tmp < dif {
+            ns = i as isize;
+            dif = tmp;
         }
-        let mut y = yy[j + ns as usize];
-        ns -= 1;
-        for m in 1..self.mm() {
-            for i in 0..(self.mm() - m) {
-                let ho = xx[j + i] - x;
-                let hp = xx[j + i + m] - x;
-                let w = c[i + 1] - d[i];
-                let mut dn = ho - hp;
-                if dn.abs() < 1e-5 {
-                    return Err(InterpError::IdenticalX())?;
-                }
-                dn = w / dn;
-                d[i] = hp * dn;
-                c[i] = ho * dn;
+    }
+    let mut y = ya[ns as usize];
+    let mut dy = T::zero();
+    ns -= 1;
+    for m in 1..mm {
+        for i in 0..(mm - m) {
+            let ho = xa[i] - x;
+            let hp = xa[i + m] - x;
+            let w = c[i + 1] - d[i];
+            let mut dn = ho - hp;
+            if dn.abs() < T::lit(1e-5) {
+                return Err(InterpError::IdenticalX().into());
             }
-            let dy = if (2 * (ns + 1) as usize) < self.mm() - m {
-                c[(ns + 1) as usize]
-            } else {
-                ns -= 1;
-                d[(ns + 1) as usize]
-            };
-            y += dy;
+            dn = w / dn;
+            d[i] = hp * dn;
+            c[i] = ho * dn;
         }
-        Ok(y)
+        dy = if ((2 * (ns + 1)) as usize) < mm - m {
+            c[(ns + 1) as usize]
+        } else {
+            ns -= 1;
+            d[(ns + 1) as usize]
+        };
+        y = y + dy;
     }
+    Ok((y, dy))
 }
 
 #[cfg(test)]
@@ -93,20 +186,68 @@ mod tests {
 
     #[test]
     fn test_poly_1d() {
-        let xx = vec![1.0, 2.0, 3.0, 4.0, 5.0, 10.0, 20.0];
+        let xx: Vec<f64> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 10.0, 20.0];
         let yy = vec![1.0, 4.0, 9.0, 16.0, 25.0, 100.0, 400.0];
-        let mut p1d = Poly1D::new(&xx, &yy, 3);
+        let mut p1d = Poly1D::new(&xx, &yy, 3).unwrap();
         let res = p1d.interp(1.5).unwrap();
         assert!((res - 2.25).abs() < 1e-5, "res = {}", res);
         let res = p1d.interp(9.0).unwrap();
         assert!((res - 81.0).abs() < 1e-5, "res = {}", res);
+        // the nearest node lies above x
+        let res = p1d.interp(4.9).unwrap();
+        assert!((res - 24.01).abs() < 1e-5, "res = {}", res);
+
+        let res = p1d.derivative(7.0, 1).unwrap();
+        assert!((res - 14.0).abs() < 1e-10, "res = {}", res);
+        assert!((p1d.derivative(7.0, 2).unwrap() - 2.0).abs() < 1e-10);
+        assert!(p1d.derivative(7.0, 3).unwrap().abs() < 1e-10);
+        let res = p1d.integral(1.0, 20.0).unwrap();
+        assert!((res - (8000.0 - 1.0) / 3.0).abs() < 1e-8, "res = {}", res);
+        let anti = p1d.antiderivative().unwrap();
+        assert!((anti.eval(3.0).unwrap() - 26.0 / 3.0).abs() < 1e-10);
+
+        assert!(Poly1D::new(&xx, &yy, xx.len() + 1).is_err());
+        assert!(Poly1D::new(&xx, &yy, 0).is_err());
+        assert!(Poly1D::new(&xx[..1], &yy[..1], 1).is_err());
+        assert!(Poly1D::new(&xx, &yy[1..], 3).is_err());
+    }
+
+    #[test]
+    fn test_poly_1d_error() {
+        let xx: Vec<f64> = (0..10).map(|i| i as f64 * 0.3).collect();
+        let yy: Vec<f64> = xx.iter().map(|x| x.exp()).collect();
+        let mut p1d = Poly1D::new(&xx, &yy, 4).unwrap();
+        for x in [0.1, 1.25, 2.6] {
+            let (y, dy) = p1d.interp_with_error(x).unwrap();
+            let err = (y - f64::exp(x)).abs();
+            assert!(
+                err < dy.abs() && dy.abs() < 0.02,
+                "err = {}, dy = {}",
+                err,
+                dy
+            );
+        }
+        // the error estimate decreases with the number of points
+        let (_, dy4) = p1d.eval_with_error(1.25).unwrap();
+        let (_, dy6) = Poly1D::new(&xx, &yy, 6)
+            .unwrap()
+            .eval_with_error(1.25)
+            .unwrap();
+        assert!(dy6.abs() < dy4.abs());
+        // exact for polynomials of lower degree
+        let yy: Vec<f64> = xx.iter().map(|x| x * x - 1.0).collect();
+        let (y, dy) = Poly1D::new(&xx, &yy, 4)
+            .unwrap()
+            .eval_with_error(1.25)
+            .unwrap();
+        assert!((y - 0.5625).abs() < 1e-12 && dy.abs() < 1e-12);
     }
 
     #[test]
     fn test_poly_1d_linear() {
-        let xx = vec![1.0, 2.0, 3.0, 4.0, 5.0, 10.0, 20.0];
+        let xx: Vec<f64> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 10.0, 20.0];
         let yy = vec![10.0, 15.0, 30.0, 10.0, 0.0, 5.0, -5.0];
-        let mut lint = Poly1D::new(&xx, &yy, 2);
+        let mut lint = Poly1D::new(&xx, &yy, 2).unwrap();
         let res = lint.interp(1.0).unwrap();
         assert!((res - 10.0).abs() < 1e-5);
         assert!((lint.interp(1.5).unwrap() - 12.5).abs() < 1e-5);
@@ -1,3 +1,5 @@
+use crate::interp::InterpFloat;
+
 pub fn poly_coeff(x: &Vec<f64>, y: &Vec<f64>, coeff: &mut Vec<f64>) {
     let n = x.len();
     let mut factor = vec![0.0; n + 1];
@@ -29,6 +31,36 @@ pub fn poly_coeff(x: &Vec<f64>, y: &Vec<f64>, coeff: &mut Vec<f64>) {
     }
 }
 
+/// Rewrites the coefficients `d` of a polynomial in y into those in x, where y = -1..1 is mapped
+/// linearly onto x = a..b.
+pub fn pcshft<T>(a: T, b: T, d: &mut [T])
+where
+    T: InterpFloat,
+{
+    let n = d.len();
+    let cnst = T::lit(2.0) / (b - a);
+    let mut fac = cnst;
+    for dj in d.iter_mut().skip(1) {
+        *dj = *dj * fac;
+        fac = fac * cnst;
+    }
+    let cnst = T::lit(0.5) * (a + b);
+    for j in 0..n.saturating_sub(1) {
+        for k in (j..(n - 1)).rev() {
+            d[k] = d[k] - cnst * d[k + 1];
+        }
+    }
+}
+
+/// Inverse of `pcshft`: coefficients in x = a..b back to those in y = -1..1.
+pub fn ipcshft<T>(a: T, b: T, d: &mut [T])
+where
+    T: InterpFloat,
+{
+    let two = T::lit(2.0);
+    pcshft((-two - b - a) / (b - a), (two - b - a) / (b - a), d);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,4 +77,20 @@ mod tests {
         assert!((coeff[1] - 2.0).abs() < 1e-5);
         assert!((coeff[2] - 3.0).abs() < 1e-5);
     }
+
+    #[test]
+    fn test_pcshft() {
+        // y^2 + y with y = 2x - 3 on [1, 2] is 4x^2 - 10x + 6
+        let mut d: Vec<f64> = vec![0.0, 1.0, 1.0];
+        pcshft(1.0, 2.0, &mut d);
+        let expected = [6.0, -10.0, 4.0];
+        for (v, e) in d.iter().zip(expected.iter()) {
+            assert!((v - e).abs() < 1e-12, "d = {:?}", d);
+        }
+        ipcshft(1.0, 2.0, &mut d);
+        let expected = [0.0, 1.0, 1.0];
+        for (v, e) in d.iter().zip(expected.iter()) {
+            assert!((v - e).abs() < 1e-12, "d = {:?}", d);
+        }
+    }
 }
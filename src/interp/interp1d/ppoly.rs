@@ -0,0 +1,164 @@
+use crate::{accessor_impl, interp::InterpError, Matrix};
+use anyhow::Result;
+
+use super::{Cursor, Extrapolation, Interp, InterpFloat};
+
+/// Piecewise polynomial. On the interval \[xx\[j\], xx\[j+1\]\] the value is
+/// sum_k coeffs\[j\]\[k\] (x - xx\[j\])^k.
+#[derive(Debug, Clone)]
+pub struct PiecewisePoly<T>
+where
+    T: InterpFloat,
+{
+    mm: usize,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    xx: Vec<T>,
+    coeffs: Matrix<T>,
+}
+
+impl<T> PiecewisePoly<T>
+where
+    T: InterpFloat,
+{
+    /// `coeffs` has a row of local coefficients for each of the xx.len() - 1 intervals.
+    pub fn new(xx: &[T], coeffs: Matrix<T>) -> Result<Self> {
+        if xx.len() < 2 || coeffs.rows() + 1 != xx.len() || coeffs.cols() == 0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
+            mm: 2,
+            cursor: Cursor::new(xx.len()),
+            extrapolation: Extrapolation::default(),
+            xx: xx.to_vec(),
+            coeffs,
+        })
+    }
+
+    pub fn coeffs(&self) -> &Matrix<T> {
+        &self.coeffs
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coeffs.cols() - 1
+    }
+
+    /// `order`-th derivative at `x`.
+    pub fn derivative(&self, x: T, order: usize) -> Result<T> {
+        self.check_range(x)?;
+        let j = self.locate(x);
+        Ok(taylor(&self.coeffs[j], x - self.xx[j], order))
+    }
+
+    /// Piecewise polynomial of the `order`-th derivative.
+    pub fn derivative_poly(&self, order: usize) -> Self {
+        let n = self.coeffs.rows();
+        let ncols = std::cmp::max(1, self.coeffs.cols().saturating_sub(order));
+        let mut data = vec![T::zero(); n * ncols];
+        for j in 0..n {
+            for (k, val) in data[(j * ncols)..((j + 1) * ncols)].iter_mut().enumerate() {
+                *val = taylor(&self.coeffs[j], T::zero(), order + k) / factorial::<T>(k);
+            }
+        }
+        let mut obj = Self::new(&self.xx, Matrix::new(n, ncols, data)).unwrap();
+        obj.extrapolation = self.extrapolation;
+        obj
+    }
+
+    /// Antiderivative which is zero at xx\[0\].
+    pub fn antiderivative(&self) -> Self {
+        let n = self.coeffs.rows();
+        let ncols = self.coeffs.cols() + 1;
+        let mut data = vec![T::zero(); n * ncols];
+        let mut acc = T::zero();
+        for j in 0..n {
+            let row = &mut data[(j * ncols)..((j + 1) * ncols)];
+            row[0] = acc;
+            for (k, val) in row.iter_mut().enumerate().skip(1) {
+                *val = self.coeffs[j][k - 1] / T::from_usize(k).unwrap();
+            }
+            acc = taylor(row, self.xx[j + 1] - self.xx[j], 0);
+        }
+        let mut obj = Self::new(&self.xx, Matrix::new(n, ncols, data)).unwrap();
+        obj.extrapolation = self.extrapolation;
+        obj
+    }
+
+    /// Definite integral over \[a, b\]. Outside the data range the end polynomials are extended.
+    pub fn integral(&self, a: T, b: T) -> Result<T> {
+        self.check_range(a)?;
+        self.check_range(b)?;
+        let anti = self.antiderivative();
+        let ja = anti.locate(a);
+        let jb = anti.locate(b);
+        Ok(anti.rawinterp(jb, b)? - anti.rawinterp(ja, a)?)
+    }
+}
+
+impl<T> Interp<T> for PiecewisePoly<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
+
+    fn xx(&self) -> &[T] {
+        &self.xx
+    }
+
+    fn rawinterp(&self, j: usize, x: T) -> Result<T> {
+        Ok(taylor(&self.coeffs[j], x - self.xx[j], 0))
+    }
+}
+
+/// `order`-th derivative of sum_k c\[k\] t^k at `t`.
+pub(crate) fn taylor<T>(c: &[T], t: T, order: usize) -> T
+where
+    T: InterpFloat,
+{
+    c.iter()
+        .enumerate()
+        .skip(order)
+        .rev()
+        .fold(T::zero(), |acc, (k, &ck)| {
+            acc * t + ck * factorial::<T>(k) / factorial::<T>(k - order)
+        })
+}
+
+pub(crate) fn factorial<T>(k: usize) -> T
+where
+    T: InterpFloat,
+{
+    (2..=k).fold(T::one(), |acc, m| acc * T::from_usize(m).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piecewise_poly() {
+        // 1 + x^2 on [0, 1], 2 + 2(x - 1) on [1, 3]
+        let xx: Vec<f64> = vec![0.0, 1.0, 3.0];
+        let coeffs = Matrix::new(2, 3, vec![1.0, 0.0, 1.0, 2.0, 2.0, 0.0]);
+        let pp = PiecewisePoly::new(&xx, coeffs).unwrap();
+        assert_eq!(pp.degree(), 2);
+        assert!((pp.eval(0.5).unwrap() - 1.25).abs() < 1e-12);
+        assert!((pp.eval(2.0).unwrap() - 4.0).abs() < 1e-12);
+        assert!((pp.derivative(0.5, 1).unwrap() - 1.0).abs() < 1e-12);
+        assert!((pp.derivative(0.5, 2).unwrap() - 2.0).abs() < 1e-12);
+        assert!(pp.derivative(0.5, 3).unwrap().abs() < 1e-12);
+        assert!((pp.derivative_poly(1).eval(2.5).unwrap() - 2.0).abs() < 1e-12);
+
+        // int_0^1 (1 + x^2) = 4/3, int_1^3 2x = 8
+        assert!((pp.integral(0.0, 3.0).unwrap() - (4.0 / 3.0 + 8.0)).abs() < 1e-12);
+        assert!(
+            (pp.integral(3.0, 0.5).unwrap() + (4.0 / 3.0 - 0.5 - 1.0 / 24.0 + 8.0)).abs() < 1e-12
+        );
+        let anti = pp.antiderivative();
+        assert!((anti.eval(2.0).unwrap() - (4.0 / 3.0 + 3.0)).abs() < 1e-12);
+
+        assert!(PiecewisePoly::new(&xx, Matrix::new(1, 3, vec![0.0; 3])).is_err());
+    }
+}
@@ -1,68 +1,126 @@
-use crate::accessor_impl;
+use crate::{accessor_impl, interp::InterpError};
 
-use super::Interp;
+use super::{Cursor, Extrapolation, Interp, InterpFloat};
 
-pub struct Rational1D<'a> {
-    n: usize,
+#[derive(Debug, Clone)]
+pub struct Rational1D<T>
+where
+    T: InterpFloat,
+{
     mm: usize,
-    jsav: usize,
-    dj: usize,
-    cor: usize,
-    xx: &'a Vec<f64>,
-    yy: &'a Vec<f64>,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    xx: Vec<T>,
+    yy: Vec<T>,
 }
 
-impl<'a> Rational1D<'a> {
-    pub fn new(xx: &'a Vec<f64>, yy: &'a Vec<f64>, mm: usize) -> Self {
-        Self {
-            n: xx.len(),
+impl<T> Rational1D<T>
+where
+    T: InterpFloat,
+{
+    /// Interpolates through the `mm` nodes around each point.
+    pub fn new(xx: &[T], yy: &[T], mm: usize) -> anyhow::Result<Self> {
+        if xx.len() != yy.len() || xx.len() < 2 || mm == 0 || mm > xx.len() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
             mm,
-            jsav: 0,
-            dj: 0,
-            cor: 0,
-            xx,
-            yy,
+            cursor: Cursor::new(xx.len()),
+            extrapolation: Extrapolation::default(),
+            xx: xx.to_vec(),
+            yy: yy.to_vec(),
+        })
+    }
+
+    /// Value at `x` and its error estimate. Points handled by the extrapolation policy
+    /// have a zero error estimate.
+    pub fn eval_with_error(&self, x: T) -> anyhow::Result<(T, T)> {
+        if let Some(y) = self.extrapolate(x)? {
+            return Ok((y, T::zero()));
+        }
+        let j = self.locate(x);
+        ratint(&self.xx[j..(j + self.mm)], &self.yy[j..(j + self.mm)], x)
+    }
+
+    /// Same as `eval_with_error`, with the cursor kept in the interpolator.
+    pub fn interp_with_error(&mut self, x: T) -> anyhow::Result<(T, T)> {
+        if let Some(y) = self.extrapolate(x)? {
+            return Ok((y, T::zero()));
         }
+        let mut cursor = self.cursor;
+        let j = self.hunt(&mut cursor, x);
+        self.cursor = cursor;
+        ratint(&self.xx[j..(j + self.mm)], &self.yy[j..(j + self.mm)], x)
     }
 }
 
-impl<'a> Interp for Rational1D<'a> {
-    accessor_impl!((get = n, set = set_n): usize);
-    accessor_impl!((get = mm, set = set_mm): usize);
-    accessor_impl!((get = jsav, set = set_jsav): usize);
-    accessor_impl!((get = dj, set = set_dj): usize);
-    accessor_impl!((get = cor, set = set_cor): usize);
+impl<T> Interp<T> for Rational1D<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
 
-    fn xx(&self) -> &Vec<f64> {
-        self.xx
+    fn xx(&self) -> &[T] {
+        &self.xx
     }
 
-    fn rawinterp(&self, j: usize, x: f64) -> anyhow::Result<f64> {
-        let xx = self.xx;
-        let yy = self.yy;
-        let mut c = vec![0.0; self.mm()];
-        let mut d = vec![0.0; self.mm()];
-        for i in 0..self.mm() {
-            if (x - xx[j + i]).abs() < 1e-7 {
-                return Ok(xx[j + i]);
-            }
-            c[i] = yy[j + i];
-            d[i] = yy[j + i];
+    fn rawinterp(&self, j: usize, x: T) -> anyhow::Result<T> {
+        let mm = self.mm;
+        Ok(ratint(&self.xx[j..(j + mm)], &self.yy[j..(j + mm)], x)?.0)
+    }
+}
+
+/// Bulirsch-Stoer algorithm: value at `x` of the diagonal rational function through all the
+/// points (xa\[i\], ya\[i\]), and the last correction as the error estimate.
+pub fn ratint<T>(xa: &[T], ya: &[T], x: T) -> anyhow::Result<(T, T)>
+where
+    T: InterpFloat,
+{
+    let mm = xa.len();
+    if ya.len() != mm {
+        return Err(InterpError::SizeNotMatch().into());
+    }
+    let tiny = T::min_positive_value();
+    let mut c = ya.to_vec();
+    let mut d: Vec<T> = ya.iter().map(|&v| v + tiny).collect();
+    let mut ns: isize = 0;
+    let mut hh = (x - xa[0]).abs();
+    for (i, &xi) in xa.iter().enumerate() {
+        let h = (x - xi).abs();
+        if h == T::zero() {
+            return Ok((ya[i], T::zero()));
+        } else if h < hh {
+            ns = i as isize;
+            hh = h;
         }
-        let mut y = c[0];
-        for m in 0..(self.mm() - 1) {
-            for i in 0..(self.mm() - m - 1) {
-                let xval = (x - xx[j + i]) / (x - xx[j + i + m + 1]);
-                let dnume = c[i + 1] * (c[i + 1] - d[i]);
-                let cnume = xval * d[i] * (c[i + 1] - d[i]);
-                let deno = xval * d[i] - c[i + 1];
-                d[i] = dnume / deno;
-                c[i] = cnume / deno;
+    }
+    let mut y = ya[ns as usize];
+    let mut dy = T::zero();
+    ns -= 1;
+    for m in 1..mm {
+        for i in 0..(mm - m) {
+            let w = c[i + 1] - d[i];
+            let h = xa[i + m] - x;
+            let t = (xa[i] - x) * d[i] / h;
+            let mut dd = t - c[i + 1];
+            if dd == T::zero() {
+                return Err(InterpError::Pole().into());
             }
-            y += c[0];
+            dd = w / dd;
+            d[i] = c[i + 1] * dd;
+            c[i] = t * dd;
         }
-        Ok(y)
+        dy = if ((2 * (ns + 1)) as usize) < mm - m {
+            c[(ns + 1) as usize]
+        } else {
+            ns -= 1;
+            d[(ns + 1) as usize]
+        };
+        y = y + dy;
     }
+    Ok((y, dy))
 }
 
 #[cfg(test)]
@@ -72,7 +130,7 @@ mod tests {
     #[test]
     fn test_rational1d() {
         let func = |x: f64| (x * x + 2.0 * x + 1.0) / (x + 2.0);
-        let xx = vec![1.0, 2.0, 3.0, 6.0, 8.0, 9.0, 10.0, 11.0, 12.0];
+        let xx: Vec<f64> = vec![1.0, 2.0, 3.0, 6.0, 8.0, 9.0, 10.0, 11.0, 12.0];
         let yy = vec![
             func(1.0),
             func(2.0),
@@ -84,7 +142,7 @@ mod tests {
             func(11.0),
             func(12.0),
         ];
-        let mut rat1d = Rational1D::new(&xx, &yy, 4);
+        let mut rat1d = Rational1D::new(&xx, &yy, 4).unwrap();
         println!("yy = {:?}", yy);
         let res = rat1d.interp(4.0).unwrap();
         let tval = func(4.0);
@@ -93,5 +151,54 @@ mod tests {
         let res = rat1d.interp(5.0).unwrap();
         let tval = func(5.0);
         assert!((res - tval).abs() < 1e-2, "y = {}, true = {}", res, tval);
+
+        let res = rat1d.interp(6.0).unwrap();
+        assert!((res - func(6.0)).abs() < 1e-10, "y = {}", res);
+
+        assert!(Rational1D::new(&xx, &yy, 13).is_err());
+        assert!(Rational1D::new(&xx[..1], &yy[..1], 1).is_err());
+        assert!(Rational1D::new(&xx[1..], &yy, 4).is_err());
+
+        let (y, dy) = rat1d.interp_with_error(4.5).unwrap();
+        let err = (y - func(4.5)).abs();
+        assert!(
+            err < 1e-2 && err < 10.0 * dy.abs(),
+            "err = {}, dy = {}",
+            err,
+            dy
+        );
+        // with 5 points the diagonal rational function reproduces func exactly
+        let (y, dy) = Rational1D::new(&xx, &yy, 5)
+            .unwrap()
+            .eval_with_error(4.5)
+            .unwrap();
+        assert!((y - func(4.5)).abs() < 1e-10, "y = {}", y);
+        // the estimate is the correction over the 4 point approximant
+        assert!((dy - err).abs() < 1e-3 * err, "dy = {}", dy);
+    }
+
+    #[test]
+    fn test_rational1d_error() {
+        // a function with a pole near the interval
+        let func = |x: f64| 1.0 / (x + 0.2) + x.sin();
+        let xx: Vec<f64> = (0..12).map(|i| i as f64 * 0.25).collect();
+        let yy: Vec<f64> = xx.iter().map(|&x| func(x)).collect();
+        let rat1d = Rational1D::new(&xx, &yy, 5).unwrap();
+        for x in [0.1, 1.3, 2.6] {
+            let (y, dy) = rat1d.eval_with_error(x).unwrap();
+            let err = (y - func(x)).abs();
+            assert!(
+                err < 0.05 && dy.abs() < 0.1,
+                "x = {}, err = {}, dy = {}",
+                x,
+                err,
+                dy
+            );
+        }
+        // next to the pole the estimate bounds the actual error
+        let (y, dy) = rat1d.eval_with_error(0.1).unwrap();
+        assert!((y - func(0.1)).abs() < dy.abs());
+        let (y, dy) = rat1d.eval_with_error(xx[3]).unwrap();
+        assert!((y - yy[3]).abs() < 1e-15 && dy == 0.0);
     }
 }
@@ -0,0 +1,461 @@
+use anyhow::Result;
+
+use crate::{interp::InterpError, linalg::banddiagonal::BandMatrix, MatLinAlgBound};
+
+use super::{spline1d::Spline1D, Cursor, Extrapolation, Interp, InterpFloat};
+
+/// Reinsch's cubic smoothing spline: the natural cubic spline g minimizing
+/// sum_i w\[i\] (yy\[i\] - g(xx\[i\]))^2 + lambda int g''(x)^2 dx.
+#[derive(Debug, Clone)]
+pub struct SmoothingSpline<T>
+where
+    T: InterpFloat,
+{
+    spline: Spline1D<T>,
+    lambda: T,
+    gcv: T,
+    dof: T,
+}
+
+/// The penalized system of Reinsch with the second derivatives at the inner nodes as unknowns:
+/// (R + lambda Q' W^-1 Q) gamma = Q' y, where R and Q are the tridiagonal matrices of the
+/// continuity conditions and Q' y are the differences of the slopes.
+struct Reinsch<T> {
+    h: Vec<T>,
+    wi: Vec<T>,
+    yy: Vec<T>,
+}
+
+/// Result of a fit for a given lambda.
+struct Fit<T> {
+    fitted: Vec<T>,
+    gcv: T,
+    dof: T,
+}
+
+impl<T> SmoothingSpline<T>
+where
+    T: InterpFloat + MatLinAlgBound,
+{
+    /// Smoothing spline for the smoothing parameter `lambda`. `w` are the weights of the
+    /// data points (the inverse variances), all 1 if `None`.
+    pub fn new(xx: &[T], yy: &[T], w: Option<&[T]>, lambda: T) -> Result<Self> {
+        let sys = Reinsch::new(xx, yy, w)?;
+        let fit = sys.fit(lambda)?;
+        SmoothingSpline::from_fit(xx, fit, lambda)
+    }
+
+    /// Smoothing spline with lambda minimizing the generalized cross-validation score
+    /// V(lambda) = n |W^1/2 (y - g)|^2 / tr(I - A)^2, A being the influence matrix.
+    pub fn gcv(xx: &[T], yy: &[T], w: Option<&[T]>) -> Result<Self> {
+        let sys = Reinsch::new(xx, yy, w)?;
+        let scale = sys.scale();
+        let ten = T::lit(10.0);
+        let score = |p: T| -> Result<T> { Ok(sys.fit(scale * ten.powf(p))?.gcv) };
+
+        // coarse search on log10(lambda / scale), then golden section around the minimum
+        let mut best = (T::zero(), T::infinity());
+        for k in -20..=20 {
+            let p = T::lit(k as f64 * 0.5);
+            let v = score(p)?;
+            if v < best.1 {
+                best = (p, v);
+            }
+        }
+        let ratio = T::lit(0.5 * (5.0f64.sqrt() - 1.0));
+        let (mut a, mut b) = (best.0 - T::lit(0.5), best.0 + T::lit(0.5));
+        let mut c = b - ratio * (b - a);
+        let mut d = a + ratio * (b - a);
+        let (mut fc, mut fd) = (score(c)?, score(d)?);
+        while b - a > T::lit(1e-4) {
+            if fc < fd {
+                b = d;
+                d = c;
+                fd = fc;
+                c = b - ratio * (b - a);
+                fc = score(c)?;
+            } else {
+                a = c;
+                c = d;
+                fc = fd;
+                d = a + ratio * (b - a);
+                fd = score(d)?;
+            }
+        }
+        let p = if fc < fd { c } else { d };
+        let p = if best.1 < fc.min(fd) { best.0 } else { p };
+        let lambda = scale * ten.powf(p);
+        let fit = sys.fit(lambda)?;
+        SmoothingSpline::from_fit(xx, fit, lambda)
+    }
+
+    fn from_fit(xx: &[T], fit: Fit<T>, lambda: T) -> Result<Self> {
+        Ok(Self {
+            spline: Spline1D::new(xx, &fit.fitted)?,
+            lambda,
+            gcv: fit.gcv,
+            dof: fit.dof,
+        })
+    }
+}
+
+impl<T> SmoothingSpline<T>
+where
+    T: InterpFloat,
+{
+    pub fn lambda(&self) -> T {
+        self.lambda
+    }
+
+    /// Values of the spline at the nodes.
+    pub fn fitted(&self) -> &[T] {
+        self.spline.yy()
+    }
+
+    /// Generalized cross-validation score of the fit.
+    pub fn gcv_score(&self) -> T {
+        self.gcv
+    }
+
+    /// Equivalent degrees of freedom tr(A), from n (interpolation) down to 2 (straight line).
+    pub fn dof(&self) -> T {
+        self.dof
+    }
+
+    /// The natural cubic spline through the fitted values.
+    pub fn spline(&self) -> &Spline1D<T> {
+        &self.spline
+    }
+}
+
+impl<T> Interp<T> for SmoothingSpline<T>
+where
+    T: InterpFloat,
+{
+    fn cursor(&self) -> Cursor {
+        self.spline.cursor()
+    }
+
+    fn set_cursor(&mut self, val: Cursor) {
+        self.spline.set_cursor(val);
+    }
+
+    fn extrapolation(&self) -> Extrapolation<T> {
+        self.spline.extrapolation()
+    }
+
+    fn set_extrapolation(&mut self, val: Extrapolation<T>) {
+        self.spline.set_extrapolation(val);
+    }
+
+    fn mm(&self) -> usize {
+        self.spline.mm()
+    }
+
+    fn xx(&self) -> &[T] {
+        self.spline.xx()
+    }
+
+    fn rawinterp(&self, jlo: usize, x: T) -> Result<T> {
+        self.spline.rawinterp(jlo, x)
+    }
+}
+
+impl<T> Reinsch<T>
+where
+    T: InterpFloat + MatLinAlgBound,
+{
+    fn new(xx: &[T], yy: &[T], w: Option<&[T]>) -> Result<Self> {
+        let n = xx.len();
+        if n < 3 || yy.len() != n || w.is_some_and(|w| w.len() != n) {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let h: Vec<T> = xx.windows(2).map(|v| v[1] - v[0]).collect();
+        if h.iter().any(|&v| v <= T::zero()) {
+            return Err(InterpError::IdenticalX().into());
+        }
+        let wi = match w {
+            Some(w) => w.iter().map(|&v| T::one() / v).collect(),
+            None => vec![T::one(); n],
+        };
+        Ok(Self {
+            h,
+            wi,
+            yy: yy.to_vec(),
+        })
+    }
+
+    /// Column j of Q (j = 1..n-2) has the entries k = j-1, j, j+1.
+    fn q(&self, k: usize, j: usize) -> T {
+        let h = &self.h;
+        if k + 1 == j {
+            T::one() / h[j - 1]
+        } else if k == j {
+            -T::one() / h[j - 1] - T::one() / h[j]
+        } else {
+            T::one() / h[j]
+        }
+    }
+
+    /// Bands of R (diagonal and first super-diagonal) and of Q' W^-1 Q (diagonal and two super-diagonals).
+    #[allow(clippy::type_complexity)]
+    fn bands(&self) -> ([Vec<T>; 2], [Vec<T>; 3]) {
+        let m = self.h.len() - 1;
+        let h = &self.h;
+        let mut r = [vec![T::zero(); m], vec![T::zero(); m]];
+        let mut b = [vec![T::zero(); m], vec![T::zero(); m], vec![T::zero(); m]];
+        for i in 0..m {
+            let j = i + 1;
+            r[0][i] = (h[j - 1] + h[j]) / T::lit(3.0);
+            if i + 1 < m {
+                r[1][i] = h[j] / T::lit(6.0);
+            }
+            for (off, band) in b.iter_mut().enumerate() {
+                if i + off >= m {
+                    continue;
+                }
+                // rows k of Q where both columns j and j + off are non-zero
+                band[i] = ((j + off - 1)..=(j + 1))
+                    .map(|k| self.q(k, j) * self.wi[k] * self.q(k, j + off))
+                    .fold(T::zero(), |acc, v| acc + v);
+            }
+        }
+        (r, b)
+    }
+
+    /// lambda at which the penalty and the fidelity terms are of the same order.
+    fn scale(&self) -> T {
+        let (r, b) = self.bands();
+        let tr = |v: &Vec<T>| v.iter().fold(T::zero(), |acc, &x| acc + x);
+        tr(&r[0]) / tr(&b[0])
+    }
+
+    fn fit(&self, lambda: T) -> Result<Fit<T>> {
+        let n = self.yy.len();
+        let m = n - 2;
+        let (r, b) = self.bands();
+        let a: Vec<Vec<T>> = (0..3)
+            .map(|off| {
+                (0..m)
+                    .map(|i| {
+                        let rv = if off < 2 { r[off][i] } else { T::zero() };
+                        rv + lambda * b[off][i]
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let bw = std::cmp::min(2, m - 1);
+        let mut mat = BandMatrix::new(m, bw, bw);
+        for (off, band) in a.iter().enumerate().take(bw + 1) {
+            for (i, &val) in band.iter().enumerate().take(m - off) {
+                mat.set(i, i + off, val)?;
+                mat.set(i + off, i, val)?;
+            }
+        }
+        let qty: Vec<T> = (0..m)
+            .map(|i| {
+                let j = i + 1;
+                (self.yy[j + 1] - self.yy[j]) / self.h[j]
+                    - (self.yy[j] - self.yy[j - 1]) / self.h[j - 1]
+            })
+            .collect();
+        let mut gamma = vec![T::zero(); m];
+        mat.solve(&qty, &mut gamma)?;
+
+        // g = y - lambda W^-1 Q gamma
+        let mut fitted = self.yy.clone();
+        for (i, &gi) in gamma.iter().enumerate() {
+            let j = i + 1;
+            for (k, f) in fitted.iter_mut().enumerate().skip(j - 1).take(3) {
+                *f -= lambda * self.wi[k] * self.q(k, j) * gi;
+            }
+        }
+
+        let nt = T::from_usize(n).unwrap();
+        let rss = (0..n).fold(T::zero(), |acc, i| {
+            let res = self.yy[i] - fitted[i];
+            acc + res * res / self.wi[i]
+        });
+        // tr(I - A) = m - tr(M^-1 R)
+        let sigma = inverse_band(&a)?;
+        let mut tr = T::zero();
+        for i in 0..m {
+            tr = tr + r[0][i] * sigma[0][i] + T::lit(2.0) * r[1][i] * sigma[1][i];
+        }
+        let tr = T::from_usize(m).unwrap() - tr;
+        Ok(Fit {
+            fitted,
+            gcv: nt * rss / (tr * tr),
+            dof: nt - tr,
+        })
+    }
+}
+
+/// Central band (diagonal and two super-diagonals) of the inverse of a symmetric positive definite
+/// pentadiagonal matrix given by the same bands, by the LDL' decomposition and the recurrence of
+/// Hutchinson and de Hoog.
+fn inverse_band<T>(a: &[Vec<T>]) -> Result<[Vec<T>; 3]>
+where
+    T: InterpFloat,
+{
+    let m = a[0].len();
+    let mut d = vec![T::zero(); m];
+    let mut l1 = vec![T::zero(); m];
+    let mut l2 = vec![T::zero(); m];
+    for i in 0..m {
+        let mut di = a[0][i];
+        if i >= 1 {
+            di = di - l1[i - 1] * l1[i - 1] * d[i - 1];
+        }
+        if i >= 2 {
+            di = di - l2[i - 2] * l2[i - 2] * d[i - 2];
+        }
+        if di <= T::zero() {
+            return Err(InterpError::ZeroDiagonal().into());
+        }
+        d[i] = di;
+        if i + 1 < m {
+            let mut v = a[1][i];
+            if i >= 1 {
+                v = v - l2[i - 1] * d[i - 1] * l1[i - 1];
+            }
+            l1[i] = v / di;
+        }
+        if i + 2 < m {
+            l2[i] = a[2][i] / di;
+        }
+    }
+
+    let mut s = [vec![T::zero(); m], vec![T::zero(); m], vec![T::zero(); m]];
+    // s[k][i] = inv[i][i + k], zero out of range
+    let get = |s: &[Vec<T>; 3], i: usize, j: usize| -> T {
+        let (i, j) = if i <= j { (i, j) } else { (j, i) };
+        if j >= m || j - i > 2 {
+            T::zero()
+        } else {
+            s[j - i][i]
+        }
+    };
+    for i in (0..m).rev() {
+        let s12 = -l1[i] * get(&s, i + 1, i + 1) - l2[i] * get(&s, i + 2, i + 1);
+        let s13 = -l1[i] * get(&s, i + 1, i + 2) - l2[i] * get(&s, i + 2, i + 2);
+        s[0][i] = T::one() / d[i] - l1[i] * s12 - l2[i] * s13;
+        s[1][i] = s12;
+        s[2][i] = s13;
+    }
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noisy_sine() -> (Vec<f64>, Vec<f64>) {
+        let xx: Vec<f64> = (0..60)
+            .map(|i| i as f64 * 0.1 + 0.01 * (i % 3) as f64)
+            .collect();
+        let mut seed = 7u64;
+        let yy = xx
+            .iter()
+            .map(|x| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                let r = (seed >> 33) as f64 / (1u64 << 31) as f64 - 0.5;
+                x.sin() + 0.2 * r
+            })
+            .collect();
+        (xx, yy)
+    }
+
+    #[test]
+    fn test_smoothing_spline_limits() {
+        let (xx, yy) = noisy_sine();
+        // lambda -> 0: interpolation
+        let sp = SmoothingSpline::new(&xx, &yy, None, 1e-10).unwrap();
+        for (f, y) in sp.fitted().iter().zip(yy.iter()) {
+            assert!((f - y).abs() < 1e-6);
+        }
+        assert!((sp.dof() - 60.0).abs() < 1e-3, "dof = {}", sp.dof());
+
+        // lambda -> infinity: least squares line
+        let sp = SmoothingSpline::new(&xx, &yy, None, 1e12).unwrap();
+        let n = xx.len() as f64;
+        let mx = xx.iter().sum::<f64>() / n;
+        let my = yy.iter().sum::<f64>() / n;
+        let sxy: f64 = xx
+            .iter()
+            .zip(yy.iter())
+            .map(|(x, y)| (x - mx) * (y - my))
+            .sum();
+        let sxx: f64 = xx.iter().map(|x| (x - mx) * (x - mx)).sum();
+        let slope = sxy / sxx;
+        for x in [0.5, 3.0, 5.5] {
+            let line = my + slope * (x - mx);
+            let res = sp.eval(x).unwrap();
+            assert!(
+                (res - line).abs() < 1e-4,
+                "x = {}, res = {}, line = {}",
+                x,
+                res,
+                line
+            );
+        }
+        assert!((sp.dof() - 2.0).abs() < 1e-3, "dof = {}", sp.dof());
+
+        // the trace of the influence matrix agrees with the one from explicit fits of unit vectors
+        let lambda = 0.01;
+        let sp = SmoothingSpline::new(&xx, &yy, None, lambda).unwrap();
+        let mut tr = 0.0;
+        for i in 0..xx.len() {
+            let mut e = vec![0.0; xx.len()];
+            e[i] = 1.0;
+            tr += SmoothingSpline::new(&xx, &e, None, lambda)
+                .unwrap()
+                .fitted()[i];
+        }
+        assert!(
+            (sp.dof() - tr).abs() < 1e-8,
+            "dof = {}, tr = {}",
+            sp.dof(),
+            tr
+        );
+    }
+
+    #[test]
+    fn test_smoothing_spline_gcv() {
+        let (xx, yy) = noisy_sine();
+        let sp = SmoothingSpline::gcv(&xx, &yy, None).unwrap();
+        let err_fit: f64 = xx
+            .iter()
+            .map(|x| (sp.eval(*x).unwrap() - x.sin()).powi(2))
+            .sum();
+        let err_raw: f64 = xx
+            .iter()
+            .zip(yy.iter())
+            .map(|(x, y)| (y - x.sin()).powi(2))
+            .sum();
+        assert!(
+            err_fit < 0.5 * err_raw,
+            "fit = {}, raw = {}",
+            err_fit,
+            err_raw
+        );
+        assert!(sp.dof() > 2.5 && sp.dof() < 20.0, "dof = {}", sp.dof());
+        for lambda in [0.1, 10.0] {
+            let other = SmoothingSpline::new(&xx, &yy, None, sp.lambda() * lambda).unwrap();
+            assert!(other.gcv_score() >= sp.gcv_score());
+        }
+
+        // weights: a point with a tiny weight is ignored
+        let mut y2 = yy.clone();
+        y2[30] += 10.0;
+        let mut w = vec![1.0; xx.len()];
+        w[30] = 1e-12;
+        let sp2 = SmoothingSpline::new(&xx, &y2, Some(&w), sp.lambda()).unwrap();
+        assert!((sp2.eval(xx[30]).unwrap() - sp.eval(xx[30]).unwrap()).abs() < 0.1);
+
+        assert!(SmoothingSpline::new(&xx[..2], &yy[..2], None, 1.0).is_err());
+    }
+}
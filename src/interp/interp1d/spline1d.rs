@@ -1,30 +1,66 @@
-use crate::accessor_impl;
+use crate::{
+    accessor_impl,
+    interp::InterpError,
+    linalg::tridiagonal::{cyclic, tridiag},
+    MatLinAlgBound, Matrix,
+};
 
-use super::Interp;
+use super::{
+    ppoly::{taylor, PiecewisePoly},
+    Cursor, Extrapolation, Interp, InterpFloat,
+};
 use anyhow::Result;
+use num::Float;
 
-pub struct Spline1D<'a> {
-    n: usize,
+/// End conditions of a cubic spline.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SplineBoundary<T> {
+    /// Zero second derivatives at both ends.
+    #[default]
+    Natural,
+    /// Given first derivatives at the first and the last points.
+    Clamped(T, T),
+    /// Continuous third derivative at the second and the second-to-last points.
+    NotAKnot,
+    /// Periodic with period xx\[n-1\] - xx\[0\]. yy\[0\] and yy\[n-1\] must be equal.
+    Periodic,
+}
+
+#[derive(Debug, Clone)]
+pub struct Spline1D<T>
+where
+    T: InterpFloat,
+{
     mm: usize,
-    jsav: usize,
-    dj: usize,
-    cor: usize,
-    xx: &'a Vec<f64>,
-    yy: &'a [f64],
-    y2: Vec<f64>,
+    cursor: Cursor,
+    extrapolation: Extrapolation<T>,
+    boundary: SplineBoundary<T>,
+    xx: Vec<T>,
+    yy: Vec<T>,
+    y2: Vec<T>,
 }
 
-impl<'a> Spline1D<'a> {
-    pub fn new(xx: &'a Vec<f64>, yy: &'a [f64]) -> Result<Self> {
+impl<T> Spline1D<T>
+where
+    T: InterpFloat + MatLinAlgBound,
+{
+    /// Natural cubic spline.
+    pub fn new(xx: &[T], yy: &[T]) -> Result<Self> {
+        Spline1D::with_boundary(xx, yy, SplineBoundary::Natural)
+    }
+
+    pub fn with_boundary(xx: &[T], yy: &[T], boundary: SplineBoundary<T>) -> Result<Self> {
+        if xx.len() != yy.len() || xx.len() < 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
         let mut obj = Self {
-            n: xx.len(),
             mm: 2,
-            jsav: 0,
-            dj: 0,
-            cor: 0,
-            xx,
-            yy,
-            y2: vec![0.0; xx.len()],
+            cursor: Cursor::new(xx.len()),
+            extrapolation: Extrapolation::default(),
+            boundary,
+            xx: xx.to_vec(),
+            yy: yy.to_vec(),
+            y2: vec![T::zero(); xx.len()],
         };
         match obj.calcy2() {
             Ok(_) => Ok(obj),
@@ -32,55 +68,170 @@ impl<'a> Spline1D<'a> {
         }
     }
 
+    pub fn boundary(&self) -> SplineBoundary<T> {
+        self.boundary
+    }
+
+    /// Second derivatives at the nodes.
+    pub fn y2(&self) -> &[T] {
+        &self.y2
+    }
+
+    /// Compute the second derivatives at the nodes from the continuity of the first derivatives:
+    /// h\[i-1\] y2\[i-1\] / 6 + (h\[i-1\] + h\[i\]) y2\[i\] / 3 + h\[i\] y2\[i+1\] / 6 = s\[i\] - s\[i-1\],
+    /// where h and s are the widths and the slopes of the intervals, closed by the boundary condition.
     pub fn calcy2(&mut self) -> Result<()> {
-        let mut l = vec![0.0; self.n];
-        let mut u = vec![0.0; self.n];
-        let mut d = vec![0.0; self.n];
-        let x = self.xx;
-        let y = self.yy;
-        let mut lhs1 = (y[1] - y[0]) / (x[1] - x[0]);
-
-        for i in 1..(self.n - 1) {
-            let lhs0 = lhs1;
-            lhs1 = (y[i + 1] - y[i]) / (x[i + 1] - x[i]);
-            if i > 1 {
-                let a = (x[i] - x[i - 1]) / 6.0;
-                l[i] = a / d[i - 1];
+        let n = self.n();
+        let x = &self.xx;
+        let y = &self.yy;
+        let (three, six) = (T::lit(3.0), T::lit(6.0));
+        let h: Vec<T> = (0..(n - 1)).map(|i| x[i + 1] - x[i]).collect();
+        let s: Vec<T> = (0..(n - 1)).map(|i| (y[i + 1] - y[i]) / h[i]).collect();
+        let mut a = vec![T::zero(); n];
+        let mut b = vec![T::one(); n];
+        let mut c = vec![T::zero(); n];
+        let mut r = vec![T::zero(); n];
+        for i in 1..(n - 1) {
+            a[i] = h[i - 1] / six;
+            b[i] = (h[i - 1] + h[i]) / three;
+            c[i] = h[i] / six;
+            r[i] = s[i] - s[i - 1];
+        }
+        match self.boundary {
+            SplineBoundary::Natural => tridiag(&a, &b, &c, &r, &mut self.y2),
+            SplineBoundary::Clamped(d0, d1) => {
+                b[0] = h[0] / three;
+                c[0] = h[0] / six;
+                r[0] = s[0] - d0;
+                a[n - 1] = h[n - 2] / six;
+                b[n - 1] = h[n - 2] / three;
+                r[n - 1] = d1 - s[n - 2];
+                tridiag(&a, &b, &c, &r, &mut self.y2)
             }
-            let b = (x[i + 1] - x[i - 1]) / 3.0;
-            d[i] = b - l[i] * u[i - 1];
-            if i < self.n - 2 {
-                u[i] = (x[i + 1] - x[i]) / 6.0;
+            SplineBoundary::NotAKnot if n < 4 => {
+                // a single polynomial of degree n - 1 through all the points
+                let y2 = if n == 3 {
+                    T::lit(2.0) * (s[1] - s[0]) / (x[2] - x[0])
+                } else {
+                    T::zero()
+                };
+                self.y2.iter_mut().for_each(|v| *v = y2);
+                Ok(())
+            }
+            SplineBoundary::NotAKnot => {
+                // y2[0] and y2[n-1] are linear extrapolations of their neighbours,
+                // which are substituted into the second and the second-to-last rows.
+                let (h0, h1) = (h[0], h[1]);
+                b[1] += a[1] * (h0 + h1) / h1;
+                c[1] -= a[1] * h0 / h1;
+                let (h0, h1) = (h[n - 3], h[n - 2]);
+                b[n - 2] += c[n - 2] * (h0 + h1) / h0;
+                a[n - 2] -= c[n - 2] * h1 / h0;
+                let m = n - 1;
+                tridiag(&a[1..m], &b[1..m], &c[1..m], &r[1..m], &mut self.y2[1..m])?;
+                self.y2[0] = ((h[0] + h[1]) * self.y2[1] - h[0] * self.y2[2]) / h[1];
+                self.y2[m] = ((h0 + h1) * self.y2[m - 1] - h1 * self.y2[m - 2]) / h0;
+                Ok(())
+            }
+            SplineBoundary::Periodic => {
+                let scale = T::one().max(Float::abs(y[0]));
+                if Float::abs(y[n - 1] - y[0]) > T::lit(1e-7) * scale {
+                    return Err(InterpError::NotPeriodic().into());
+                }
+                if n < 4 {
+                    return Err(InterpError::SizeNotMatch().into());
+                }
+                let m = n - 1;
+                a[0] = h[m - 1] / six;
+                b[0] = (h[m - 1] + h[0]) / three;
+                c[0] = h[0] / six;
+                r[0] = s[0] - s[m - 1];
+                let corner = h[m - 1] / six;
+                cyclic(
+                    &a[..m],
+                    &b[..m],
+                    &c[..m],
+                    corner,
+                    corner,
+                    &r[..m],
+                    &mut self.y2[..m],
+                )?;
+                self.y2[m] = self.y2[0];
+                Ok(())
             }
-            self.y2[i] = lhs1 - lhs0 - self.y2[i - 1] * l[i];
-        }
-        for i in (1..(self.n - 1)).rev() {
-            let tmp = (self.y2[i] - u[i] * self.y2[i + 1]) / d[i];
-            self.y2[i] = tmp;
         }
-        Ok(())
     }
 }
 
-impl<'a> Interp for Spline1D<'a> {
-    accessor_impl!((get = n, set = set_n): usize);
-    accessor_impl!((get = mm, set = set_mm): usize);
-    accessor_impl!((get = jsav, set = set_jsav): usize);
-    accessor_impl!((get = dj, set = set_dj): usize);
-    accessor_impl!((get = cor, set = set_cor): usize);
+impl<T> Spline1D<T>
+where
+    T: InterpFloat,
+{
+    pub fn yy(&self) -> &[T] {
+        &self.yy
+    }
+
+    /// Coefficients of the cubic on \[xx\[j\], xx\[j+1\]\] in powers of (x - xx\[j\]).
+    fn local_coeffs(&self, j: usize) -> [T; 4] {
+        let h = self.xx[j + 1] - self.xx[j];
+        let six = T::lit(6.0);
+        let (m0, m1) = (self.y2[j], self.y2[j + 1]);
+        [
+            self.yy[j],
+            (self.yy[j + 1] - self.yy[j]) / h - h * (T::lit(2.0) * m0 + m1) / six,
+            m0 / T::lit(2.0),
+            (m1 - m0) / (six * h),
+        ]
+    }
 
-    fn xx(&self) -> &Vec<f64> {
-        self.xx
+    pub fn to_ppoly(&self) -> PiecewisePoly<T> {
+        let data = (0..(self.n() - 1))
+            .flat_map(|j| self.local_coeffs(j))
+            .collect();
+        PiecewisePoly::new(&self.xx, Matrix::new(self.n() - 1, 4, data))
+            .unwrap()
+            .with_extrapolation(self.extrapolation)
     }
 
-    fn rawinterp(&self, j: usize, x: f64) -> anyhow::Result<f64> {
+    /// `order`-th derivative at `x`.
+    pub fn derivative(&self, x: T, order: usize) -> Result<T> {
+        self.check_range(x)?;
+        let j = self.locate(x);
+        Ok(taylor(&self.local_coeffs(j), x - self.xx[j], order))
+    }
+
+    /// Definite integral over \[a, b\].
+    pub fn integral(&self, a: T, b: T) -> Result<T> {
+        self.to_ppoly().integral(a, b)
+    }
+
+    /// Antiderivative which is zero at xx\[0\].
+    pub fn antiderivative(&self) -> PiecewisePoly<T> {
+        self.to_ppoly().antiderivative()
+    }
+}
+
+impl<T> Interp<T> for Spline1D<T>
+where
+    T: InterpFloat,
+{
+    accessor_impl!((get = mm): usize);
+    accessor_impl!((get = cursor, set = set_cursor): Cursor);
+    accessor_impl!((get = extrapolation, set = set_extrapolation): Extrapolation<T>);
+
+    fn xx(&self) -> &[T] {
+        &self.xx
+    }
+
+    fn rawinterp(&self, j: usize, x: T) -> anyhow::Result<T> {
         if self.xx[j] == self.xx[j + 1] {
             return Ok(self.yy[j]);
         }
-        let a = (self.xx[j + 1] - x) / (self.xx[j + 1] - self.xx[j]);
-        let b = 1.0 - a;
-        let c = (a * a * a - a) * (self.xx[j + 1] - self.xx[j]).powi(2) / 6.0;
-        let d = (b * b * b - b) * (self.xx[j + 1] - self.xx[j]).powi(2) / 6.0;
+        let h = self.xx[j + 1] - self.xx[j];
+        let a = (self.xx[j + 1] - x) / h;
+        let b = T::one() - a;
+        let c = (a * a * a - a) * h * h / T::lit(6.0);
+        let d = (b * b * b - b) * h * h / T::lit(6.0);
         let y = a * self.yy[j] + b * self.yy[j + 1] + c * self.y2[j] + d * self.y2[j + 1];
         Ok(y)
     }
@@ -92,12 +243,110 @@ mod tests {
 
     #[test]
     fn test_spline1d() {
-        let x = vec![0.0, 2.0, 3.0, 5.0, 7.0, 8.0];
+        let x: Vec<f64> = vec![0.0, 2.0, 3.0, 5.0, 7.0, 8.0];
         let y = vec![4.0, 2.0, 6.0, 8.0, 6.0, 8.0];
         let sp1d = Spline1D::new(&x, &y);
         assert!(sp1d.is_ok());
         let mut sp1d = sp1d.unwrap();
         let y = sp1d.interp(6.0).unwrap();
         assert!((y - 6.50358166).abs() < 1e-5, "y = {}", y);
+
+        let sp1d = sp1d.with_extrapolation(Extrapolation::Clamp);
+        assert!((sp1d.eval(9.0).unwrap() - 8.0).abs() < 1e-10);
+        assert!((sp1d.eval(-1.0).unwrap() - 4.0).abs() < 1e-10);
+        let sp1d = sp1d.with_extrapolation(Extrapolation::Error);
+        assert!(sp1d.eval(8.5).is_err());
+    }
+
+    #[test]
+    fn test_spline1d_boundary() {
+        let x: Vec<f64> = vec![0.0, 1.0, 2.5, 3.0, 4.5, 6.0];
+        let y: Vec<f64> = vec![0.0, 0.8, 0.6, 0.1, -0.7, 0.0];
+        let cases = [
+            (
+                SplineBoundary::Clamped(1.0, -0.5),
+                [
+                    0.9584870748299363,
+                    -0.23061526832955412,
+                    0.19491389698736653,
+                ],
+            ),
+            (
+                SplineBoundary::NotAKnot,
+                [0.9590493333333309, -0.5706545185185184, 0.2029893333333322],
+            ),
+            (
+                SplineBoundary::Periodic,
+                [0.9742780952380932, -0.5317282539682533, 0.17167673469387817],
+            ),
+        ];
+        for (boundary, expect) in cases {
+            let sp1d = Spline1D::with_boundary(&x, &y, boundary).unwrap();
+            for (xp, yp) in [1.7, 5.2, 0.2].iter().zip(expect) {
+                let res = sp1d.eval(*xp).unwrap();
+                assert!((res - yp).abs() < 1e-10, "{:?}: y = {}", boundary, res);
+            }
+        }
+
+        // clamped and not-a-knot splines reproduce cubic polynomials
+        let p = |x: f64| x * x * x - 2.0 * x + 1.0;
+        let yc: Vec<f64> = x.iter().map(|&v| p(v)).collect();
+        let clamped = SplineBoundary::Clamped(-2.0, 106.0);
+        for boundary in [clamped, SplineBoundary::NotAKnot] {
+            let sp1d = Spline1D::with_boundary(&x, &yc, boundary).unwrap();
+            let res = sp1d.eval(3.7).unwrap();
+            assert!((res - p(3.7)).abs() < 1e-10, "{:?}: y = {}", boundary, res);
+        }
+        let sp1d = Spline1D::with_boundary(&x[..3], &yc[..3], SplineBoundary::NotAKnot).unwrap();
+        assert!((sp1d.eval(2.0).unwrap() - 6.0).abs() < 1e-10);
+
+        let res = Spline1D::with_boundary(&x, &yc, SplineBoundary::Periodic);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_spline1d_derivative() {
+        // the clamped spline reproduces the cubic
+        let p = |x: f64| x * x * x - 2.0 * x + 1.0;
+        let x: Vec<f64> = vec![0.0, 1.0, 2.5, 3.0, 4.5, 6.0];
+        let y: Vec<f64> = x.iter().map(|&v| p(v)).collect();
+        let clamped = SplineBoundary::Clamped(-2.0, 106.0);
+        let sp1d = Spline1D::with_boundary(&x, &y, clamped).unwrap();
+        for xp in [0.3, 2.7, 5.9] {
+            assert!((sp1d.derivative(xp, 0).unwrap() - p(xp)).abs() < 1e-10);
+            let dy = 3.0 * xp * xp - 2.0;
+            assert!((sp1d.derivative(xp, 1).unwrap() - dy).abs() < 1e-10);
+            assert!((sp1d.derivative(xp, 2).unwrap() - 6.0 * xp).abs() < 1e-10);
+            assert!((sp1d.derivative(xp, 3).unwrap() - 6.0).abs() < 1e-10);
+            assert!(sp1d.derivative(xp, 4).unwrap().abs() < 1e-10);
+        }
+        let q = |x: f64| x.powi(4) / 4.0 - x * x + x;
+        assert!((sp1d.integral(0.5, 5.5).unwrap() - (q(5.5) - q(0.5))).abs() < 1e-10);
+        let anti = sp1d.antiderivative();
+        assert!((anti.eval(2.0).unwrap() - q(2.0)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_spline1d_shared() {
+        let x: Vec<f64> = (0..50).map(|i| i as f64 * 0.2).collect();
+        let y: Vec<f64> = x.iter().map(|v| v.sin()).collect();
+        let sp1d = Spline1D::new(&x, &y).unwrap();
+        let xs: Vec<f64> = (0..200).map(|i| i as f64 * 0.049).collect();
+        let expect = sp1d.interp_many(&xs).unwrap();
+        std::thread::scope(|s| {
+            for k in 0..4 {
+                let sp1d = &sp1d;
+                let xs = &xs;
+                let expect = &expect;
+                s.spawn(move || {
+                    let mut cursor = Cursor::new(sp1d.n());
+                    for (i, x) in xs.iter().enumerate().skip(k) {
+                        let y = sp1d.eval_with(&mut cursor, *x).unwrap();
+                        assert!((y - expect[i]).abs() < 1e-12);
+                        assert!((y - x.sin()).abs() < 1e-3, "x = {}, y = {}", x, y);
+                    }
+                });
+            }
+        });
     }
 }
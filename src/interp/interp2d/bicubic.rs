@@ -1,57 +1,69 @@
 use anyhow::Result;
 
-use crate::{interp::InterpError, Matrix};
+use crate::{
+    interp::{linear::LinearInterp, strictly_monotone, Cursor, Interp, InterpError},
+    Matrix,
+};
 
-fn bcucof(
-    y: &Vec<f64>,
-    y1: &Vec<f64>,
-    y2: &Vec<f64>,
-    y12: &Vec<f64>,
+use super::fill_grid;
+
+/// Weights taking the corner values and scaled derivatives to the bicubic coefficients.
+#[rustfmt::skip]
+const WT: [[i8; 16]; 16] = [
+    [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0],
+    [-3, 0, 0, 3, 0, 0, 0, 0, -2, 0, 0, -1, 0, 0, 0, 0],
+    [2, 0, 0, -2, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0],
+    [0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0],
+    [0, 0, 0, 0, -3, 0, 0, 3, 0, 0, 0, 0, -2, 0, 0, -1],
+    [0, 0, 0, 0, 2, 0, 0, -2, 0, 0, 0, 0, 1, 0, 0, 1],
+    [-3, 3, 0, 0, -2, -1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, -3, 3, 0, 0, -2, -1, 0, 0],
+    [9, -9, 9, -9, 6, 3, -3, -6, 6, -6, -3, 3, 4, 2, 1, 2],
+    [-6, 6, -6, 6, -4, -2, 2, 4, -3, 3, 3, -3, -2, -1, -1, -2],
+    [2, -2, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, -2, 0, 0, 1, 1, 0, 0],
+    [-6, 6, -6, 6, -3, -3, 3, 3, -4, 4, 2, -2, -2, -2, -1, -1],
+    [4, -4, 4, -4, 2, 2, -2, -2, 2, -2, -2, 2, 1, 1, 1, 1],
+];
+
+/// Coefficients c\[k\]\[l\] of sum_kl c\[k\]\[l\] t^k u^l on a cell of sides `d1` x `d2`,
+/// from the values and derivatives at its corners counterclockwise from the lower left.
+pub(crate) fn bcucof(
+    y: &[f64; 4],
+    y1: &[f64; 4],
+    y2: &[f64; 4],
+    y12: &[f64; 4],
     d1: f64,
     d2: f64,
-    c: &mut Matrix<f64>,
-) {
-    #[rustfmt::skip]
-    let wt_d: Vec<isize> = vec![
-        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
-        -3, 0, 0, 3, 0, 0, 0, 0, -2, 0, 0, -1, 0, 0, 0, 0,
-        2, 0, 0, -2, 0, 0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0,
-        0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0,
-        0, 0, 0, 0, -3, 0, 0, 3, 0, 0, 0, 0, -2, 0, 0, -1,
-        0, 0, 0, 0, 2, 0, 0, -2, 0, 0, 0, 0, 1, 0, 0, 1,
-        -3, 3, 0, 0, -2, -1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, -3, 3, 0, 0, -2, -1, 0, 0,
-        9, -9, 9, -9, 6, 3, -3, -6, 6, -6, -3, 3, 4, 2, 1, 2,
-        -6, 6, -6, 6, -4, -2, 2, 4, -3, 3, 3, -3, -2, -1, -1, -2,
-        2, -2, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0, 2, -2, 0, 0, 1, 1, 0, 0,
-        -6, 6, -6, 6, -3, -3, 3, 3, -4, 4, 2, -2, -2, -2, -1, -1,
-        4, -4, 4, -4, 2, 2, -2, -2, 2, -2, -2, 2, 1, 1, 1, 1,
-    ];
+) -> [[f64; 4]; 4] {
     let d1d2 = d1 * d2;
-    let mut cl: Vec<f64> = vec![0.0; 16];
-    let mut x: Vec<f64> = vec![0.0; 16];
-    let wt = Matrix::new(16, 16, wt_d);
+    let mut x = [0.0; 16];
     for i in 0..4 {
         x[i] = y[i];
         x[i + 4] = y1[i] * d1;
         x[i + 8] = y2[i] * d2;
         x[i + 12] = y12[i] * d1d2;
     }
-    for i in 0..16 {
-        let mut xx = 0.0;
-        for k in 0..16 {
-            xx += wt[i][k] as f64 * x[k];
-        }
-        cl[i] = xx;
+    let mut c = [[0.0; 4]; 4];
+    for (i, wt) in WT.iter().enumerate() {
+        c[i / 4][i % 4] = wt.iter().zip(x.iter()).map(|(&w, xk)| w as f64 * xk).sum();
     }
-    for i in 0..4 {
-        for j in 0..4 {
-            c[i][j] = cl[i * 4 + j];
-        }
+    c
+}
+
+/// Value and the derivatives in t and u of the bicubic with coefficients `c`.
+fn eval_cell(c: &[[f64; 4]; 4], t: f64, u: f64) -> (f64, f64, f64) {
+    let mut y = 0.0;
+    let mut yt = 0.0;
+    let mut yu = 0.0;
+    for i in (0..4).rev() {
+        y = t * y + ((c[i][3] * u + c[i][2]) * u + c[i][1]) * u + c[i][0];
+        yu = t * yu + (3.0 * c[i][3] * u + 2.0 * c[i][2]) * u + c[i][1];
+        yt = u * yt + (3.0 * c[3][i] * t + 2.0 * c[2][i]) * t + c[1][i];
     }
+    (y, yt, yu)
 }
 
 pub fn bcuint(
@@ -66,26 +78,198 @@ pub fn bcuint(
     x1: f64,
     x2: f64,
 ) -> Result<(f64, f64, f64)> {
-    let d1 = x1u - x1l;
-    let d2 = x2u - x2l;
-    let mut c = Matrix::new(4, 4, vec![0.0; 16]);
-    bcucof(y, y1, y2, y12, d1, d2, &mut c);
     if x1u == x1l || x2u == x2l {
         return Err(InterpError::IdenticalX())?;
     }
-    let t = (x1 - x1l) / d1;
-    let u = (x2 - x2l) / d2;
-    let mut y = 0.0;
-    let mut y1 = 0.0;
-    let mut y2 = 0.0;
-    for i in (0..3).rev() {
-        y = t * y + ((c[i][3] * u + c[i][2]) * u + c[i][i]) * u + c[i][0];
-        y2 = t * y2 + (3.0 * c[i][3] * u + 2.0 * c[i][2]) * u + c[i][1];
-        y1 = t * y1 + (3.0 * c[3][i] * t + 2.0 * c[2][i]) * t + c[1][i];
+    if y.len() != 4 || y1.len() != 4 || y2.len() != 4 || y12.len() != 4 {
+        return Err(InterpError::SizeNotMatch())?;
     }
-    y1 /= d1;
-    y2 /= d2;
-    Ok((y, y1, y2))
+    let corners = |v: &Vec<f64>| [v[0], v[1], v[2], v[3]];
+    let d1 = x1u - x1l;
+    let d2 = x2u - x2l;
+    let c = bcucof(
+        &corners(y),
+        &corners(y1),
+        &corners(y2),
+        &corners(y12),
+        d1,
+        d2,
+    );
+    let (y, y1, y2) = eval_cell(&c, (x1 - x1l) / d1, (x2 - x2l) / d2);
+    Ok((y, y1 / d1, y2 / d2))
+}
+
+/// Bicubic interpolation on a rectilinear grid. The derivatives at the nodes are estimated
+/// by second order finite differences, central inside the grid and one-sided at the border.
+/// Outside the grid the border cells are extended.
+#[derive(Debug, Clone)]
+pub struct BicubicGrid {
+    y: Matrix<f64>,
+    y1: Matrix<f64>,
+    y2: Matrix<f64>,
+    y12: Matrix<f64>,
+    x1interp: LinearInterp<f64>,
+    x2interp: LinearInterp<f64>,
+    x1cursor: Cursor,
+    x2cursor: Cursor,
+}
+
+impl BicubicGrid {
+    pub fn new(x1v: &[f64], x2v: &[f64], y: &Matrix<f64>) -> Result<Self> {
+        let m = x1v.len();
+        let n = x2v.len();
+        if m < 2 || n < 2 || y.rows() != m || y.cols() != n {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if !strictly_monotone(x1v) || !strictly_monotone(x2v) {
+            return Err(InterpError::IdenticalX().into());
+        }
+        let w1: Vec<Vec<(usize, f64)>> = (0..m).map(|i| fd_weights(x1v, i)).collect();
+        let w2: Vec<Vec<(usize, f64)>> = (0..n).map(|j| fd_weights(x2v, j)).collect();
+        let mut y1 = Matrix::new(m, n, vec![0.0; m * n]);
+        let mut y2 = Matrix::new(m, n, vec![0.0; m * n]);
+        let mut y12 = Matrix::new(m, n, vec![0.0; m * n]);
+        for i in 0..m {
+            for j in 0..n {
+                y1[i][j] = w1[i].iter().map(|&(k, w)| w * y[k][j]).sum();
+                y2[i][j] = w2[j].iter().map(|&(l, w)| w * y[i][l]).sum();
+                y12[i][j] = w1[i]
+                    .iter()
+                    .flat_map(|&(k, wk)| w2[j].iter().map(move |&(l, wl)| wk * wl * y[k][l]))
+                    .sum();
+            }
+        }
+        Ok(Self {
+            y: y.clone(),
+            y1,
+            y2,
+            y12,
+            x1interp: LinearInterp::new(x1v, x1v)?,
+            x2interp: LinearInterp::new(x2v, x2v)?,
+            x1cursor: Cursor::new(m),
+            x2cursor: Cursor::new(n),
+        })
+    }
+
+    pub fn interp(&mut self, x1p: f64, x2p: f64) -> Result<f64> {
+        Ok(self.gradient(x1p, x2p)?.0)
+    }
+
+    /// Value and gradient \[dy/dx1, dy/dx2\].
+    pub fn gradient(&mut self, x1p: f64, x2p: f64) -> Result<(f64, Vec<f64>)> {
+        let i = self.x1interp.hunt(&mut self.x1cursor, x1p);
+        let j = self.x2interp.hunt(&mut self.x2cursor, x2p);
+        let x1 = self.x1interp.xx();
+        let x2 = self.x2interp.xx();
+        let d1 = x1[i + 1] - x1[i];
+        let d2 = x2[j + 1] - x2[j];
+        let c = self.coefficients(i, j);
+        let (y, y1, y2) = eval_cell(&c, (x1p - x1[i]) / d1, (x2p - x2[j]) / d2);
+        Ok((y, vec![y1 / d1, y2 / d2]))
+    }
+
+    /// Values at all the points (x1s\[i\], x2s\[j\]) as a x1s.len() x x2s.len() matrix.
+    /// Each coordinate is located only once, hunting from the previous one, and the
+    /// coefficients of each cell holding some of the points are computed once.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        let rows = cells(&self.x1interp, x1s)?;
+        let cols = cells(&self.x2interp, x2s)?;
+        // the cells hit along each axis, and the position of each point's cell among them
+        let hit = |pts: &[(usize, f64)]| -> (Vec<usize>, Vec<usize>) {
+            let mut cells: Vec<usize> = pts.iter().map(|&(i, _)| i).collect();
+            cells.sort_unstable();
+            cells.dedup();
+            let pos = pts
+                .iter()
+                .map(|(i, _)| cells.binary_search(i).unwrap())
+                .collect();
+            (cells, pos)
+        };
+        let (icells, ipos) = hit(&rows);
+        let (jcells, jpos) = hit(&cols);
+        let coefs: Vec<[[f64; 4]; 4]> = icells
+            .iter()
+            .flat_map(|&i| jcells.iter().map(move |&j| (i, j)))
+            .map(|(i, j)| self.coefficients(i, j))
+            .collect();
+        fill_grid(rows.len(), cols.len(), |r, out| {
+            let t = rows[r].1;
+            let base = ipos[r] * jcells.len();
+            for ((y, &(_, u)), &pos) in out.iter_mut().zip(cols.iter()).zip(jpos.iter()) {
+                let c = &coefs[base + pos];
+                *y = (0..4).rev().fold(0.0, |acc, k| {
+                    t * acc + ((c[k][3] * u + c[k][2]) * u + c[k][1]) * u + c[k][0]
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn coefficients(&self, i: usize, j: usize) -> [[f64; 4]; 4] {
+        // corners counterclockwise from (x1[i], x2[j])
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let pick = |mat: &Matrix<f64>| corners.map(|(k, l)| mat[k][l]);
+        let x1 = self.x1interp.xx();
+        let x2 = self.x2interp.xx();
+        bcucof(
+            &pick(&self.y),
+            &pick(&self.y1),
+            &pick(&self.y2),
+            &pick(&self.y12),
+            x1[i + 1] - x1[i],
+            x2[j + 1] - x2[j],
+        )
+    }
+}
+
+/// Cell index and the scaled coordinate in it for each of `xs`.
+fn cells(interp: &LinearInterp<f64>, xs: &[f64]) -> Result<Vec<(usize, f64)>> {
+    let xv = interp.xx();
+    let mut cursor = Cursor::new(xv.len());
+    xs.iter()
+        .map(|&x| {
+            let i = interp.hunt(&mut cursor, x);
+            let d = xv[i + 1] - xv[i];
+            if d == 0.0 {
+                return Err(InterpError::IdenticalX().into());
+            }
+            Ok((i, (x - xv[i]) / d))
+        })
+        .collect()
+}
+
+/// Weights of the derivative at xv\[i\] of the parabola through three neighbouring nodes
+/// (the line through two if there are only two).
+fn fd_weights(xv: &[f64], i: usize) -> Vec<(usize, f64)> {
+    let n = xv.len();
+    if n == 2 {
+        let w = 1.0 / (xv[1] - xv[0]);
+        return vec![(0, -w), (1, w)];
+    }
+    let lo = std::cmp::min(i.saturating_sub(1), n - 3);
+    let nodes = [lo, lo + 1, lo + 2];
+    nodes
+        .iter()
+        .map(|&k| {
+            let denom: f64 = nodes
+                .iter()
+                .filter(|&&l| l != k)
+                .map(|&l| xv[k] - xv[l])
+                .product();
+            let numer: f64 = nodes
+                .iter()
+                .filter(|&&l| l != k)
+                .map(|&l| {
+                    nodes
+                        .iter()
+                        .filter(|&&p| p != k && p != l)
+                        .map(|&p| xv[i] - xv[p])
+                        .product::<f64>()
+                })
+                .sum();
+            (k, numer / denom)
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -94,15 +278,121 @@ mod tests {
 
     #[test]
     fn test_bicubic() {
-        let y: Vec<f64> = vec![0.0, 1.0, 5.0, 4.0];
-        let y1: Vec<f64> = vec![0.0, 2.0, 2.0, 0.0];
-        let y2: Vec<f64> = vec![0.0, 0.0, 4.0, 4.0];
-        let y12: Vec<f64> = vec![0.0, 2.0, 6.0, 4.0];
-        let res = bcuint(&y, &y1, &y2, &y12, 0.0, 1.0, 0.0, 1.0, 0.5, 0.5);
-        assert!(res.is_ok());
-        // println!("ay = {}, ay1 = {}, ay2 = {}", ay, ay1, ay2);
-        // assert!((ay - 0.75).abs() < 1e-5);
-        // assert!((ay1 - 1.0).abs() < 1e-5);
-        // assert!((ay2 - 2.0).abs() < 1e-5);
+        // x1^2 + 2 x2^2 + x1 x2 at the corners of the unit square
+        let y: Vec<f64> = vec![0.0, 1.0, 4.0, 2.0];
+        let y1: Vec<f64> = vec![0.0, 2.0, 3.0, 1.0];
+        let y2: Vec<f64> = vec![0.0, 1.0, 5.0, 4.0];
+        let y12: Vec<f64> = vec![1.0, 1.0, 1.0, 1.0];
+        let (ay, ay1, ay2) = bcuint(&y, &y1, &y2, &y12, 0.0, 1.0, 0.0, 1.0, 0.5, 0.5).unwrap();
+        assert!((ay - 1.0).abs() < 1e-12, "ay = {}", ay);
+        assert!((ay1 - 1.5).abs() < 1e-12, "ay1 = {}", ay1);
+        assert!((ay2 - 2.5).abs() < 1e-12, "ay2 = {}", ay2);
+
+        assert!(bcuint(&y, &y1, &y2, &y12, 0.0, 0.0, 0.0, 1.0, 0.5, 0.5).is_err());
+        assert!(bcuint(
+            &y,
+            &y1,
+            &y2,
+            &y12[1..].to_vec(),
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+            0.5,
+            0.5
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_bicubic_cell() {
+        // any bicubic polynomial is reproduced from exact corner data
+        let f = |a: f64, b: f64| a.powi(3) + 2.0 * a * a * b - b.powi(3) + a * b * b * b;
+        let f1 = |a: f64, b: f64| 3.0 * a * a + 4.0 * a * b + b * b * b;
+        let f2 = |a: f64, b: f64| 2.0 * a * a - 3.0 * b * b + 3.0 * a * b * b;
+        let f12 = |a: f64, b: f64| 4.0 * a + 3.0 * b * b;
+        let (x1l, x1u, x2l, x2u) = (1.0, 2.0, 0.5, 2.0);
+        let corners = [(x1l, x2l), (x1u, x2l), (x1u, x2u), (x1l, x2u)];
+        let pick = |g: &dyn Fn(f64, f64) -> f64| -> Vec<f64> {
+            corners.iter().map(|&(a, b)| g(a, b)).collect()
+        };
+        let (y, y1, y2, y12) = (pick(&f), pick(&f1), pick(&f2), pick(&f12));
+        for &(a, b) in &[(1.2, 0.7), (1.9, 1.9), (1.5, 1.25), (1.0, 2.0)] {
+            let (ay, ay1, ay2) = bcuint(&y, &y1, &y2, &y12, x1l, x1u, x2l, x2u, a, b).unwrap();
+            assert!((ay - f(a, b)).abs() < 1e-12, "ay = {}", ay);
+            assert!((ay1 - f1(a, b)).abs() < 1e-12, "ay1 = {}", ay1);
+            assert!((ay2 - f2(a, b)).abs() < 1e-12, "ay2 = {}", ay2);
+        }
+    }
+
+    #[test]
+    fn test_bicubic_grid() {
+        // finite differences are exact for a bilinear function
+        let x1 = vec![0.0, 1.0, 2.5, 3.0, 5.0];
+        let x2 = vec![-1.0, 0.0, 0.5, 2.0];
+        let f = |a: f64, b: f64| 2.0 + a * b - 3.0 * a + b;
+        let data: Vec<f64> = x1
+            .iter()
+            .flat_map(|&a| x2.iter().map(move |&b| f(a, b)))
+            .collect();
+        let y = Matrix::new(x1.len(), x2.len(), data);
+        let mut grid = BicubicGrid::new(&x1, &x2, &y).unwrap();
+        for &(a, b) in &[(0.5, -0.5), (2.7, 1.9), (4.9, 0.1), (1.0, 0.5)] {
+            let (ay, g) = grid.gradient(a, b).unwrap();
+            let (ay1, ay2) = (g[0], g[1]);
+            assert!((ay - f(a, b)).abs() < 1e-12, "ay = {}", ay);
+            assert!((ay1 - (b - 3.0)).abs() < 1e-12, "ay1 = {}", ay1);
+            assert!((ay2 - (a + 1.0)).abs() < 1e-12, "ay2 = {}", ay2);
+        }
+        let (x1s, x2s) = ([-0.5, 0.5, 1.0, 2.7, 4.9, 6.0], [-0.5, -0.2, 0.1, 1.9, 2.5]);
+        let zz = grid.interp_grid(&x1s, &x2s).unwrap();
+        for (i, &a) in x1s.iter().enumerate() {
+            for (j, &b) in x2s.iter().enumerate() {
+                let res = grid.interp(a, b).unwrap();
+                assert!((zz[i][j] - res).abs() < 1e-12, "{} != {}", zz[i][j], res);
+            }
+        }
+        // unsorted points share the cell coefficients
+        let (x1r, x2r) = ([4.9, -0.5, 2.7, 0.5], [2.5, -0.5, 1.9, -0.2]);
+        let zr = grid.interp_grid(&x1r, &x2r).unwrap();
+        for (i, &a) in x1r.iter().enumerate() {
+            for (j, &b) in x2r.iter().enumerate() {
+                assert!((zr[i][j] - grid.interp(a, b).unwrap()).abs() < 1e-12);
+            }
+        }
+
+        // smooth function on a fine grid
+        let x1: Vec<f64> = (0..41).map(|i| i as f64 * 0.05).collect();
+        let x2: Vec<f64> = (0..31).map(|i| -1.0 + i as f64 * 0.1).collect();
+        let f = |a: f64, b: f64| a.sin() * (0.5 * b).cos();
+        let data: Vec<f64> = x1
+            .iter()
+            .flat_map(|&a| x2.iter().map(move |&b| f(a, b)))
+            .collect();
+        let y = Matrix::new(x1.len(), x2.len(), data);
+        let mut grid = BicubicGrid::new(&x1, &x2, &y).unwrap();
+        for &(a, b) in &[(0.33, -0.77), (1.234, 1.55), (1.99, 0.01)] {
+            let (ay, g) = grid.gradient(a, b).unwrap();
+            let ay1 = g[0];
+            assert!((ay - f(a, b)).abs() < 1e-4, "ay = {}", ay);
+            assert!(
+                (ay1 - a.cos() * (0.5 * b).cos()).abs() < 1e-2,
+                "ay1 = {}",
+                ay1
+            );
+        }
+
+        assert!(BicubicGrid::new(&x1[1..], &x2, &y).is_err());
+        // the table is owned
+        drop(y);
+        assert!((grid.interp(0.5, 0.5).unwrap() - f(0.5, 0.5)).abs() < 1e-4);
+
+        let x1 = vec![0.0, 1.0, 1.0, 2.0];
+        let y = Matrix::new(4, 2, vec![0.0; 8]);
+        let res = BicubicGrid::new(&x1, &[0.0, 1.0], &y);
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<InterpError>(),
+            Some(InterpError::IdenticalX())
+        ));
     }
 }
@@ -1,43 +1,184 @@
+use anyhow::Result;
+
 use crate::{
-    interp::{spline1d::Spline1D, Interp},
+    interp::{
+        linear::LinearInterp, spline1d::Spline1D, strictly_monotone, Cursor, Interp, InterpError,
+    },
     Matrix,
 };
 
-pub struct Spline2D<'a> {
-    _m: usize,
-    _n: usize,
-    _y: &'a Matrix<f64>,
-    x1: &'a Vec<f64>,
-    srp: Vec<Spline1D<'a>>,
+use super::{bicubic::bcucof, fill_grid};
+
+/// Bicubic spline surface: the tensor product of natural cubic splines along each axis.
+/// Each grid cell stores the coefficients c\[k\]\[l\] of sum_kl c\[k\]\[l\] t^k u^l, where t and u
+/// are the coordinates scaled to \[0, 1\] in the cell. Outside the grid the border patches
+/// are extended.
+#[derive(Debug, Clone)]
+pub struct Spline2D {
+    x1interp: LinearInterp<f64>,
+    x2interp: LinearInterp<f64>,
+    x1cursor: Cursor,
+    x2cursor: Cursor,
+    patches: Vec<[[f64; 4]; 4]>,
 }
 
-impl<'a> Spline2D<'a> {
-    pub fn new(x1v: &'a Vec<f64>, x2v: &'a Vec<f64>, ym: &'a Matrix<f64>) -> Self {
+impl Spline2D {
+    pub fn new(x1v: &[f64], x2v: &[f64], ym: &Matrix<f64>) -> Result<Self> {
         let m = x1v.len();
-        let srp = (0..m)
-            .map(|i| Spline1D::new(x2v, &ym[i]).unwrap())
-            .collect();
-        Self {
-            _m: m,
-            _n: x2v.len(),
-            _y: ym,
-            x1: x1v,
-            srp,
+        let n = x2v.len();
+        if m < 2 || n < 2 || ym.rows() != m || ym.cols() != n {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if !strictly_monotone(x1v) || !strictly_monotone(x2v) {
+            return Err(InterpError::IdenticalX().into());
+        }
+        // derivatives at the nodes of the row splines and the column splines through them
+        let mut y2 = Matrix::new(m, n, vec![0.0; m * n]);
+        for i in 0..m {
+            let spl = Spline1D::new(x2v, &ym[i][..n])?;
+            for j in 0..n {
+                y2[i][j] = spl.derivative(x2v[j], 1)?;
+            }
         }
+        let mut y1 = Matrix::new(m, n, vec![0.0; m * n]);
+        let mut y12 = Matrix::new(m, n, vec![0.0; m * n]);
+        for j in 0..n {
+            let col: Vec<f64> = (0..m).map(|i| ym[i][j]).collect();
+            let spl = Spline1D::new(x1v, &col)?;
+            let col: Vec<f64> = (0..m).map(|i| y2[i][j]).collect();
+            let dspl = Spline1D::new(x1v, &col)?;
+            for i in 0..m {
+                y1[i][j] = spl.derivative(x1v[i], 1)?;
+                y12[i][j] = dspl.derivative(x1v[i], 1)?;
+            }
+        }
+
+        let mut patches = Vec::with_capacity((m - 1) * (n - 1));
+        for i in 0..(m - 1) {
+            for j in 0..(n - 1) {
+                // corners counterclockwise from (x1[i], x2[j])
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                let pick = |mat: &Matrix<f64>| corners.map(|(k, l)| mat[k][l]);
+                patches.push(bcucof(
+                    &pick(ym),
+                    &pick(&y1),
+                    &pick(&y2),
+                    &pick(&y12),
+                    x1v[i + 1] - x1v[i],
+                    x2v[j + 1] - x2v[j],
+                ));
+            }
+        }
+        Ok(Self {
+            x1interp: LinearInterp::new(x1v, x1v)?,
+            x2interp: LinearInterp::new(x2v, x2v)?,
+            x1cursor: Cursor::new(m),
+            x2cursor: Cursor::new(n),
+            patches,
+        })
+    }
+
+    pub fn interp(&mut self, x1p: f64, x2p: f64) -> Result<f64> {
+        let (i, t) = locate(&self.x1interp, &mut self.x1cursor, x1p);
+        let (j, u) = locate(&self.x2interp, &mut self.x2cursor, x2p);
+        let c = self.patch(i, j);
+        Ok(powers(t, 0)
+            .iter()
+            .zip(c.iter())
+            .map(|(tk, row)| tk * dot(row, &powers(u, 0)))
+            .sum())
+    }
+
+    /// Value and gradient \[dy/dx1, dy/dx2\].
+    pub fn gradient(&mut self, x1p: f64, x2p: f64) -> Result<(f64, Vec<f64>)> {
+        let (y, grad, _) = self.derivatives(x1p, x2p)?;
+        Ok((y, grad))
+    }
+
+    /// Value, gradient and Hessian \[\[y11, y12\], \[y12, y22\]\].
+    #[allow(clippy::type_complexity)]
+    pub fn derivatives(&mut self, x1p: f64, x2p: f64) -> Result<(f64, Vec<f64>, [[f64; 2]; 2])> {
+        let (i, t) = locate(&self.x1interp, &mut self.x1cursor, x1p);
+        let (j, u) = locate(&self.x2interp, &mut self.x2cursor, x2p);
+        let x1 = self.x1interp.xx();
+        let x2 = self.x2interp.xx();
+        let d1 = x1[i + 1] - x1[i];
+        let d2 = x2[j + 1] - x2[j];
+        let c = self.patch(i, j);
+        // sum_kl c[k][l] t^(k) u^(l) with the given orders of derivative in t and u
+        let part = |ot: usize, ou: usize| -> f64 {
+            let pu = powers(u, ou);
+            powers(t, ot)
+                .iter()
+                .zip(c.iter())
+                .map(|(tk, row)| tk * dot(row, &pu))
+                .sum()
+        };
+        let y = part(0, 0);
+        let grad = vec![part(1, 0) / d1, part(0, 1) / d2];
+        let y12 = part(1, 1) / (d1 * d2);
+        let hess = [[part(2, 0) / (d1 * d1), y12], [y12, part(0, 2) / (d2 * d2)]];
+        Ok((y, grad, hess))
     }
 
-    pub fn interp(&mut self, x1p: f64, x2p: f64) -> f64 {
-        let yv: Vec<f64> = self
-            .srp
-            .iter_mut()
-            .map(|srp| srp.interp(x2p).unwrap())
+    /// Values at all the points (x1s\[i\], x2s\[j\]) as a x1s.len() x x2s.len() matrix.
+    /// Each coordinate is located only once, hunting from the previous one.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        let mut cursor = Cursor::new(self.x1interp.n());
+        let rows: Vec<(usize, [f64; 4])> = x1s
+            .iter()
+            .map(|&x1| {
+                let (i, t) = locate(&self.x1interp, &mut cursor, x1);
+                (i, powers(t, 0))
+            })
             .collect();
-        // println!("len srp = {}, yv = {:?}", self.srp.len(), yv);
-        let mut scol = Spline1D::new(self.x1, &yv).unwrap();
-        scol.interp(x1p).unwrap()
+        let mut cursor = Cursor::new(self.x2interp.n());
+        let cols: Vec<(usize, [f64; 4])> = x2s
+            .iter()
+            .map(|&x2| {
+                let (j, u) = locate(&self.x2interp, &mut cursor, x2);
+                (j, powers(u, 0))
+            })
+            .collect();
+        fill_grid(rows.len(), cols.len(), |r, out| {
+            let (i, pt) = rows[r];
+            for (y, &(j, pu)) in out.iter_mut().zip(cols.iter()) {
+                *y = pt
+                    .iter()
+                    .zip(self.patch(i, j).iter())
+                    .map(|(tk, row)| tk * dot(row, &pu))
+                    .sum();
+            }
+            Ok(())
+        })
+    }
+
+    fn patch(&self, i: usize, j: usize) -> &[[f64; 4]; 4] {
+        &self.patches[i * (self.x2interp.n() - 1) + j]
+    }
+}
+
+/// Cell index and the scaled coordinate in it.
+fn locate(interp: &LinearInterp<f64>, cursor: &mut Cursor, x: f64) -> (usize, f64) {
+    let i = interp.hunt(cursor, x);
+    let xx = interp.xx();
+    (i, (x - xx[i]) / (xx[i + 1] - xx[i]))
+}
+
+/// `order`-th derivatives of 1, t, t^2, t^3.
+fn powers(t: f64, order: usize) -> [f64; 4] {
+    match order {
+        0 => [1.0, t, t * t, t * t * t],
+        1 => [0.0, 1.0, 2.0 * t, 3.0 * t * t],
+        2 => [0.0, 0.0, 2.0, 6.0 * t],
+        _ => [0.0, 0.0, 0.0, 6.0],
     }
 }
 
+fn dot(a: &[f64; 4], b: &[f64; 4]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,8 +196,8 @@ mod tests {
             4.0, 2.0, 6.0, 8.0, 6.0, 8.0,
             4.0, 2.0, 6.0, 8.0, 6.0, 8.0
         ]);
-        let mut sp2d = Spline2D::new(&x1, &x2, &y);
-        let y = sp2d.interp(6.0, 6.0);
+        let mut sp2d = Spline2D::new(&x1, &x2, &y).unwrap();
+        let y = sp2d.interp(6.0, 6.0).unwrap();
         assert!((y - 6.50358166).abs() < 1e-5, "y = {}", y);
 
         let x1 = vec![0.0, 2.0, 3.0, 5.0, 7.0];
@@ -69,8 +210,85 @@ mod tests {
             4.0, 2.0, 6.0, 8.0, 6.0, 8.0,
             4.0, 2.0, 6.0, 8.0, 6.0, 8.0,
         ]);
-        let mut sp2d = Spline2D::new(&x1, &x2, &y);
-        let y = sp2d.interp(4.0, 6.0);
+        assert!(Spline2D::new(&x1, &x2, &y).is_err());
+        let y = Matrix::new(5, 6, (0..5).flat_map(|i| y[i].to_vec()).collect());
+        let mut sp2d = Spline2D::new(&x1, &x2, &y).unwrap();
+        let y = sp2d.interp(4.0, 6.0).unwrap();
         assert!((y - 6.50358166).abs() < 1e-5, "y = {}", y);
     }
+
+    #[test]
+    fn test_spline_tensor() {
+        // same values as splining the rows and then a column through them
+        let x1 = vec![0.0, 1.0, 2.0, 4.0, 6.0, 10.0];
+        let x2 = vec![0.0, 2.0, 3.0, 5.0, 7.0, 10.0];
+        let func = |a: f64, b: f64| (0.3 * a).sin() * (b * b + 1.0).sqrt();
+        let data: Vec<f64> = x1
+            .iter()
+            .flat_map(|&a| x2.iter().map(move |&b| func(a, b)))
+            .collect();
+        let y = Matrix::new(x1.len(), x2.len(), data);
+        let mut sp2d = Spline2D::new(&x1, &x2, &y).unwrap();
+        let pts = [(0.5, 0.5), (3.3, 6.1), (9.9, 2.5), (4.0, 3.0)];
+        for &(a, b) in &pts {
+            let yv: Vec<f64> = (0..x1.len())
+                .map(|i| Spline1D::new(&x2, &y[i]).unwrap().eval(b).unwrap())
+                .collect();
+            let expected = Spline1D::new(&x1, &yv).unwrap().eval(a).unwrap();
+            let res = sp2d.interp(a, b).unwrap();
+            assert!((res - expected).abs() < 1e-12, "res = {}", res);
+        }
+
+        let grid = sp2d
+            .interp_grid(&[0.5, 3.3, 9.9], &[0.5, 6.1, 2.5])
+            .unwrap();
+        assert_eq!((grid.rows(), grid.cols()), (3, 3));
+        assert!((grid[1][1] - sp2d.interp(3.3, 6.1).unwrap()).abs() < 1e-14);
+        assert!((grid[2][2] - sp2d.interp(9.9, 2.5).unwrap()).abs() < 1e-14);
+        assert!((grid[0][2] - sp2d.interp(0.5, 2.5).unwrap()).abs() < 1e-14);
+
+        // derivatives against central differences
+        let h = 1e-5;
+        let (a, b) = (3.3, 6.1);
+        let (_, g, hess) = sp2d.derivatives(a, b).unwrap();
+        let (g1, g2) = (g[0], g[1]);
+        let fd1 = (sp2d.interp(a + h, b).unwrap() - sp2d.interp(a - h, b).unwrap()) / (2.0 * h);
+        let fd2 = (sp2d.interp(a, b + h).unwrap() - sp2d.interp(a, b - h).unwrap()) / (2.0 * h);
+        assert!((g1 - fd1).abs() < 1e-7 && (g2 - fd2).abs() < 1e-7);
+        let fd12 = (sp2d.gradient(a, b + h).unwrap().1[0] - sp2d.gradient(a, b - h).unwrap().1[0])
+            / (2.0 * h);
+        let fd11 = (sp2d.gradient(a + h, b).unwrap().1[0] - sp2d.gradient(a - h, b).unwrap().1[0])
+            / (2.0 * h);
+        let fd22 = (sp2d.gradient(a, b + h).unwrap().1[1] - sp2d.gradient(a, b - h).unwrap().1[1])
+            / (2.0 * h);
+        assert!((hess[0][1] - fd12).abs() < 1e-6, "{} {}", hess[0][1], fd12);
+        assert!((hess[0][0] - fd11).abs() < 1e-6, "{} {}", hess[0][0], fd11);
+        assert!((hess[1][1] - fd22).abs() < 1e-6, "{} {}", hess[1][1], fd22);
+    }
+
+    #[test]
+    fn test_spline_bilinear() {
+        // x1 x2 + 2 x1 - x2 is reproduced with its derivatives
+        let x1 = vec![0.0, 1.0, 3.0, 4.0];
+        let x2 = vec![-1.0, 0.0, 2.0, 5.0, 6.0];
+        let func = |a: f64, b: f64| a * b + 2.0 * a - b;
+        let data: Vec<f64> = x1
+            .iter()
+            .flat_map(|&a| x2.iter().map(move |&b| func(a, b)))
+            .collect();
+        let y = Matrix::new(x1.len(), x2.len(), data);
+        let mut sp2d = Spline2D::new(&x1, &x2, &y).unwrap();
+        let (v, g, hess) = sp2d.derivatives(2.5, 3.5).unwrap();
+        let (g1, g2) = (g[0], g[1]);
+        assert!((v - func(2.5, 3.5)).abs() < 1e-12);
+        assert!((g1 - 5.5).abs() < 1e-12 && (g2 - 1.5).abs() < 1e-12);
+        assert!(hess[0][0].abs() < 1e-12 && hess[1][1].abs() < 1e-12);
+        assert!((hess[0][1] - 1.0).abs() < 1e-12);
+        // the border patches are extended outside the grid
+        assert!((sp2d.interp(5.0, 7.0).unwrap() - func(5.0, 7.0)).abs() < 1e-12);
+
+        assert!(Spline2D::new(&x1[..1], &x2, &Matrix::new(1, 5, y[0].to_vec())).is_err());
+        let x1 = vec![0.0, 1.0, 1.0, 4.0];
+        assert!(Spline2D::new(&x1, &x2, &y).is_err());
+    }
 }
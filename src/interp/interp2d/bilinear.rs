@@ -1,42 +1,126 @@
+use anyhow::Result;
+
 use crate::{
-    interp::{linear::LinearInterp, Interp},
+    interp::{linear::LinearInterp, Cursor, Extrapolation, Interp, InterpError},
     Matrix,
 };
 
+use super::fill_grid;
+
 pub struct Bilinear<'a> {
     _m: usize,
     _n: usize,
     y: &'a Matrix<f64>,
-    x1interp: LinearInterp<'a>,
-    x2interp: LinearInterp<'a>,
+    x1interp: LinearInterp<f64>,
+    x2interp: LinearInterp<f64>,
+    x1cursor: Cursor,
+    x2cursor: Cursor,
+    extrapolation: Extrapolation<f64>,
 }
 
 impl<'a> Bilinear<'a> {
-    pub fn new(x1v: &'a Vec<f64>, x2v: &'a Vec<f64>, y: &'a Matrix<f64>) -> Self {
-        Bilinear {
+    pub fn new(x1v: &[f64], x2v: &[f64], y: &'a Matrix<f64>) -> Result<Self> {
+        if y.rows() != x1v.len() || y.cols() != x2v.len() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Bilinear {
             _m: x1v.len(),
             _n: x2v.len(),
-            y: y,
-            x1interp: LinearInterp::new(x1v, x1v),
-            x2interp: LinearInterp::new(x2v, x2v),
-        }
+            y,
+            x1interp: LinearInterp::new(x1v, x1v)?,
+            x2interp: LinearInterp::new(x2v, x2v)?,
+            x1cursor: Cursor::new(x1v.len()),
+            x2cursor: Cursor::new(x2v.len()),
+            extrapolation: Extrapolation::default(),
+        })
+    }
+
+    pub fn extrapolation(&self) -> Extrapolation<f64> {
+        self.extrapolation
+    }
+
+    /// `Linear` and `Natural` both extend the bilinear patch at the border.
+    pub fn set_extrapolation(&mut self, extrapolation: Extrapolation<f64>) {
+        self.extrapolation = extrapolation;
     }
 
-    pub fn interp(&mut self, x1p: f64, x2p: f64) -> f64 {
-        let i = if self.x1interp.cor() == 1 {
-            self.x1interp.hunt(x1p)
+    pub fn with_extrapolation(mut self, extrapolation: Extrapolation<f64>) -> Self {
+        self.set_extrapolation(extrapolation);
+        self
+    }
+
+    /// Apply the extrapolation policy to a coordinate along one axis.
+    /// Returns `Err(value)` when the policy decides the result by itself.
+    fn bound(&self, xv: &[f64], x: f64) -> Result<std::result::Result<f64, f64>> {
+        let (lo, hi) = if xv[xv.len() - 1] >= xv[0] {
+            (xv[0], xv[xv.len() - 1])
         } else {
-            self.x1interp.locate(x1p)
+            (xv[xv.len() - 1], xv[0])
         };
-        let j = if self.x2interp.cor() == 1 {
-            self.x2interp.hunt(x2p)
-        } else {
-            self.x2interp.locate(x2p)
+        if lo <= x && x <= hi {
+            return Ok(Ok(x));
+        }
+        match self.extrapolation {
+            Extrapolation::Error => Err(InterpError::OutOfRange().into()),
+            Extrapolation::Fill(val) => Ok(Err(val)),
+            Extrapolation::Clamp => Ok(Ok(x.max(lo).min(hi))),
+            Extrapolation::Linear | Extrapolation::Natural => Ok(Ok(x)),
+        }
+    }
+
+    pub fn interp(&mut self, x1p: f64, x2p: f64) -> Result<f64> {
+        let x1p = match self.bound(self.x1interp.xx(), x1p)? {
+            Ok(x) => x,
+            Err(val) => return Ok(val),
+        };
+        let x2p = match self.bound(self.x2interp.xx(), x2p)? {
+            Ok(x) => x,
+            Err(val) => return Ok(val),
         };
+        let i = self.x1interp.hunt(&mut self.x1cursor, x1p);
+        let j = self.x2interp.hunt(&mut self.x2cursor, x2p);
         let t = (x1p - self.x1interp.xx()[i]) / (self.x1interp.xx()[i + 1] - self.x1interp.xx()[i]);
         let u = (x2p - self.x2interp.xx()[j]) / (self.x2interp.xx()[j + 1] - self.x2interp.xx()[j]);
-        // println!("i = {}, j = {}, t = {}, u = {}", i, j, t, u);
+        Ok(self.blend(i, t, j, u))
+    }
+
+    /// Values at all the points (x1s\[i\], x2s\[j\]) as a x1s.len() x x2s.len() matrix.
+    /// Each coordinate is located only once, hunting from the previous one.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        let rows = self.cells(&self.x1interp, x1s)?;
+        let cols = self.cells(&self.x2interp, x2s)?;
+        fill_grid(rows.len(), cols.len(), |r, out| {
+            for (y, col) in out.iter_mut().zip(cols.iter()) {
+                *y = match (rows[r], col) {
+                    (Err(val), _) | (_, &Err(val)) => val,
+                    (Ok((i, t)), &Ok((j, u))) => self.blend(i, t, j, u),
+                };
+            }
+            Ok(())
+        })
+    }
+
+    /// Cell and scaled coordinate in it for each of `xs`, or the value decided by the
+    /// extrapolation policy.
+    #[allow(clippy::type_complexity)]
+    fn cells(
+        &self,
+        interp: &LinearInterp<f64>,
+        xs: &[f64],
+    ) -> Result<Vec<std::result::Result<(usize, f64), f64>>> {
+        let xv = interp.xx();
+        let mut cursor = Cursor::new(xv.len());
+        xs.iter()
+            .map(|&x| {
+                Ok(self.bound(xv, x)?.map(|x| {
+                    let i = interp.hunt(&mut cursor, x);
+                    (i, (x - xv[i]) / (xv[i + 1] - xv[i]))
+                }))
+            })
+            .collect()
+    }
 
+    fn blend(&self, i: usize, t: f64, j: usize, u: f64) -> f64 {
         (1.0 - t) * (1.0 - u) * self.y[i][j]
             + t * (1.0 - u) * self.y[i + 1][j]
             + (1.0 - t) * u * self.y[i][j + 1]
@@ -60,18 +144,69 @@ mod tests {
             4.0, 5.0, 6.0, 7.0, 8.0,
             5.0, 6.0, 7.0, 8.0, 9.0,
         ]);
-        let mut interp = Bilinear::new(&x1, &x2, &y);
+        let mut interp = Bilinear::new(&x1, &x2, &y).unwrap();
 
-        let res = interp.interp(0.0, 0.0);
+        let res = interp.interp(0.0, 0.0).unwrap();
         assert!((res - 1.0).abs() < 1e-5, "res = {}", res);
 
-        let res = interp.interp(0.5, 0.5);
+        let res = interp.interp(0.5, 0.5).unwrap();
         assert!((res - 2.0).abs() < 1e-5);
 
-        let res = interp.interp(2.5, 3.0);
+        let res = interp.interp(2.5, 3.0).unwrap();
         assert!((res - 6.0).abs() < 1e-5);
 
-        let res = interp.interp(3.5, 5.0);
+        let res = interp.interp(3.5, 5.0).unwrap();
         assert!((res - 7.5).abs() < 1e-5);
+
+        let res = interp.interp(6.0, 9.0).unwrap();
+        assert!((res - 9.75).abs() < 1e-5, "res = {}", res);
+
+        let mut interp = interp.with_extrapolation(Extrapolation::Clamp);
+        let res = interp.interp(6.0, -1.0).unwrap();
+        assert!((res - 5.0).abs() < 1e-5, "res = {}", res);
+
+        interp.set_extrapolation(Extrapolation::Fill(0.0));
+        assert!(interp.interp(2.0, 9.0).unwrap().abs() < 1e-5);
+        assert!((interp.interp(2.0, 2.0).unwrap() - 5.0).abs() < 1e-5);
+
+        interp.set_extrapolation(Extrapolation::Error);
+        assert!(interp.interp(-0.1, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_bilinear_grid() {
+        let x1 = vec![0.0, 1.0, 2.0, 3.0, 5.0];
+        let x2 = vec![0.0, 1.0, 2.0, 4.0, 8.0];
+        let y = Matrix::new(5, 5, (0..25).map(|k| ((k * 7) % 11) as f64).collect());
+        let x1s = vec![-1.0, 0.0, 0.3, 1.7, 2.2, 4.9, 6.0, 0.5];
+        let x2s = vec![-0.5, 0.4, 1.0, 3.9, 7.5, 9.0];
+        for extrapolation in [
+            Extrapolation::Natural,
+            Extrapolation::Clamp,
+            Extrapolation::Fill(-1.0),
+        ] {
+            let mut interp = Bilinear::new(&x1, &x2, &y)
+                .unwrap()
+                .with_extrapolation(extrapolation);
+            let grid = interp.interp_grid(&x1s, &x2s).unwrap();
+            assert_eq!((grid.rows(), grid.cols()), (x1s.len(), x2s.len()));
+            for (i, &x1p) in x1s.iter().enumerate() {
+                for (j, &x2p) in x2s.iter().enumerate() {
+                    let res = interp.interp(x1p, x2p).unwrap();
+                    assert!(
+                        (grid[i][j] - res).abs() < 1e-12,
+                        "{} != {}",
+                        grid[i][j],
+                        res
+                    );
+                }
+            }
+        }
+
+        let interp = Bilinear::new(&x1, &x2, &y)
+            .unwrap()
+            .with_extrapolation(Extrapolation::Error);
+        assert!(interp.interp_grid(&x1s, &x2s).is_err());
+        assert!(interp.interp_grid(&x1s[1..6], &x2s[1..5]).is_ok());
     }
 }
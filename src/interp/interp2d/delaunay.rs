@@ -0,0 +1,696 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use crate::{interp::InterpError, Matrix};
+
+use super::{eval_grid, eval_rows};
+
+/// Vertex at infinity. Each convex hull edge (a, b) is closed by a ghost triangle
+/// (b, a, GHOST), so that every edge has a triangle on both sides.
+const GHOST: usize = usize::MAX;
+
+/// Half the machine epsilon, the relative rounding error of one operation.
+const EPS: f64 = f64::EPSILON / 2.0;
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+    (x, (a - av) + (b - bv))
+}
+
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+/// Adds `b` exactly to the nonoverlapping expansion `e`, dropping zero components
+/// (Grow-Expansion of Shewchuk, 1997).
+fn grow(e: &mut Vec<f64>, b: f64) {
+    let mut q = b;
+    for h in std::mem::take(e) {
+        let (s, err) = two_sum(q, h);
+        q = s;
+        if err != 0.0 {
+            e.push(err);
+        }
+    }
+    e.push(q);
+}
+
+fn grow_product(e: &mut Vec<f64>, a: f64, b: f64) {
+    let (x, y) = two_product(a, b);
+    grow(e, y);
+    grow(e, x);
+}
+
+/// Most significant component of an expansion, which has the sign of the exact sum.
+fn estimate(e: &[f64]) -> f64 {
+    e.iter().rev().find(|&&v| v != 0.0).cloned().unwrap_or(0.0)
+}
+
+fn orient2d_exact(a: &[f64], b: &[f64], c: &[f64]) -> Vec<f64> {
+    let mut e = vec![];
+    grow_product(&mut e, a[0], b[1]);
+    grow_product(&mut e, -a[0], c[1]);
+    grow_product(&mut e, -a[1], b[0]);
+    grow_product(&mut e, a[1], c[0]);
+    grow_product(&mut e, b[0], c[1]);
+    grow_product(&mut e, -b[1], c[0]);
+    e
+}
+
+/// Twice the signed area of the triangle (a, b, c): positive if counterclockwise, negative if
+/// clockwise and zero if collinear. The sign is exact, the value is approximate.
+pub fn orient2d(a: &[f64], b: &[f64], c: &[f64]) -> f64 {
+    let detleft = (a[0] - c[0]) * (b[1] - c[1]);
+    let detright = (a[1] - c[1]) * (b[0] - c[0]);
+    let det = detleft - detright;
+    let bound = (3.0 + 16.0 * EPS) * EPS * (detleft.abs() + detright.abs());
+    if det.abs() > bound {
+        det
+    } else {
+        estimate(&orient2d_exact(a, b, c))
+    }
+}
+
+/// Positive if `d` lies inside the circle through the counterclockwise triangle (a, b, c),
+/// negative if outside and zero if the four points are cocircular. The sign is exact.
+pub fn incircle(a: &[f64], b: &[f64], c: &[f64], d: &[f64]) -> f64 {
+    let (adx, ady) = (a[0] - d[0], a[1] - d[1]);
+    let (bdx, bdy) = (b[0] - d[0], b[1] - d[1]);
+    let (cdx, cdy) = (c[0] - d[0], c[1] - d[1]);
+    let (alift, blift, clift) = (
+        adx * adx + ady * ady,
+        bdx * bdx + bdy * bdy,
+        cdx * cdx + cdy * cdy,
+    );
+    let det = alift * (bdx * cdy - cdx * bdy)
+        + blift * (cdx * ady - adx * cdy)
+        + clift * (adx * bdy - bdx * ady);
+    let permanent = alift * ((bdx * cdy).abs() + (cdx * bdy).abs())
+        + blift * ((cdx * ady).abs() + (adx * cdy).abs())
+        + clift * ((adx * bdy).abs() + (bdx * ady).abs());
+    if det.abs() > (10.0 + 96.0 * EPS) * EPS * permanent {
+        return det;
+    }
+    // det |x y x^2+y^2 1| over the rows a, b, c, d, expanded along the lifted column
+    let mut e = vec![];
+    let terms = [
+        (a, [b, c, d], 1.0),
+        (b, [a, c, d], -1.0),
+        (c, [a, b, d], 1.0),
+        (d, [a, b, c], -1.0),
+    ];
+    for (p, [q, r, s], sign) in terms {
+        let (x0, x1) = two_product(p[0], p[0]);
+        let (y0, y1) = two_product(p[1], p[1]);
+        let orient = orient2d_exact(q, r, s);
+        for l in [x0, x1, y0, y1] {
+            for &o in &orient {
+                grow_product(&mut e, sign * l, o);
+            }
+        }
+    }
+    estimate(&e)
+}
+
+fn circumcenter(a: &[f64], b: &[f64], c: &[f64]) -> [f64; 2] {
+    let (bx, by) = (b[0] - a[0], b[1] - a[1]);
+    let (cx, cy) = (c[0] - a[0], c[1] - a[1]);
+    let (b2, c2) = (bx * bx + by * by, cx * cx + cy * cy);
+    let d = 2.0 * (bx * cy - by * cx);
+    [
+        a[0] + (cy * b2 - by * c2) / d,
+        a[1] + (bx * c2 - cx * b2) / d,
+    ]
+}
+
+/// Delaunay triangulation of scattered 2D points, built incrementally by the Bowyer-Watson
+/// algorithm with exact orientation and incircle predicates.
+/// Duplicated points are kept only once, at their first index.
+pub struct Delaunay<'a> {
+    /// Given scattered points (2d-array). \[n_point, 2\]
+    pts: &'a Matrix<f64>,
+    /// counterclockwise vertices, the finite triangles first and then the ghost triangles
+    tris: Vec<[usize; 3]>,
+    /// nbrs\[t\]\[i\] is the triangle across the edge opposite to tris\[t\]\[i\]
+    nbrs: Vec<[usize; 3]>,
+    /// number of finite triangles
+    ntri: usize,
+}
+
+impl<'a> Delaunay<'a> {
+    pub fn new(pts: &'a Matrix<f64>) -> Result<Self> {
+        if pts.cols() != 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let n = pts.rows();
+        let i1 = (1..n).find(|&i| pts[i] != pts[0]);
+        let i2 =
+            i1.and_then(|i1| ((i1 + 1)..n).find(|&i| orient2d(&pts[0], &pts[i1], &pts[i]) != 0.0));
+        let (i1, i2) = match (i1, i2) {
+            (Some(i1), Some(i2)) => (i1, i2),
+            _ => return Err(InterpError::Collinear().into()),
+        };
+        let (a, b, c) = if orient2d(&pts[0], &pts[i1], &pts[i2]) > 0.0 {
+            (0, i1, i2)
+        } else {
+            (0, i2, i1)
+        };
+        let mut builder = Builder {
+            pts,
+            tris: vec![[a, b, c], [b, a, GHOST], [c, b, GHOST], [a, c, GHOST]],
+            nbrs: vec![[0; 3]; 4],
+            alive: vec![true; 4],
+            free: vec![],
+            start: 0,
+            step: 0,
+        };
+        let edges = builder.edges(&[0, 1, 2, 3]);
+        for t in 0..4 {
+            for i in 0..3 {
+                let (x, y) = builder.edge(t, i);
+                builder.nbrs[t][i] = edges[&(y, x)];
+            }
+        }
+        for i in 1..n {
+            if i != i1 && i != i2 {
+                builder.insert(i);
+            }
+        }
+        Ok(builder.finish())
+    }
+
+    pub fn pts(&self) -> &Matrix<f64> {
+        self.pts
+    }
+
+    /// Counterclockwise vertex indices of the triangles.
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.tris[..self.ntri]
+    }
+
+    /// Index of a triangle containing `pt`, `None` outside the convex hull.
+    pub fn locate(&self, pt: &[f64]) -> Option<usize> {
+        self.locate_from(0, pt)
+    }
+
+    /// Same as `locate`, but walks from the triangle `start`, e.g. the one found for the
+    /// previous of a sequence of nearby points.
+    pub fn locate_from(&self, start: usize, pt: &[f64]) -> Option<usize> {
+        let start = if start < self.ntri { start } else { 0 };
+        let t = walk(self.pts, &self.tris, &self.nbrs, start, pt, &mut 0);
+        if t < self.ntri {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    /// Triangle containing `pt` and the barycentric coordinates of `pt` in it.
+    pub fn barycentric(&self, pt: &[f64]) -> Option<(usize, [f64; 3])> {
+        self.barycentric_from(0, pt)
+    }
+
+    /// Same as `barycentric`, walking from the triangle `start` as `locate_from` does.
+    pub fn barycentric_from(&self, start: usize, pt: &[f64]) -> Option<(usize, [f64; 3])> {
+        let t = self.locate_from(start, pt)?;
+        let [a, b, c] = self.tris[t].map(|v| &self.pts[v]);
+        let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        let la = ((b[0] - pt[0]) * (c[1] - pt[1]) - (b[1] - pt[1]) * (c[0] - pt[0])) / area;
+        let lb = ((c[0] - pt[0]) * (a[1] - pt[1]) - (c[1] - pt[1]) * (a[0] - pt[0])) / area;
+        Some((t, [la, lb, 1.0 - la - lb]))
+    }
+
+    /// Vertices on the convex hull in counterclockwise order, including the points lying on
+    /// its edges.
+    pub fn convex_hull(&self) -> Vec<usize> {
+        // ghost (x, y) closes the hull edge y -> x
+        let next: HashMap<usize, usize> = self.tris[self.ntri..]
+            .iter()
+            .map(|&[x, y, _]| (y, x))
+            .collect();
+        let first = self.tris[self.ntri][1];
+        let mut hull = vec![first];
+        let mut v = next[&first];
+        while v != first {
+            hull.push(v);
+            v = next[&v];
+        }
+        hull
+    }
+
+    /// Sibson's natural neighbour coordinates of `pt` as (vertex, weight), `None` outside the
+    /// convex hull. On the hull the coordinates are the linear ones along the edge.
+    pub fn natural_neighbours(&self, pt: &[f64]) -> Option<Vec<(usize, f64)>> {
+        let (t, bary) = self.barycentric(pt)?;
+        if let Some(&v) = self.tris[t].iter().find(|&&v| self.pts[v] == *pt) {
+            return Some(vec![(v, 1.0)]);
+        }
+        let bad = cavity(self.pts, &self.tris, &self.nbrs, t, pt);
+        if bad.iter().any(|&t| t >= self.ntri) {
+            return Some(self.tris[t].iter().cloned().zip(bary).collect());
+        }
+        let in_cavity: HashSet<usize> = bad.iter().cloned().collect();
+        // boundary edge u -> v of the cavity, by u
+        let mut next = HashMap::new();
+        for &t in &bad {
+            for i in 0..3 {
+                if !in_cavity.contains(&self.nbrs[t][i]) {
+                    let [u, v] = [self.tris[t][(i + 1) % 3], self.tris[t][(i + 2) % 3]];
+                    next.insert(u, (v, t));
+                }
+            }
+        }
+        let prev: HashMap<usize, (usize, usize)> =
+            next.iter().map(|(&u, &(v, t))| (v, (u, t))).collect();
+        let center = |t: usize| {
+            let [a, b, c] = self.tris[t];
+            circumcenter(&self.pts[a], &self.pts[b], &self.pts[c])
+        };
+
+        // area of the Voronoi cell of each neighbour taken by the cell of `pt`
+        let mut coords: Vec<(usize, f64)> = next
+            .iter()
+            .map(|(&v, &(x, last))| {
+                let (w, mut t) = prev[&v];
+                let mut poly = vec![circumcenter(&self.pts[w], &self.pts[v], pt)];
+                // turn around v through the cavity, from the edge w -> v to the edge v -> x
+                loop {
+                    poly.push(center(t));
+                    if t == last {
+                        break;
+                    }
+                    let j = self.tris[t].iter().position(|&u| u == v).unwrap();
+                    t = self.nbrs[t][(j + 2) % 3];
+                }
+                poly.push(circumcenter(&self.pts[v], &self.pts[x], pt));
+                let area: f64 = (0..poly.len())
+                    .map(|k| {
+                        let (p, q) = (poly[k], poly[(k + 1) % poly.len()]);
+                        p[0] * q[1] - p[1] * q[0]
+                    })
+                    .sum();
+                (v, 0.5 * area.abs())
+            })
+            .collect();
+        let total: f64 = coords.iter().map(|c| c.1).sum();
+        coords.iter_mut().for_each(|c| c.1 /= total);
+        coords.sort_by_key(|c| c.0);
+        Some(coords)
+    }
+}
+
+/// Mutable state of the triangulation during the incremental construction.
+struct Builder<'a> {
+    pts: &'a Matrix<f64>,
+    tris: Vec<[usize; 3]>,
+    nbrs: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    /// slots of the deleted triangles
+    free: Vec<usize>,
+    /// a finite triangle to start the walks from
+    start: usize,
+    step: usize,
+}
+
+impl<'a> Builder<'a> {
+    /// Directed edge opposite to the vertex i of the triangle t.
+    fn edge(&self, t: usize, i: usize) -> (usize, usize) {
+        (self.tris[t][(i + 1) % 3], self.tris[t][(i + 2) % 3])
+    }
+
+    fn edges(&self, ts: &[usize]) -> HashMap<(usize, usize), usize> {
+        ts.iter()
+            .flat_map(|&t| (0..3).map(move |i| (self.edge(t, i), t)))
+            .collect()
+    }
+
+    fn insert(&mut self, ip: usize) {
+        let pts = self.pts;
+        let p = &pts[ip];
+        let seed = walk(
+            self.pts,
+            &self.tris,
+            &self.nbrs,
+            self.start,
+            p,
+            &mut self.step,
+        );
+        if self.tris[seed]
+            .iter()
+            .any(|&v| v != GHOST && self.pts[v] == *p)
+        {
+            return;
+        }
+        let bad = cavity(self.pts, &self.tris, &self.nbrs, seed, p);
+        let in_cavity: HashSet<usize> = bad.iter().cloned().collect();
+
+        // fan of new triangles from the boundary of the cavity to p, the ghost vertex last
+        let mut boundary = vec![];
+        for &t in &bad {
+            for i in 0..3 {
+                let nb = self.nbrs[t][i];
+                if !in_cavity.contains(&nb) {
+                    boundary.push((self.edge(t, i), nb));
+                }
+            }
+        }
+        for &t in &bad {
+            self.alive[t] = false;
+            self.free.push(t);
+        }
+        let mut added = vec![];
+        for &((u, v), _) in &boundary {
+            let tri = if u == GHOST {
+                [v, ip, GHOST]
+            } else if v == GHOST {
+                [ip, u, GHOST]
+            } else {
+                [u, v, ip]
+            };
+            let t = match self.free.pop() {
+                Some(t) => {
+                    self.tris[t] = tri;
+                    self.alive[t] = true;
+                    t
+                }
+                None => {
+                    self.tris.push(tri);
+                    self.nbrs.push([0; 3]);
+                    self.alive.push(true);
+                    self.tris.len() - 1
+                }
+            };
+            added.push(t);
+        }
+        let edges = self.edges(&added);
+        for (&t, &((u, v), outer)) in added.iter().zip(boundary.iter()) {
+            for i in 0..3 {
+                let (x, y) = self.edge(t, i);
+                if (x, y) == (u, v) {
+                    self.nbrs[t][i] = outer;
+                    let j = (0..3).find(|&j| self.edge(outer, j) == (v, u)).unwrap();
+                    self.nbrs[outer][j] = t;
+                } else {
+                    self.nbrs[t][i] = edges[&(y, x)];
+                }
+            }
+            if self.tris[t][2] != GHOST {
+                self.start = t;
+            }
+        }
+    }
+
+    /// Renumbers the live triangles, the finite ones first.
+    fn finish(self) -> Delaunay<'a> {
+        let (tris, alive) = (&self.tris, &self.alive);
+        let live = |ghost: bool| {
+            (0..tris.len()).filter(move |&t| alive[t] && (tris[t][2] == GHOST) == ghost)
+        };
+        let ntri = live(false).count();
+        let order: Vec<usize> = live(false).chain(live(true)).collect();
+        let mut id = vec![usize::MAX; tris.len()];
+        for (k, &t) in order.iter().enumerate() {
+            id[t] = k;
+        }
+        Delaunay {
+            pts: self.pts,
+            tris: order.iter().map(|&t| tris[t]).collect(),
+            nbrs: order
+                .iter()
+                .map(|&t| self.nbrs[t].map(|nb| id[nb]))
+                .collect(),
+            ntri,
+        }
+    }
+}
+
+/// Visibility walk from the finite triangle `t` to the triangle containing `p`, or to a ghost
+/// triangle whose hull edge sees `p` if `p` is outside the convex hull.
+fn walk(
+    pts: &Matrix<f64>,
+    tris: &[[usize; 3]],
+    nbrs: &[[usize; 3]],
+    mut t: usize,
+    p: &[f64],
+    step: &mut usize,
+) -> usize {
+    'walk: while tris[t][2] != GHOST {
+        // rotate the first edge tested to avoid cycling on degenerate configurations
+        *step += 1;
+        for k in 0..3 {
+            let i = (k + *step) % 3;
+            let (x, y) = (tris[t][(i + 1) % 3], tris[t][(i + 2) % 3]);
+            if orient2d(&pts[x], &pts[y], p) < 0.0 {
+                t = nbrs[t][i];
+                continue 'walk;
+            }
+        }
+        break;
+    }
+    t
+}
+
+/// Whether `p` lies strictly inside the circumcircle of the triangle t. For a ghost
+/// triangle this is the open half plane beyond its hull edge plus the open edge itself.
+fn in_circle(pts: &Matrix<f64>, tri: &[usize; 3], p: &[f64]) -> bool {
+    let [a, b, c] = *tri;
+    if c == GHOST {
+        let (a, b) = (&pts[a], &pts[b]);
+        let o = orient2d(a, b, p);
+        let inner =
+            |u: &[f64], v: &[f64]| (p[0] - u[0]) * (v[0] - u[0]) + (p[1] - u[1]) * (v[1] - u[1]);
+        o > 0.0 || (o == 0.0 && inner(a, b) > 0.0 && inner(b, a) > 0.0)
+    } else {
+        incircle(&pts[a], &pts[b], &pts[c], p) > 0.0
+    }
+}
+
+/// Triangles whose circumcircle contains `p`, connected to `seed`.
+fn cavity(
+    pts: &Matrix<f64>,
+    tris: &[[usize; 3]],
+    nbrs: &[[usize; 3]],
+    seed: usize,
+    p: &[f64],
+) -> Vec<usize> {
+    let mut bad = vec![seed];
+    let mut seen: HashSet<usize> = bad.iter().cloned().collect();
+    let mut k = 0;
+    while k < bad.len() {
+        for nb in nbrs[bad[k]] {
+            if seen.insert(nb) && in_circle(pts, &tris[nb], p) {
+                bad.push(nb);
+            }
+        }
+        k += 1;
+    }
+    bad
+}
+
+/// Piecewise linear and natural neighbour interpolation over the Delaunay triangulation.
+pub struct DelaunayInterp<'a> {
+    tri: Delaunay<'a>,
+    /// values of the points. length = n_point
+    vals: &'a [f64],
+}
+
+impl<'a> DelaunayInterp<'a> {
+    pub fn new(pts: &'a Matrix<f64>, vals: &'a [f64]) -> Result<Self> {
+        if vals.len() != pts.rows() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
+            tri: Delaunay::new(pts)?,
+            vals,
+        })
+    }
+
+    pub fn triangulation(&self) -> &Delaunay<'a> {
+        &self.tri
+    }
+
+    /// Linear interpolation in the triangle containing `pt`.
+    pub fn interp(&self, pt: &[f64]) -> Result<f64> {
+        self.linear_from(&mut 0, pt)
+    }
+
+    /// `interp` at each row of `pts`. The search for each point starts from the triangle
+    /// of the previous one, so nearby consecutive points are cheap.
+    pub fn interp_many(&self, pts: &Matrix<f64>) -> Result<Vec<f64>> {
+        eval_rows(pts, || 0, |hint, pt| self.linear_from(hint, pt))
+    }
+
+    /// `interp` at all the points (x1s\[i\], x2s\[j\]) as a x1s.len() x x2s.len() matrix,
+    /// walking along each row from one triangle to the next.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        eval_grid(x1s, x2s, || 0, |hint, pt| self.linear_from(hint, pt))
+    }
+
+    /// Linear interpolation, searching from the triangle `hint` and leaving there the one found.
+    fn linear_from(&self, hint: &mut usize, pt: &[f64]) -> Result<f64> {
+        let (t, bary) = self
+            .tri
+            .barycentric_from(*hint, pt)
+            .ok_or(InterpError::OutOfRange())?;
+        *hint = t;
+        Ok(self.tri.triangles()[t]
+            .iter()
+            .zip(bary.iter())
+            .map(|(&v, w)| w * self.vals[v])
+            .sum())
+    }
+
+    /// Sibson's natural neighbour interpolation.
+    pub fn natural_neighbour(&self, pt: &[f64]) -> Result<f64> {
+        let coords = self
+            .tri
+            .natural_neighbours(pt)
+            .ok_or(InterpError::OutOfRange())?;
+        Ok(coords.iter().map(|&(v, w)| w * self.vals[v]).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interp::testing::random_points;
+
+    #[test]
+    fn test_predicates() {
+        let (a, b, c) = ([0.0, 0.0], [1.0, 0.0], [0.0, 1.0]);
+        assert!(orient2d(&a, &b, &c) > 0.0);
+        assert!(orient2d(&a, &c, &b) < 0.0);
+        assert!(incircle(&a, &b, &c, &[0.25, 0.25]) > 0.0);
+        assert!(incircle(&a, &b, &c, &[2.0, 2.0]) < 0.0);
+
+        // nearly degenerate configurations far from the origin
+        let o = 1e6;
+        let (a, b, c) = ([o + 1.0, o], [o, o + 1.0], [o - 1.0, o]);
+        let ulp = 2f64.powi(-33);
+        assert_eq!(incircle(&a, &b, &c, &[o, o - 1.0]), 0.0);
+        assert!(incircle(&a, &b, &c, &[o, o - 1.0 + ulp]) > 0.0);
+        assert!(incircle(&a, &b, &c, &[o, o - 1.0 - 2.0 * ulp]) < 0.0);
+        let (p, q) = ([o, o], [o + 1.0, o + 1.0]);
+        assert_eq!(orient2d(&p, &q, &[o + 3.0, o + 3.0]), 0.0);
+        assert!(orient2d(&p, &q, &[o + 3.0, o + 3.0 + 2.0 * ulp]) > 0.0);
+        assert!(orient2d(&p, &q, &[o + 3.0 + 2.0 * ulp, o + 3.0]) < 0.0);
+    }
+
+    #[test]
+    fn test_delaunay() {
+        let pts = random_points(300, 2, 5);
+        let tri = Delaunay::new(&pts).unwrap();
+        let hull = tri.convex_hull();
+        assert_eq!(tri.triangles().len(), 2 * pts.rows() - 2 - hull.len());
+        for t in tri.triangles() {
+            let [a, b, c] = t.map(|v| &pts[v]);
+            assert!(orient2d(a, b, c) > 0.0);
+            for i in 0..pts.rows() {
+                assert!(incircle(a, b, c, &pts[i]) <= 0.0);
+            }
+        }
+        // the hull is convex and contains every point
+        for k in 0..hull.len() {
+            let (a, b) = (&pts[hull[k]], &pts[hull[(k + 1) % hull.len()]]);
+            for i in 0..pts.rows() {
+                assert!(orient2d(a, b, &pts[i]) >= 0.0);
+            }
+        }
+        for q in 0..50 {
+            let pt = [q as f64 / 50.0, 0.5 + 0.3 * (q as f64).sin()];
+            match tri.barycentric(&pt) {
+                Some((_, bary)) => assert!(bary.iter().all(|&w| w > -1e-12)),
+                None => assert!((0..hull.len()).any(|k| {
+                    orient2d(&pts[hull[k]], &pts[hull[(k + 1) % hull.len()]], &pt) < 0.0
+                })),
+            }
+        }
+    }
+
+    #[test]
+    fn test_delaunay_degenerate() {
+        // cocircular points of a grid, duplicates and collinear hull points
+        let n = 6;
+        let mut data: Vec<f64> = (0..(n * n))
+            .flat_map(|i| vec![(i / n) as f64, (i % n) as f64])
+            .collect();
+        data.extend([2.0, 3.0, 0.0, 0.0]);
+        let pts = Matrix::new(n * n + 2, 2, data);
+        let tri = Delaunay::new(&pts).unwrap();
+        assert_eq!(tri.triangles().len(), 2 * (n - 1) * (n - 1));
+        assert_eq!(tri.convex_hull().len(), 4 * (n - 1));
+        assert!(tri.triangles().iter().flatten().all(|&v| v < n * n));
+
+        let line = Matrix::new(3, 2, vec![0.0, 0.0, 1.0, 1.0, 2.0, 2.0]);
+        assert!(Delaunay::new(&line).is_err());
+    }
+
+    #[test]
+    fn test_delaunay_interp() {
+        let mut pts = random_points(200, 2, 11);
+        for (i, corner) in [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
+            .iter()
+            .enumerate()
+        {
+            pts[i].copy_from_slice(corner);
+        }
+        let linear = |p: &[f64]| 1.0 + 2.0 * p[0] - 3.0 * p[1];
+        let vals: Vec<f64> = (0..pts.rows()).map(|i| linear(&pts[i])).collect();
+        let interp = DelaunayInterp::new(&pts, &vals).unwrap();
+        assert_eq!(interp.triangulation().convex_hull().len(), 4);
+        for p in [[0.3, 0.7], [0.01, 0.99], [0.5, 0.0], [0.77, 0.21]] {
+            let lin = interp.interp(&p).unwrap();
+            let nn = interp.natural_neighbour(&p).unwrap();
+            assert!((lin - linear(&p)).abs() < 1e-12, "{}", lin);
+            assert!((nn - linear(&p)).abs() < 1e-10, "{}", nn);
+        }
+        assert_eq!(interp.natural_neighbour(&pts[17]).unwrap(), vals[17]);
+        assert!(interp.interp(&[1.5, 0.5]).is_err());
+        assert!(interp.natural_neighbour(&[-0.1, 0.5]).is_err());
+
+        // smooth function, natural neighbour coordinates sum to one
+        let func = |p: &[f64]| (3.0 * p[0]).sin() * p[1];
+        let vals: Vec<f64> = (0..pts.rows()).map(|i| func(&pts[i])).collect();
+        let interp = DelaunayInterp::new(&pts, &vals).unwrap();
+        let coords = interp
+            .triangulation()
+            .natural_neighbours(&[0.4, 0.6])
+            .unwrap();
+        assert!((coords.iter().map(|c| c.1).sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(coords.len() >= 3);
+        for p in [[0.4, 0.6], [0.8, 0.3]] {
+            assert!((interp.natural_neighbour(&p).unwrap() - func(&p)).abs() < 2e-2);
+            assert!((interp.interp(&p).unwrap() - func(&p)).abs() < 2e-2);
+        }
+
+        // batches walk from the previous triangle and agree with single points
+        let ticks: Vec<f64> = (0..=20).map(|i| i as f64 / 20.0).collect();
+        let grid = interp.interp_grid(&ticks, &ticks).unwrap();
+        let pts_grid = Matrix::new(
+            ticks.len() * ticks.len(),
+            2,
+            ticks
+                .iter()
+                .flat_map(|&a| ticks.iter().flat_map(move |&b| [a, b]))
+                .collect(),
+        );
+        let many = interp.interp_many(&pts_grid).unwrap();
+        for (i, &a) in ticks.iter().enumerate() {
+            for (j, &b) in ticks.iter().enumerate() {
+                let res = interp.interp(&[a, b]).unwrap();
+                assert!(
+                    (grid[i][j] - res).abs() < 1e-12,
+                    "{} != {}",
+                    grid[i][j],
+                    res
+                );
+                assert!((many[i * ticks.len() + j] - res).abs() < 1e-12);
+            }
+        }
+        assert!(interp.interp_grid(&ticks, &[0.5, 1.2]).is_err());
+    }
+}
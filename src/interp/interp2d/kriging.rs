@@ -0,0 +1,559 @@
+use anyhow::Result;
+
+use super::{
+    eval_grid, eval_rows,
+    rbf::{distance, monomial, monomials},
+};
+use crate::{
+    interp::{kdtree::KdTree, InterpError},
+    linalg::cholesky::Cholesky,
+    Matrix,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariogramModel {
+    Spherical,
+    Exponential,
+    Gaussian,
+    /// Matérn model with the half-integer smoothness nu = p + 1/2.
+    /// `Matern(0)` is the exponential model.
+    Matern(usize),
+}
+
+impl VariogramModel {
+    /// Correlation at the scaled distance t = h / range, 1 at t = 0.
+    fn correlation(&self, t: f64) -> f64 {
+        match *self {
+            VariogramModel::Spherical => {
+                if t >= 1.0 {
+                    0.0
+                } else {
+                    1.0 - 1.5 * t + 0.5 * t * t * t
+                }
+            }
+            VariogramModel::Exponential => (-t).exp(),
+            VariogramModel::Gaussian => (-t * t).exp(),
+            VariogramModel::Matern(p) => {
+                let s = (2.0 * p as f64 + 1.0).sqrt() * t;
+                let sum: f64 = (0..=p)
+                    .map(|i| {
+                        factorial(p + i) / (factorial(i) * factorial(p - i))
+                            * (2.0 * s).powi((p - i) as i32)
+                    })
+                    .sum();
+                (-s).exp() * factorial(p) / factorial(2 * p) * sum
+            }
+        }
+    }
+}
+
+fn factorial(n: usize) -> f64 {
+    (1..=n).map(|i| i as f64).product()
+}
+
+/// Variogram gamma(h) = nugget + sill * (1 - rho(h / range)) for h > 0, gamma(0) = 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Variogram {
+    pub model: VariogramModel,
+    pub nugget: f64,
+    /// partial sill, the variogram levels off at nugget + sill
+    pub sill: f64,
+    pub range: f64,
+}
+
+impl Variogram {
+    pub fn new(model: VariogramModel, nugget: f64, sill: f64, range: f64) -> Self {
+        Self {
+            model,
+            nugget,
+            sill,
+            range,
+        }
+    }
+
+    pub fn gamma(&self, h: f64) -> f64 {
+        if h == 0.0 {
+            0.0
+        } else {
+            self.nugget + self.sill * (1.0 - self.model.correlation(h / self.range))
+        }
+    }
+
+    /// Covariance C(h) = C(0) - gamma(h), with C(0) = nugget + sill.
+    pub fn covariance(&self, h: f64) -> f64 {
+        if h == 0.0 {
+            self.nugget + self.sill
+        } else {
+            self.sill * self.model.correlation(h / self.range)
+        }
+    }
+
+    /// Fits nugget, sill and range to the empirical variogram by least squares weighted
+    /// with the number of pairs in each bin.
+    pub fn fit(emp: &EmpiricalVariogram, model: VariogramModel) -> Result<Self> {
+        let lo = emp.lags.iter().cloned().fold(f64::INFINITY, f64::min);
+        let hi = emp.lags.iter().cloned().fold(0.0, f64::max);
+        if emp.lags.is_empty() || lo <= 0.0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        // coarse search over log(range), then golden section around the best one
+        let (a, b) = ((0.25 * lo).ln(), (4.0 * hi).ln());
+        let ngrid = 100;
+        let grid: Vec<f64> = (0..=ngrid)
+            .map(|i| a + (b - a) * i as f64 / ngrid as f64)
+            .collect();
+        let cost = |log_range: f64| emp.fit_linear(model, log_range.exp());
+        let costs: Vec<f64> = grid.iter().map(|&g| cost(g).1).collect();
+        let best = (0..=ngrid)
+            .min_by(|&i, &j| costs[i].partial_cmp(&costs[j]).unwrap())
+            .unwrap();
+        let (mut a, mut b) = (grid[best.max(1) - 1], grid[(best + 1).min(ngrid)]);
+        let ratio = (5.0f64.sqrt() - 1.0) / 2.0;
+        for _ in 0..40 {
+            let c = b - ratio * (b - a);
+            let d = a + ratio * (b - a);
+            if cost(c).1 < cost(d).1 {
+                b = d;
+            } else {
+                a = c;
+            }
+        }
+        let range = (0.5 * (a + b)).exp();
+        let ((nugget, sill), _) = emp.fit_linear(model, range);
+        Ok(Self::new(model, nugget, sill, range))
+    }
+}
+
+/// Empirical (Matheron) variogram: half the mean squared difference of the pairs in each
+/// distance bin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmpiricalVariogram {
+    /// mean distance of the pairs in each bin
+    pub lags: Vec<f64>,
+    pub gammas: Vec<f64>,
+    /// number of pairs in each bin
+    pub counts: Vec<usize>,
+}
+
+impl EmpiricalVariogram {
+    /// Bins the pairs closer than `max_dist` into `nbins` bins of equal width.
+    /// Empty bins are dropped.
+    pub fn new(pts: &Matrix<f64>, vals: &[f64], nbins: usize, max_dist: f64) -> Result<Self> {
+        if vals.len() != pts.rows() || nbins == 0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let width = max_dist / nbins as f64;
+        let mut sums = vec![(0.0, 0.0, 0); nbins];
+        let tree = KdTree::new(pts)?;
+        for i in 0..pts.rows() {
+            for (j, d) in tree.within(&pts[i], max_dist) {
+                if j <= i || d >= max_dist {
+                    continue;
+                }
+                let bin = &mut sums[((d / width) as usize).min(nbins - 1)];
+                bin.0 += d;
+                bin.1 += 0.5 * (vals[i] - vals[j]) * (vals[i] - vals[j]);
+                bin.2 += 1;
+            }
+        }
+        let sums: Vec<_> = sums.into_iter().filter(|s| s.2 > 0).collect();
+        Ok(Self {
+            lags: sums.iter().map(|s| s.0 / s.2 as f64).collect(),
+            gammas: sums.iter().map(|s| s.1 / s.2 as f64).collect(),
+            counts: sums.iter().map(|s| s.2).collect(),
+        })
+    }
+
+    /// Non-negative weighted least squares for (nugget, sill) at a fixed range, with the cost.
+    fn fit_linear(&self, model: VariogramModel, range: f64) -> ((f64, f64), f64) {
+        let (mut sw, mut sg, mut sgg, mut sy, mut sgy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for ((&h, &y), &cnt) in self
+            .lags
+            .iter()
+            .zip(self.gammas.iter())
+            .zip(self.counts.iter())
+        {
+            let (w, g) = (cnt as f64, 1.0 - model.correlation(h / range));
+            sw += w;
+            sg += w * g;
+            sgg += w * g * g;
+            sy += w * y;
+            sgy += w * g * y;
+        }
+        let det = sw * sgg - sg * sg;
+        let (mut nugget, mut sill) = if det.abs() > 1e-14 * sw * sgg {
+            ((sgg * sy - sg * sgy) / det, (sw * sgy - sg * sy) / det)
+        } else {
+            (0.0, sgy / sgg)
+        };
+        if nugget < 0.0 {
+            nugget = 0.0;
+            sill = sgy / sgg;
+        }
+        if sill < 0.0 {
+            sill = 0.0;
+            nugget = sy / sw;
+        }
+        let cost = self
+            .lags
+            .iter()
+            .zip(self.gammas.iter())
+            .zip(self.counts.iter())
+            .map(|((&h, &y), &cnt)| {
+                let r = y - nugget - sill * (1.0 - model.correlation(h / range));
+                cnt as f64 * r * r
+            })
+            .sum();
+        ((nugget, sill), cost)
+    }
+}
+
+/// Trend of the kriging mean.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Drift {
+    /// unknown constant mean
+    #[default]
+    Ordinary,
+    /// unknown polynomial mean of the given total degree
+    Universal(usize),
+}
+
+/// Kriging (Gaussian process regression with a generalized least squares trend) for
+/// scattered points. Every prediction comes with its kriging variance.
+/// (Section 3.7.4 of Numerical Recipes in C)
+pub struct Kriging<'a> {
+    /// Given scattered points (2d-array). \[n_point, n_dimension\]
+    pts: &'a Matrix<f64>,
+    variogram: Variogram,
+    /// exponents of the trend monomials
+    powers: Vec<Vec<i32>>,
+    /// Cholesky factor of the covariance matrix C
+    chol: Cholesky<f64>,
+    /// trend coefficients
+    beta: Vec<f64>,
+    /// C^-1 (y - F beta)
+    alpha: Vec<f64>,
+    /// C^-1 f_k for each trend monomial f_k
+    cinv_f: Vec<Vec<f64>>,
+    /// Cholesky factor of F^T C^-1 F
+    fcf: Cholesky<f64>,
+    /// (y - F beta)^T C^-1 (y - F beta)
+    quad: f64,
+}
+
+impl<'a> Kriging<'a> {
+    pub fn new(
+        pts: &'a Matrix<f64>,
+        vals: &[f64],
+        variogram: Variogram,
+        drift: Drift,
+    ) -> Result<Self> {
+        let n = pts.rows();
+        let dim = pts.cols();
+        let powers = match drift {
+            Drift::Ordinary => monomials(dim, Some(0)),
+            Drift::Universal(degree) => monomials(dim, Some(degree)),
+        };
+        let m = powers.len();
+        if vals.len() != n || n <= m {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let mut cov = Matrix::new(n, n, vec![0.0; n * n]);
+        for i in 0..n {
+            for j in 0..=i {
+                let c = variogram.covariance(distance(&pts[i], &pts[j], dim));
+                cov[i][j] = c;
+                cov[j][i] = c;
+            }
+        }
+        let chol = Cholesky::new(&cov)?;
+
+        let mut cinv_f = vec![];
+        for pw in &powers {
+            let f: Vec<f64> = (0..n).map(|i| monomial(pw, &pts[i])).collect();
+            let mut x = vec![0.0; n];
+            chol.solve(&f, &mut x)?;
+            cinv_f.push(x);
+        }
+        // F^T C^-1 F and F^T C^-1 y
+        let mut fcf = Matrix::new(m, m, vec![0.0; m * m]);
+        let mut fcy = vec![0.0; m];
+        for k in 0..m {
+            for l in 0..m {
+                fcf[k][l] = (0..n)
+                    .map(|i| monomial(&powers[l], &pts[i]) * cinv_f[k][i])
+                    .sum();
+            }
+            fcy[k] = (0..n).map(|i| vals[i] * cinv_f[k][i]).sum();
+        }
+        let fcf = Cholesky::new(&fcf)?;
+        let mut beta = vec![0.0; m];
+        fcf.solve(&fcy, &mut beta)?;
+
+        let resid: Vec<f64> = (0..n)
+            .map(|i| {
+                vals[i]
+                    - powers
+                        .iter()
+                        .zip(beta.iter())
+                        .map(|(pw, b)| b * monomial(pw, &pts[i]))
+                        .sum::<f64>()
+            })
+            .collect();
+        let mut alpha = vec![0.0; n];
+        chol.solve(&resid, &mut alpha)?;
+        let quad = resid.iter().zip(alpha.iter()).map(|(r, a)| r * a).sum();
+        Ok(Self {
+            pts,
+            variogram,
+            powers,
+            chol,
+            beta,
+            alpha,
+            cinv_f,
+            fcf,
+            quad,
+        })
+    }
+
+    pub fn variogram(&self) -> &Variogram {
+        &self.variogram
+    }
+
+    /// Gaussian log likelihood of the data, with the trend at its generalized least
+    /// squares estimate.
+    pub fn log_likelihood(&self) -> f64 {
+        let n = self.pts.rows() as f64;
+        -0.5 * (self.quad + self.chol.logdet() + n * (2.0 * std::f64::consts::PI).ln())
+    }
+
+    /// Maximum likelihood variogram over the candidate ranges and nugget-to-sill ratios.
+    /// The sill is estimated in closed form for each candidate.
+    /// Returns the variogram and its log likelihood.
+    pub fn fit_ml(
+        pts: &Matrix<f64>,
+        vals: &[f64],
+        model: VariogramModel,
+        drift: Drift,
+        ranges: &[f64],
+        nugget_ratios: &[f64],
+    ) -> Result<(Variogram, f64)> {
+        let n = pts.rows() as f64;
+        let mut best: Option<(Variogram, f64)> = None;
+        for &range in ranges {
+            for &ratio in nugget_ratios {
+                let unit = Variogram::new(model, ratio, 1.0, range);
+                let krig = match Kriging::new(pts, vals, unit, drift) {
+                    Ok(krig) => krig,
+                    // not positive definite, e.g. a Gaussian model without nugget
+                    Err(_) => continue,
+                };
+                let sill = krig.quad / n;
+                let ll = -0.5
+                    * (n * sill.ln()
+                        + krig.chol.logdet()
+                        + n
+                        + n * (2.0 * std::f64::consts::PI).ln());
+                let better = match best {
+                    Some((_, b)) => ll > b,
+                    None => true,
+                };
+                if better {
+                    best = Some((Variogram::new(model, ratio * sill, sill, range), ll));
+                }
+            }
+        }
+        best.ok_or_else(|| InterpError::NotConverged().into())
+    }
+
+    pub fn interp(&self, pt: &[f64]) -> Result<f64> {
+        Ok(self.predict(pt)?.0)
+    }
+
+    /// Kriging mean at each row of `pts`, without the variances (see `predict`).
+    pub fn interp_many(&self, pts: &Matrix<f64>) -> Result<Vec<f64>> {
+        if pts.cols() != self.pts.cols() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_rows(pts, || (), |_, pt| self.interp(pt))
+    }
+
+    /// Kriging mean on the x1s.len() x x2s.len() grid of points (x1s\[i\], x2s\[j\]).
+    /// The data must lie in the plane.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        if self.pts.cols() != 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_grid(x1s, x2s, || (), |_, pt| self.interp(pt))
+    }
+
+    /// Prediction at `pt` with its kriging variance.
+    pub fn predict(&self, pt: &[f64]) -> Result<(f64, f64)> {
+        let n = self.pts.rows();
+        let dim = self.pts.cols();
+        if pt.len() != dim {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let c0: Vec<f64> = (0..n)
+            .map(|i| self.variogram.covariance(distance(&self.pts[i], pt, dim)))
+            .collect();
+        let f0: Vec<f64> = self.powers.iter().map(|pw| monomial(pw, pt)).collect();
+        let value = f0
+            .iter()
+            .zip(self.beta.iter())
+            .map(|(f, b)| f * b)
+            .sum::<f64>()
+            + c0.iter()
+                .zip(self.alpha.iter())
+                .map(|(c, a)| c * a)
+                .sum::<f64>();
+
+        // var = C(0) - c0^T C^-1 c0 + u^T (F^T C^-1 F)^-1 u, u = f0 - F^T C^-1 c0
+        let mut lc = vec![0.0; n];
+        self.chol.elsolve(&c0, &mut lc)?;
+        let u: Vec<f64> = f0
+            .iter()
+            .zip(self.cinv_f.iter())
+            .map(|(f, cf)| f - cf.iter().zip(c0.iter()).map(|(a, b)| a * b).sum::<f64>())
+            .collect();
+        let mut w = vec![0.0; u.len()];
+        self.fcf.solve(&u, &mut w)?;
+        let var = self.variogram.covariance(0.0) - lc.iter().map(|v| v * v).sum::<f64>()
+            + u.iter().zip(w.iter()).map(|(a, b)| a * b).sum::<f64>();
+        Ok((value, var.max(0.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interp::testing::perturbed_grid;
+
+    fn func(p: &[f64]) -> f64 {
+        (2.0 * p[0]).sin() * (3.0 * p[1]).cos() + p[0]
+    }
+
+    fn samples() -> (Matrix<f64>, Vec<f64>) {
+        let pts = perturbed_grid(8, 0.3);
+        let vals = (0..pts.rows()).map(|i| func(&pts[i])).collect();
+        (pts, vals)
+    }
+
+    #[test]
+    fn test_variogram() {
+        let models = [
+            VariogramModel::Spherical,
+            VariogramModel::Exponential,
+            VariogramModel::Gaussian,
+            VariogramModel::Matern(1),
+            VariogramModel::Matern(2),
+        ];
+        for model in models {
+            let v = Variogram::new(model, 0.1, 2.0, 0.5);
+            assert_eq!(v.gamma(0.0), 0.0);
+            assert!((v.gamma(1e-12) - 0.1).abs() < 1e-6);
+            assert!((v.gamma(100.0) - 2.1).abs() < 1e-10);
+            assert!((v.gamma(0.3) + v.covariance(0.3) - 2.1).abs() < 1e-14);
+        }
+        let t: f64 = 0.7;
+        let matern = |p| VariogramModel::Matern(p).correlation(t);
+        assert!((matern(0) - VariogramModel::Exponential.correlation(t)).abs() < 1e-15);
+        let s = 3.0f64.sqrt() * t;
+        assert!((matern(1) - (1.0 + s) * (-s).exp()).abs() < 1e-15);
+        let s = 5.0f64.sqrt() * t;
+        assert!((matern(2) - (1.0 + s + s * s / 3.0) * (-s).exp()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_empirical_variogram() {
+        // values linear along a line: gamma(h) = h^2 / 2
+        let n = 20;
+        let pts = Matrix::new(n, 1, (0..n).map(|i| i as f64).collect());
+        let vals: Vec<f64> = (0..n).map(|i| i as f64).collect();
+        let emp = EmpiricalVariogram::new(&pts, &vals, 5, 5.5).unwrap();
+        assert_eq!(emp.lags, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(emp.counts, vec![19, 18, 17, 16, 15]);
+        for (h, g) in emp.lags.iter().zip(emp.gammas.iter()) {
+            assert!((g - 0.5 * h * h).abs() < 1e-12);
+        }
+
+        // fit recovers the model it is sampled from
+        let truth = Variogram::new(VariogramModel::Spherical, 0.2, 1.5, 0.8);
+        let lags: Vec<f64> = (1..=30).map(|i| i as f64 * 0.04).collect();
+        let emp = EmpiricalVariogram {
+            gammas: lags.iter().map(|&h| truth.gamma(h)).collect(),
+            counts: vec![10; lags.len()],
+            lags,
+        };
+        let fit = Variogram::fit(&emp, VariogramModel::Spherical).unwrap();
+        assert!((fit.nugget - 0.2).abs() < 1e-4, "{:?}", fit);
+        assert!((fit.sill - 1.5).abs() < 1e-4, "{:?}", fit);
+        assert!((fit.range - 0.8).abs() < 1e-4, "{:?}", fit);
+    }
+
+    #[test]
+    fn test_kriging() {
+        let (pts, vals) = samples();
+        let v = Variogram::new(VariogramModel::Matern(2), 0.0, 1.0, 0.5);
+        for drift in [Drift::Ordinary, Drift::Universal(1)] {
+            let krig = Kriging::new(&pts, &vals, v, drift).unwrap();
+            for i in [0, 9, 35, 63] {
+                let (val, var) = krig.predict(&pts[i]).unwrap();
+                assert!((val - vals[i]).abs() < 1e-8);
+                assert!(var < 1e-8);
+            }
+            for p in [[0.3, 0.4], [0.55, 0.81]] {
+                let (val, var) = krig.predict(&p).unwrap();
+                assert!((val - func(&p)).abs() < 1e-3, "{} {}", val, func(&p));
+                assert!(var > 0.0);
+            }
+            // uncertainty grows away from the data
+            let inside = krig.predict(&[0.5, 0.5]).unwrap().1;
+            let outside = krig.predict(&[2.0, 2.0]).unwrap().1;
+            assert!(outside > 100.0 * inside);
+            assert!(krig.predict(&[0.5]).is_err());
+
+            let many = krig.interp_many(&pts).unwrap();
+            assert!(many
+                .iter()
+                .zip(vals.iter())
+                .all(|(a, b)| (a - b).abs() < 1e-8));
+            let grid = krig.interp_grid(&[0.3, 0.55], &[0.4, 0.81]).unwrap();
+            assert_eq!(grid[1][0], krig.interp(&[0.55, 0.4]).unwrap());
+            assert!(krig.interp_many(&Matrix::new(0, 3, vec![])).is_err());
+        }
+
+        // universal kriging reproduces its trend
+        let lin: Vec<f64> = (0..pts.rows())
+            .map(|i| 1.0 + 2.0 * pts[i][0] - pts[i][1])
+            .collect();
+        let krig = Kriging::new(&pts, &lin, v, Drift::Universal(1)).unwrap();
+        let (val, _) = krig.predict(&[3.0, -1.0]).unwrap();
+        assert!((val - 8.0).abs() < 1e-8, "{}", val);
+    }
+
+    #[test]
+    fn test_kriging_ml() {
+        let (pts, vals) = samples();
+        let model = VariogramModel::Exponential;
+        let ranges = [0.1, 0.3, 1.0, 3.0];
+        let ratios = [0.0, 0.01, 0.1];
+        let (best, ll) =
+            Kriging::fit_ml(&pts, &vals, model, Drift::Ordinary, &ranges, &ratios).unwrap();
+        let krig = Kriging::new(&pts, &vals, best, Drift::Ordinary).unwrap();
+        assert!((krig.log_likelihood() - ll).abs() < 1e-8);
+        // the maximum over the candidates and over the sill
+        for &range in &ranges {
+            for &ratio in &ratios {
+                let v = Variogram::new(model, ratio * best.sill, best.sill, range);
+                let other = Kriging::new(&pts, &vals, v, Drift::Ordinary).unwrap();
+                assert!(other.log_likelihood() <= ll + 1e-8);
+            }
+        }
+        for scale in [0.5, 2.0] {
+            let v = Variogram::new(model, best.nugget * scale, best.sill * scale, best.range);
+            let other = Kriging::new(&pts, &vals, v, Drift::Ordinary).unwrap();
+            assert!(other.log_likelihood() < ll);
+        }
+    }
+}
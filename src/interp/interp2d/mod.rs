@@ -1,6 +1,80 @@
 pub mod bicubic;
 pub mod bicubic_spline;
 pub mod bilinear;
+pub mod delaunay;
+pub mod kriging;
 pub mod poly2d;
 pub mod rbf;
 pub mod shep;
+
+use anyhow::Result;
+
+use crate::Matrix;
+
+/// `rows` x `cols` matrix whose i-th row is written by `row(i, out)`. With the `rayon`
+/// feature large grids are filled in parallel, one row per task.
+pub(crate) fn fill_grid<F>(rows: usize, cols: usize, row: F) -> Result<Matrix<f64>>
+where
+    F: Fn(usize, &mut [f64]) -> Result<()> + Sync + Send,
+{
+    let mut data = vec![0.0; rows * cols];
+    if cols > 0 {
+        #[cfg(feature = "rayon")]
+        if rows * cols >= crate::interp::interp1d::PAR_CHUNK {
+            use rayon::prelude::*;
+            data.par_chunks_mut(cols)
+                .enumerate()
+                .try_for_each(|(i, out)| row(i, out))?;
+            return Ok(Matrix::new(rows, cols, data));
+        }
+        data.chunks_mut(cols)
+            .enumerate()
+            .try_for_each(|(i, out)| row(i, out))?;
+    }
+    Ok(Matrix::new(rows, cols, data))
+}
+
+/// Value at each row of `pts` by `f`, which carries a lookup state (a cursor or the last
+/// triangle visited, say) from one point to the next. With the `rayon` feature large batches
+/// are split into chunks evaluated in parallel, each starting from `init()`.
+pub(crate) fn eval_rows<S, I, F>(pts: &Matrix<f64>, init: I, f: F) -> Result<Vec<f64>>
+where
+    I: Fn() -> S + Sync + Send,
+    F: Fn(&mut S, &[f64]) -> Result<f64> + Sync + Send,
+{
+    let chunk = |start: usize, out: &mut [f64]| -> Result<()> {
+        let mut state = init();
+        for (k, y) in out.iter_mut().enumerate() {
+            *y = f(&mut state, &pts[start + k])?;
+        }
+        Ok(())
+    };
+    let mut out = vec![0.0; pts.rows()];
+    #[cfg(feature = "rayon")]
+    {
+        use crate::interp::interp1d::PAR_CHUNK;
+        use rayon::prelude::*;
+        out.par_chunks_mut(PAR_CHUNK)
+            .enumerate()
+            .try_for_each(|(c, out)| chunk(c * PAR_CHUNK, out))?;
+    }
+    #[cfg(not(feature = "rayon"))]
+    chunk(0, &mut out)?;
+    Ok(out)
+}
+
+/// Values at all the points (x1s\[i\], x2s\[j\]) by `f` as a x1s.len() x x2s.len() matrix.
+/// The lookup state of `f` is carried along each row, as in `eval_rows`.
+pub(crate) fn eval_grid<S, I, F>(x1s: &[f64], x2s: &[f64], init: I, f: F) -> Result<Matrix<f64>>
+where
+    I: Fn() -> S + Sync + Send,
+    F: Fn(&mut S, &[f64]) -> Result<f64> + Sync + Send,
+{
+    fill_grid(x1s.len(), x2s.len(), |r, out| {
+        let mut state = init();
+        for (y, &x2) in out.iter_mut().zip(x2s.iter()) {
+            *y = f(&mut state, &[x1s[r], x2])?;
+        }
+        Ok(())
+    })
+}
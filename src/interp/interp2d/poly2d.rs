@@ -1,62 +1,115 @@
+use anyhow::Result;
+
 use crate::{
-    interp::{poly_1d::Poly1D, Interp},
+    interp::{
+        poly_1d::{neville, Poly1D},
+        strictly_monotone, Cursor, Interp, InterpError,
+    },
     Matrix,
 };
 
+use super::fill_grid;
+
 pub struct Poly2D<'a> {
-    m: usize,
+    _m: usize,
     _n: usize,
     mm: usize,
-    _nn: usize,
+    nn: usize,
     y: &'a Matrix<f64>,
     yv: Vec<f64>,
-    x1v: &'a Vec<f64>,
-    x2interp: Poly1D<'a>,
+    x1interp: Poly1D<f64>,
+    x2interp: Poly1D<f64>,
+    x1cursor: Cursor,
+    x2cursor: Cursor,
 }
 
 impl<'a> Poly2D<'a> {
+    /// Interpolates through the `mp` x `np` nodes around each point.
     pub fn new(
-        x1v: &'a Vec<f64>,
-        x2v: &'a Vec<f64>,
+        x1v: &[f64],
+        x2v: &[f64],
         ym: &'a Matrix<f64>,
         mp: usize,
         np: usize,
-    ) -> Self {
+    ) -> Result<Self> {
+        if ym.rows() != x1v.len() || ym.cols() != x2v.len() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if !strictly_monotone(x1v) || !strictly_monotone(x2v) {
+            return Err(InterpError::IdenticalX().into());
+        }
         let yv = vec![0.0; x1v.len()];
-        let x2interp = Poly1D::new(x2v, x2v, np);
-        Self {
-            m: x1v.len(),
+        Ok(Self {
+            _m: x1v.len(),
             _n: x2v.len(),
             mm: mp,
-            _nn: np,
+            nn: np,
             y: ym,
             yv,
-            x1v,
-            x2interp,
-        }
+            x1interp: Poly1D::new(x1v, x1v, mp)?,
+            x2interp: Poly1D::new(x2v, x2v, np)?,
+            x1cursor: Cursor::new(x1v.len()),
+            x2cursor: Cursor::new(x2v.len()),
+        })
     }
 
-    pub fn interp(&mut self, x1p: f64, x2p: f64) -> f64 {
-        let mut x1interp = Poly1D::new(self.x1v, self.x1v, self.mm);
-        let i = if x1interp.cor() == 1 {
-            x1interp.hunt(x1p)
-        } else {
-            x1interp.locate(x1p)
-        };
-        let j = if self.x2interp.cor() == 1 {
-            self.x2interp.hunt(x2p)
-        } else {
-            self.x2interp.locate(x2p)
-        };
-        for i in 0..self.m {
-            self.x2interp.set_yy(&self.y[i]);
-            self.yv[i] = self.x2interp.rawinterp(j, x2p).unwrap();
+    pub fn interp(&mut self, x1p: f64, x2p: f64) -> Result<f64> {
+        let i = self.x1interp.hunt(&mut self.x1cursor, x1p);
+        let j = self.x2interp.hunt(&mut self.x2cursor, x2p);
+        let x2v = &self.x2interp.xx()[j..(j + self.nn)];
+        for k in i..(i + self.mm) {
+            self.yv[k] = neville(x2v, &self.y[k][j..(j + self.nn)], x2p)?;
         }
-        x1interp.set_yy(&self.yv);
-        x1interp.rawinterp(i, x1p).unwrap()
+        neville(
+            &self.x1interp.xx()[i..(i + self.mm)],
+            &self.yv[i..(i + self.mm)],
+            x1p,
+        )
+    }
+
+    /// Values at all the points (x1s\[i\], x2s\[j\]) as a x1s.len() x x2s.len() matrix.
+    /// The Lagrange weights of each coordinate are computed only once, hunting from the
+    /// previous one, so each point costs a weighted sum of `mp` x `np` table values.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        let rows = weights(&self.x1interp, self.mm, x1s);
+        let cols = weights(&self.x2interp, self.nn, x2s);
+        fill_grid(rows.len(), cols.len(), |r, out| {
+            let (i, w1) = &rows[r];
+            for (y, (j, w2)) in out.iter_mut().zip(cols.iter()) {
+                *y = w1
+                    .iter()
+                    .enumerate()
+                    .map(|(k, wk)| {
+                        let row = &self.y[i + k][*j..(j + self.nn)];
+                        wk * row.iter().zip(w2.iter()).map(|(y, w)| y * w).sum::<f64>()
+                    })
+                    .sum();
+            }
+            Ok(())
+        })
     }
 }
 
+/// First of the `mm` nodes used for each of `xs` and the Lagrange weights of those nodes.
+fn weights(interp: &Poly1D<f64>, mm: usize, xs: &[f64]) -> Vec<(usize, Vec<f64>)> {
+    let mut cursor = Cursor::new(interp.n());
+    xs.iter()
+        .map(|&x| {
+            let i = interp.hunt(&mut cursor, x);
+            let xv = &interp.xx()[i..(i + mm)];
+            let w = (0..mm)
+                .map(|l| {
+                    (0..mm)
+                        .filter(|&m| m != l)
+                        .map(|m| (x - xv[m]) / (xv[l] - xv[m]))
+                        .product()
+                })
+                .collect();
+            (i, w)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,18 +126,51 @@ mod tests {
             4.0, 5.0, 6.0, 7.0, 8.0,
             5.0, 6.0, 7.0, 8.0, 9.0,
         ]);
-        let mut interp = Poly2D::new(&x1, &x2, &y, 2, 2);
+        let mut interp = Poly2D::new(&x1, &x2, &y, 2, 2).unwrap();
 
-        let res = interp.interp(0.0, 0.0);
+        let res = interp.interp(0.0, 0.0).unwrap();
         assert!((res - 1.0).abs() < 1e-5, "res = {}", res);
 
-        let res = interp.interp(0.5, 0.5);
+        let res = interp.interp(0.5, 0.5).unwrap();
         assert!((res - 2.0).abs() < 1e-5);
 
-        let res = interp.interp(2.5, 3.0);
+        let res = interp.interp(2.5, 3.0).unwrap();
         assert!((res - 6.0).abs() < 1e-5);
 
-        let res = interp.interp(3.5, 5.0);
+        let res = interp.interp(3.5, 5.0).unwrap();
         assert!((res - 7.5).abs() < 1e-5);
+
+        assert!(Poly2D::new(&x1, &x2, &y, 6, 2).is_err());
+        assert!(Poly2D::new(&x1, &x2, &y, 2, 0).is_err());
+        assert!(Poly2D::new(&x1[..4], &x2, &y, 2, 2).is_err());
+        let x1 = vec![0.0, 1.0, 1.0, 3.0, 5.0];
+        assert!(Poly2D::new(&x1, &x2, &y, 2, 2).is_err());
+    }
+
+    #[test]
+    fn test_poly2d_grid() {
+        let x1: Vec<f64> = vec![0.0, 1.0, 2.0, 3.0, 5.0, 6.0];
+        let x2: Vec<f64> = vec![0.0, 1.0, 2.0, 4.0, 8.0];
+        let data: Vec<f64> = x1
+            .iter()
+            .flat_map(|&a| x2.iter().map(move |&b| (0.4 * a).sin() * (0.3 * b).cos()))
+            .collect();
+        let y = Matrix::new(x1.len(), x2.len(), data);
+        let mut interp = Poly2D::new(&x1, &x2, &y, 3, 4).unwrap();
+        let x1s = vec![0.2, 0.9, 2.5, 4.1, 5.7, 1.3];
+        let x2s = vec![0.1, 3.0, 5.5, 7.9];
+        let grid = interp.interp_grid(&x1s, &x2s).unwrap();
+        assert_eq!((grid.rows(), grid.cols()), (x1s.len(), x2s.len()));
+        for (i, &a) in x1s.iter().enumerate() {
+            for (j, &b) in x2s.iter().enumerate() {
+                let res = interp.interp(a, b).unwrap();
+                assert!(
+                    (grid[i][j] - res).abs() < 1e-10,
+                    "{} != {}",
+                    grid[i][j],
+                    res
+                );
+            }
+        }
     }
 }
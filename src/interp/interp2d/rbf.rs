@@ -1,6 +1,18 @@
-use crate::{linalg::lu_decomposition::LUdcmp, MatLinAlgBound, Matrix};
+use anyhow::Result;
 
-pub trait RBFFunc {
+use crate::{
+    interp::{kdtree::KdTree, InterpError},
+    linalg::{
+        lingcd::{Linbcg, NRsparseLingcb},
+        lu_decomposition::LUdcmp,
+        sparse::NRsparseMat,
+    },
+    Matrix,
+};
+
+use super::{eval_grid, eval_rows};
+
+pub trait RBFFunc: Send + Sync {
     fn rbf(&self, r: f64) -> f64;
 }
 
@@ -20,6 +32,117 @@ impl RBFFunc for MultiQuadric {
     }
 }
 
+pub struct InverseMultiQuadric {
+    r02: f64,
+}
+
+impl InverseMultiQuadric {
+    pub fn new(scale: f64) -> Self {
+        Self { r02: scale * scale }
+    }
+}
+
+impl RBFFunc for InverseMultiQuadric {
+    fn rbf(&self, r: f64) -> f64 {
+        1.0 / (r * r + self.r02).sqrt()
+    }
+}
+
+pub struct Gaussian {
+    r0: f64,
+}
+
+impl Gaussian {
+    pub fn new(scale: f64) -> Self {
+        Self { r0: scale }
+    }
+}
+
+impl RBFFunc for Gaussian {
+    fn rbf(&self, r: f64) -> f64 {
+        (-0.5 * (r / self.r0) * (r / self.r0)).exp()
+    }
+}
+
+/// r^2 log(r / r0). Needs a polynomial augmentation of degree 1.
+pub struct ThinPlate {
+    r0: f64,
+}
+
+impl ThinPlate {
+    pub fn new(scale: f64) -> Self {
+        Self { r0: scale }
+    }
+}
+
+impl RBFFunc for ThinPlate {
+    fn rbf(&self, r: f64) -> f64 {
+        if r <= 0.0 {
+            0.0
+        } else {
+            r * r * (r / self.r0).ln()
+        }
+    }
+}
+
+/// r^k for odd k, r^k log(r) for even k. Needs a polynomial augmentation of degree k / 2.
+pub struct Polyharmonic {
+    k: i32,
+}
+
+impl Polyharmonic {
+    pub fn new(k: i32) -> Self {
+        Self { k }
+    }
+}
+
+impl RBFFunc for Polyharmonic {
+    fn rbf(&self, r: f64) -> f64 {
+        if self.k % 2 == 1 {
+            r.powi(self.k)
+        } else if r <= 0.0 {
+            0.0
+        } else {
+            r.powi(self.k) * r.ln()
+        }
+    }
+}
+
+/// Wendland's C2 function (1 - r / r0)^4 (4 r / r0 + 1), zero beyond the support radius r0.
+/// Positive definite for dimension <= 3.
+pub struct Wendland {
+    r0: f64,
+}
+
+impl Wendland {
+    pub fn new(radius: f64) -> Self {
+        Self { r0: radius }
+    }
+}
+
+impl RBFFunc for Wendland {
+    fn rbf(&self, r: f64) -> f64 {
+        let t = r / self.r0;
+        if t >= 1.0 {
+            0.0
+        } else {
+            (1.0 - t).powi(4) * (4.0 * t + 1.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RBFOptions {
+    /// Normalized RBF: the weighted sum is divided by sum_i rbf(|x - x_i|).
+    pub norm: bool,
+    /// Total degree of the polynomial added to the interpolant, `None` for no polynomial.
+    pub poly_degree: Option<usize>,
+    /// Ridge parameter added to the diagonal. Zero interpolates the data exactly.
+    pub smoothing: f64,
+}
+
+/// Radial basis function interpolation
+/// (Section 3.7.1 of Numerical Recipes in C)
 pub struct RBF<'a, T>
 where
     T: RBFFunc,
@@ -27,8 +150,10 @@ where
     dim: usize,
     n: usize,
     w: Vec<f64>,
+    /// coefficients of the monomials in `powers`
+    coeffs: Vec<f64>,
+    powers: Vec<Vec<i32>>,
     pts: &'a Matrix<f64>,
-    _vals: &'a Vec<f64>,
     rbf_fn: T,
     norm: bool,
 }
@@ -37,60 +162,302 @@ impl<'a, T> RBF<'a, T>
 where
     T: RBFFunc,
 {
-    pub fn new(pts: &'a Matrix<f64>, vals: &'a Vec<f64>, rbf_fn: T, norm: bool) -> Self {
+    pub fn new(pts: &'a Matrix<f64>, vals: &[f64], rbf_fn: T, norm: bool) -> Result<Self> {
+        let opts = RBFOptions {
+            norm,
+            ..RBFOptions::default()
+        };
+        Self::with_options(pts, vals, rbf_fn, opts)
+    }
+
+    pub fn with_options(
+        pts: &'a Matrix<f64>,
+        vals: &[f64],
+        rbf_fn: T,
+        opts: RBFOptions,
+    ) -> Result<Self> {
         let dim = pts.cols();
         let n = pts.rows();
-        let mut w = vec![0.0; n];
-
-        let mut mat = Matrix::new(n, n, vec![0.0; n * n]);
-        for i in 0..n {
-            for j in 0..n {
-                let mut dist = 0.0;
-                for k in 0..dim {
-                    dist += (pts[i][k] - pts[j][k]).square();
-                }
-                dist = dist.sqrt();
-                mat[i][j] = rbf_fn.rbf(dist);
-                if norm && dist > 1e-8 {
-                    mat[i][j] /= dist;
-                }
-            }
+        if vals.len() != n {
+            return Err(InterpError::SizeNotMatch().into());
         }
-        LUdcmp::new(&mat).unwrap().solve(&vals, &mut w).unwrap();
-
-        Self {
-            dim: pts.cols(),
+        let powers = monomials(dim, opts.poly_degree);
+        let mat = system(pts, &rbf_fn, &powers, &opts);
+        let mut rhs = vals.to_vec();
+        rhs.resize(n + powers.len(), 0.0);
+        let mut x = vec![0.0; rhs.len()];
+        LUdcmp::new(&mat)?.solve(&rhs, &mut x)?;
+        let coeffs = x.split_off(n);
+        Ok(Self {
+            dim,
             n,
-            w,
+            w: x,
+            coeffs,
+            powers,
             pts,
-            _vals: vals,
-            norm,
             rbf_fn,
+            norm: opts.norm,
+        })
+    }
+
+    /// Leave-one-out errors of the interpolant with `rbf_fn` at each point, by Rippa's formula
+    /// e_k = c_k / (A^-1)_kk, which needs a single inversion of the system.
+    pub fn loocv_errors(
+        pts: &Matrix<f64>,
+        vals: &[f64],
+        rbf_fn: &T,
+        opts: RBFOptions,
+    ) -> Result<Vec<f64>> {
+        let n = pts.rows();
+        if vals.len() != n {
+            return Err(InterpError::SizeNotMatch().into());
         }
+        let powers = monomials(pts.cols(), opts.poly_degree);
+        let mat = system(pts, rbf_fn, &powers, &opts);
+        let size = mat.rows();
+        let mut inv = Matrix::new(size, size, vec![0.0; size * size]);
+        LUdcmp::new(&mat)?.inverse(&mut inv)?;
+        Ok((0..n)
+            .map(|k| {
+                let ck: f64 = (0..n).map(|j| inv[k][j] * vals[j]).sum();
+                ck / inv[k][k]
+            })
+            .collect())
     }
 
-    pub fn interp(&self, pt: &Vec<f64>) -> f64 {
+    /// The scale among `scales` giving the smallest RMS leave-one-out error, with that error.
+    pub fn select_scale<F>(
+        pts: &Matrix<f64>,
+        vals: &[f64],
+        make: F,
+        opts: RBFOptions,
+        scales: &[f64],
+    ) -> Result<(f64, f64)>
+    where
+        F: Fn(f64) -> T,
+    {
+        let mut best = (f64::NAN, f64::INFINITY);
+        for &scale in scales {
+            let errs = match Self::loocv_errors(pts, vals, &make(scale), opts) {
+                Ok(errs) => errs,
+                Err(_) => continue,
+            };
+            let cost = (errs.iter().map(|e| e * e).sum::<f64>() / errs.len() as f64).sqrt();
+            if cost < best.1 {
+                best = (scale, cost);
+            }
+        }
+        if best.0.is_nan() {
+            return Err(InterpError::NotConverged().into());
+        }
+        Ok(best)
+    }
+
+    pub fn interp(&self, pt: &[f64]) -> Result<f64> {
+        if pt.len() != self.dim {
+            return Err(InterpError::SizeNotMatch().into());
+        }
         let mut ans = 0.0;
         let mut den = 0.0;
         for i in 0..self.n {
-            let mut dist = 0.0;
-            for k in 0..self.dim {
-                dist += (self.pts[i][k] - pt[k]).square();
-            }
-            dist = dist.sqrt();
-            ans += self.w[i] * self.rbf_fn.rbf(dist);
-            den += dist;
+            let dist = distance(&self.pts[i], pt, self.dim);
+            let fval = self.rbf_fn.rbf(dist);
+            ans += self.w[i] * fval;
+            den += fval;
         }
         if self.norm {
             ans /= den;
         }
-        ans
+        Ok(ans
+            + self
+                .powers
+                .iter()
+                .zip(self.coeffs.iter())
+                .map(|(pw, c)| c * monomial(pw, pt))
+                .sum::<f64>())
+    }
+
+    /// Value of the RBF expansion, polynomial included, at each row of `pts`.
+    pub fn interp_many(&self, pts: &Matrix<f64>) -> Result<Vec<f64>> {
+        if pts.cols() != self.dim {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_rows(pts, || (), |_, pt| self.interp(pt))
+    }
+
+    /// RBF expansion evaluated on the x1s.len() x x2s.len() grid of points (x1s\[i\], x2s\[j\]).
+    /// Only for an interpolant built from points in the plane.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        if self.dim != 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_grid(x1s, x2s, || (), |_, pt| self.interp(pt))
     }
 }
 
+/// RBF interpolation with the compactly supported Wendland kernel.
+/// Only the points closer than the support radius interact, so the system is sparse: it is
+/// assembled from k-d tree range queries and solved by the preconditioned conjugate gradient
+/// method. Evaluation visits only the points within the radius.
+pub struct CompactRBF<'a> {
+    tree: KdTree<'a>,
+    w: Vec<f64>,
+    rbf_fn: Wendland,
+    radius: f64,
+}
+
+impl<'a> CompactRBF<'a> {
+    pub fn new(pts: &'a Matrix<f64>, vals: &[f64], radius: f64) -> Result<Self> {
+        let n = pts.rows();
+        if vals.len() != n {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let tree = KdTree::new(pts)?;
+        let rbf_fn = Wendland::new(radius);
+
+        // symmetric, so column j holds the neighbours of pts[j]
+        let mut col_ptr = vec![0; n + 1];
+        let mut row_ind = vec![];
+        let mut val = vec![];
+        for j in 0..n {
+            let mut near = tree.within(&pts[j], radius);
+            near.sort_by_key(|&(i, _)| i);
+            for (i, d) in near {
+                row_ind.push(i);
+                val.push(rbf_fn.rbf(d));
+            }
+            col_ptr[j + 1] = row_ind.len();
+        }
+        let mut mat = NRsparseMat::new(n, n, row_ind.len());
+        mat.col_ptr = col_ptr;
+        mat.row_ind = row_ind;
+        mat.val = val;
+
+        let b = vals.to_vec();
+        let mut w = vec![0.0; n];
+        NRsparseLingcb::new(&mat).solve(&b, &mut w, 1, 1e-12, 10 * n.max(10), 0, 0.0);
+        let scale = vals.iter().fold(0.0f64, |acc, v| acc.max(v.abs())).max(1.0);
+        let residual = mat
+            .ax(&w)
+            .iter()
+            .zip(vals.iter())
+            .fold(0.0f64, |acc, (a, v)| acc.max((a - v).abs()));
+        if residual.is_nan() || residual > 1e-8 * scale {
+            return Err(InterpError::NotConverged().into());
+        }
+        Ok(Self {
+            tree,
+            w,
+            rbf_fn,
+            radius,
+        })
+    }
+
+    pub fn interp(&self, pt: &[f64]) -> Result<f64> {
+        if pt.len() != self.tree.pts().cols() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(self
+            .tree
+            .within(pt, self.radius)
+            .iter()
+            .map(|&(i, d)| self.w[i] * self.rbf_fn.rbf(d))
+            .sum())
+    }
+
+    /// Value at each row of `pts`, summing only over the centres within the support radius.
+    pub fn interp_many(&self, pts: &Matrix<f64>) -> Result<Vec<f64>> {
+        if pts.cols() != self.tree.pts().cols() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_rows(pts, || (), |_, pt| self.interp(pt))
+    }
+
+    /// Wendland expansion on the x1s.len() x x2s.len() grid of points (x1s\[i\], x2s\[j\]).
+    /// The centres must be two-dimensional.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        if self.tree.pts().cols() != 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_grid(x1s, x2s, || (), |_, pt| self.interp(pt))
+    }
+}
+
+/// Matrix of the interpolation conditions, augmented with the monomials and the
+/// orthogonality conditions on the weights.
+fn system<T>(pts: &Matrix<f64>, rbf_fn: &T, powers: &[Vec<i32>], opts: &RBFOptions) -> Matrix<f64>
+where
+    T: RBFFunc,
+{
+    let dim = pts.cols();
+    let n = pts.rows();
+    let size = n + powers.len();
+    let mut mat = Matrix::new(size, size, vec![0.0; size * size]);
+    for i in 0..n {
+        for j in 0..n {
+            mat[i][j] = rbf_fn.rbf(distance(&pts[i], &pts[j], dim));
+        }
+        if opts.norm {
+            // row i holds the conditions at pts[i], divided like `interp` does there
+            let sum: f64 = mat[i][..n].iter().sum();
+            for v in mat[i][..n].iter_mut() {
+                *v /= sum;
+            }
+        }
+        mat[i][i] += opts.smoothing;
+        for (k, pw) in powers.iter().enumerate() {
+            let p = monomial(pw, &pts[i]);
+            mat[i][n + k] = p;
+            mat[n + k][i] = p;
+        }
+    }
+    mat
+}
+
+pub(crate) fn distance(a: &[f64], b: &[f64], dim: usize) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .take(dim)
+        .map(|(x1, x2)| (x1 - x2) * (x1 - x2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Exponents of the monomials of total degree <= `degree` in `dim` variables.
+pub(crate) fn monomials(dim: usize, degree: Option<usize>) -> Vec<Vec<i32>> {
+    let degree = match degree {
+        Some(d) => d as i32,
+        None => return vec![],
+    };
+    let mut res: Vec<Vec<i32>> = vec![vec![]];
+    for _ in 0..dim {
+        res = res
+            .into_iter()
+            .flat_map(|pw| {
+                let used: i32 = pw.iter().sum();
+                (0..=(degree - used)).map(move |e| {
+                    let mut next = pw.clone();
+                    next.push(e);
+                    next
+                })
+            })
+            .collect();
+    }
+    res
+}
+
+pub(crate) fn monomial(powers: &[i32], pt: &[f64]) -> f64 {
+    powers
+        .iter()
+        .zip(pt.iter())
+        .map(|(&e, &x)| x.powi(e))
+        .product()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interp::testing::perturbed_grid;
 
     #[test]
     fn test_rbf() {
@@ -98,13 +465,155 @@ mod tests {
         let pts = Matrix::new(5, 2, vec![1.0, 1.0, 1.0, 2.0, 2.0, 2.0, 2.0, 1.0, 0.0, 0.0]);
         let vals = vec![1.0, 5.0, 8.0, 5.0, 0.0];
 
-        let rbf = RBF::new(&pts, &vals, rbf_fn, false);
+        let rbf = RBF::new(&pts, &vals, rbf_fn, false).unwrap();
         let pt = vec![1.0, 1.0];
-        let res = rbf.interp(&pt);
+        let res = rbf.interp(&pt).unwrap();
         assert!((res - 1.0).abs() < 1e-5);
 
+        // the normalized interpolant also goes through the data
         let rbf_fn = MultiQuadric::new(1.0);
-        let rbf = RBF::new(&pts, &vals, rbf_fn, true);
-        let _res = rbf.interp(&pt);
+        let rbf = RBF::new(&pts, &vals, rbf_fn, true).unwrap();
+        for i in 0..5 {
+            let res = rbf.interp(&pts[i]).unwrap();
+            assert!((res - vals[i]).abs() < 1e-8, "res = {}", res);
+        }
+        assert!(RBF::new(&pts, &vals[1..], MultiQuadric::new(1.0), false).is_err());
+
+        let many = rbf.interp_many(&pts).unwrap();
+        for i in 0..5 {
+            assert!((many[i] - rbf.interp(&pts[i]).unwrap()).abs() < 1e-14);
+        }
+        let grid = rbf.interp_grid(&[0.5, 1.5], &[0.0, 1.0, 2.5]).unwrap();
+        assert!((grid[1][2] - rbf.interp(&[1.5, 2.5]).unwrap()).abs() < 1e-14);
+        assert!(rbf.interp_many(&Matrix::new(1, 3, vec![0.0; 3])).is_err());
+        assert!(rbf.interp(&[1.0]).is_err());
+    }
+
+    fn samples() -> (Matrix<f64>, Vec<f64>) {
+        let pts = perturbed_grid(7, 0.2);
+        let vals = (0..pts.rows()).map(|i| func(&pts[i])).collect();
+        (pts, vals)
+    }
+
+    fn func(p: &[f64]) -> f64 {
+        (2.0 * p[0]).sin() * (1.0 + p[1] * p[1]).ln() + p[0]
+    }
+
+    #[test]
+    fn test_rbf_kernels() {
+        let (pts, vals) = samples();
+        let test_pts = [[0.33, 0.71], [0.5, 0.5], [0.9, 0.12]];
+        let check = |name: &str, rbf_interp: &dyn Fn(&[f64]) -> f64, tol: f64| {
+            for i in 0..pts.rows() {
+                assert!((rbf_interp(&pts[i]) - vals[i]).abs() < 1e-6);
+            }
+            for p in &test_pts {
+                let err = (rbf_interp(p) - func(p)).abs();
+                assert!(err < tol, "{}: p = {:?}, err = {}", name, p, err);
+            }
+        };
+
+        let rbf = RBF::new(&pts, &vals, Gaussian::new(0.3), false).unwrap();
+        check("gaussian", &|p| rbf.interp(p).unwrap(), 5e-3);
+        let rbf = RBF::new(&pts, &vals, InverseMultiQuadric::new(0.3), false).unwrap();
+        check("imq", &|p| rbf.interp(p).unwrap(), 2e-2);
+        let opts = RBFOptions {
+            poly_degree: Some(1),
+            ..RBFOptions::default()
+        };
+        let rbf = RBF::with_options(&pts, &vals, ThinPlate::new(1.0), opts).unwrap();
+        check("thin plate", &|p| rbf.interp(p).unwrap(), 1e-2);
+        let rbf = RBF::with_options(&pts, &vals, Polyharmonic::new(3), opts).unwrap();
+        check("polyharmonic", &|p| rbf.interp(p).unwrap(), 1e-2);
+        let rbf = RBF::with_options(&pts, &vals, Wendland::new(0.8), opts).unwrap();
+        check("wendland", &|p| rbf.interp(p).unwrap(), 1e-2);
+        assert_eq!(Wendland::new(0.8).rbf(0.9), 0.0);
+
+        // linear functions are reproduced with the polynomial part
+        let lin: Vec<f64> = (0..pts.rows())
+            .map(|i| 1.0 + 2.0 * pts[i][0] - pts[i][1])
+            .collect();
+        let rbf = RBF::with_options(&pts, &lin, ThinPlate::new(1.0), opts).unwrap();
+        let res = rbf.interp(&[0.37, 0.61]).unwrap();
+        assert!((res - (1.0 + 0.74 - 0.61)).abs() < 1e-10, "res = {}", res);
+    }
+
+    #[test]
+    fn test_rbf_smoothing() {
+        let (pts, vals) = samples();
+        let noisy: Vec<f64> = vals
+            .iter()
+            .enumerate()
+            .map(|(i, v)| v + if i % 2 == 0 { 0.01 } else { -0.01 })
+            .collect();
+        let opts = RBFOptions {
+            smoothing: 1e-1,
+            ..RBFOptions::default()
+        };
+        let rbf = RBF::with_options(&pts, &noisy, Gaussian::new(0.3), opts).unwrap();
+        // no longer interpolates
+        let misfit = (0..pts.rows())
+            .map(|i| (rbf.interp(&pts[i]).unwrap() - noisy[i]).abs())
+            .fold(0.0, f64::max);
+        assert!(misfit > 1e-3, "misfit = {}", misfit);
+    }
+
+    #[test]
+    fn test_rbf_loocv() {
+        let (pts, vals) = samples();
+        let opts = RBFOptions::default();
+        // Rippa's formula agrees with actually leaving a point out
+        let errs = RBF::loocv_errors(&pts, &vals, &Gaussian::new(0.3), opts).unwrap();
+        let k = 17;
+        let rest: Vec<usize> = (0..pts.rows()).filter(|&i| i != k).collect();
+        let sub = Matrix::new(
+            rest.len(),
+            2,
+            rest.iter().flat_map(|&i| pts[i].to_vec()).collect(),
+        );
+        let subvals: Vec<f64> = rest.iter().map(|&i| vals[i]).collect();
+        let rbf = RBF::new(&sub, &subvals, Gaussian::new(0.3), false).unwrap();
+        let direct = vals[k] - rbf.interp(&pts[k]).unwrap();
+        assert!((errs[k] - direct).abs() < 1e-8, "{} {}", errs[k], direct);
+
+        let scales = [0.05, 0.1, 0.2, 0.3, 0.5];
+        let (scale, cost) = RBF::select_scale(&pts, &vals, Gaussian::new, opts, &scales).unwrap();
+        assert!(scale > 0.05, "scale = {}", scale);
+        let cost_small = RBF::loocv_errors(&pts, &vals, &Gaussian::new(0.05), opts).unwrap();
+        let rms = (cost_small.iter().map(|e| e * e).sum::<f64>() / cost_small.len() as f64).sqrt();
+        assert!(cost <= rms);
+    }
+
+    #[test]
+    fn test_compact_rbf() {
+        let (pts, vals) = samples();
+        let radius = 0.4;
+        let rbf = CompactRBF::new(&pts, &vals, radius).unwrap();
+        for i in 0..pts.rows() {
+            assert!((rbf.interp(&pts[i]).unwrap() - vals[i]).abs() < 1e-8);
+        }
+        // same interpolant as the dense solve
+        let dense = RBF::new(&pts, &vals, Wendland::new(radius), false).unwrap();
+        for p in [[0.13, 0.77], [0.5, 0.5], [0.91, 0.08]] {
+            let (a, b) = (rbf.interp(&p).unwrap(), dense.interp(&p).unwrap());
+            assert!((a - b).abs() < 1e-8, "{} {}", a, b);
+        }
+        assert!(CompactRBF::new(&pts, &vals[1..], radius).is_err());
+        assert!(rbf.interp(&[0.5]).is_err());
+        let mut bad = pts.clone();
+        bad[3][0] = f64::NAN;
+        assert!(CompactRBF::new(&bad, &vals, radius).is_err());
+
+        let many = rbf.interp_many(&pts).unwrap();
+        for i in 0..pts.rows() {
+            assert!((many[i] - vals[i]).abs() < 1e-8);
+        }
+        let ticks = [0.0, 0.13, 0.5, 0.91];
+        let grid = rbf.interp_grid(&ticks, &ticks).unwrap();
+        for (i, &a) in ticks.iter().enumerate() {
+            for (j, &b) in ticks.iter().enumerate() {
+                assert_eq!(grid[i][j], rbf.interp(&[a, b]).unwrap());
+            }
+        }
     }
 }
@@ -1,8 +1,13 @@
 /// Implementation of Shepard Interpolation
 /// (Section 3.7.3 of Numerical Recipes in C)
-use crate::{interp::InterpError, Matrix};
+use crate::{
+    interp::{kdtree::KdTree, InterpError},
+    Matrix,
+};
 use anyhow::Result;
 
+use super::{eval_grid, eval_rows};
+
 /// Struct for Shep interpolation
 pub struct Shep<'a> {
     /// Given scattered points (2d-array). \[n_point, n_dimension\]
@@ -46,6 +51,96 @@ impl<'a> Shep<'a> {
             .sum();
         Ok(nume / deno)
     }
+
+    /// Inverse distance weighted value at each row of `pts`, each a sum over all the points.
+    pub fn interp_many(&self, pts: &Matrix<f64>) -> Result<Vec<f64>> {
+        if pts.cols() != self.pts.cols() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_rows(pts, || (), |_, pt| self.interp(pt))
+    }
+
+    /// Inverse distance weighted values on the x1s.len() x x2s.len() grid of points
+    /// (x1s\[i\], x2s\[j\]), for scattered points in the plane.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        if self.pts.cols() != 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_grid(x1s, x2s, || (), |_, pt| self.interp(pt))
+    }
+}
+
+/// Modified Shepard interpolation (Franke and Little) over the `k` nearest points.
+/// The weight of a point at distance d is ((R - d) / (R d))^power, where R is the distance
+/// to the (k + 1)-th nearest point, so that the interpolant stays continuous.
+pub struct LocalShep<'a> {
+    tree: KdTree<'a>,
+    /// values of the points. length = n_point
+    vals: &'a [f64],
+    /// number of neighbours used
+    k: usize,
+    /// desired exponent
+    power: f64,
+}
+
+impl<'a> LocalShep<'a> {
+    pub fn new(pts: &'a Matrix<f64>, vals: &'a [f64], k: usize, power: f64) -> Result<Self> {
+        if vals.len() != pts.rows() || k == 0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
+            tree: KdTree::new(pts)?,
+            vals,
+            k,
+            power,
+        })
+    }
+
+    pub fn interp(&self, pt: &[f64]) -> Result<f64> {
+        if pt.len() != self.tree.pts().cols() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let mut near = self.tree.knn(pt, self.k + 1);
+        if near.is_empty() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if near[0].1 < 1e-8 {
+            return Ok(self.vals[near[0].0]);
+        }
+        let radius = if near.len() > self.k {
+            near.pop().unwrap().1
+        } else {
+            // fewer points than neighbours: all of them get a positive weight
+            2.0 * near[near.len() - 1].1
+        };
+        let (nume, deno) = near.iter().fold((0.0, 0.0), |(nume, deno), &(i, d)| {
+            let w = ((radius - d) / (radius * d)).max(0.0).powf(self.power);
+            (nume + w * self.vals[i], deno + w)
+        });
+        if deno > 0.0 {
+            Ok(nume / deno)
+        } else {
+            // all the neighbours are as far as the (k + 1)-th one
+            Ok(near.iter().map(|&(i, _)| self.vals[i]).sum::<f64>() / near.len() as f64)
+        }
+    }
+
+    /// Value at each row of `pts` from its k nearest points, each found by a k-d tree query.
+    pub fn interp_many(&self, pts: &Matrix<f64>) -> Result<Vec<f64>> {
+        if pts.cols() != self.tree.pts().cols() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_rows(pts, || (), |_, pt| self.interp(pt))
+    }
+
+    /// Local Shepard values on the x1s.len() x x2s.len() grid of points (x1s\[i\], x2s\[j\]).
+    /// The tree must hold two-dimensional points.
+    pub fn interp_grid(&self, x1s: &[f64], x2s: &[f64]) -> Result<Matrix<f64>> {
+        if self.tree.pts().cols() != 2 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_grid(x1s, x2s, || (), |_, pt| self.interp(pt))
+    }
 }
 
 #[cfg(test)]
@@ -70,6 +165,60 @@ mod tests {
             assert!((res - 0.0).abs() < 1e-5, "{}", res);
             let res = shep.interp(&[1.0, 1.0 + 1e-7]).unwrap();
             assert!((res - 2.0).abs() < 1e-5, "{}", res);
-        })
+        });
+
+        let shep = Shep::new(&pts, &vals, 2.0);
+        assert_eq!(shep.interp_many(&pts).unwrap(), vals);
+        let grid = shep.interp_grid(&[0.0, 0.3, 1.0], &[0.2, 0.5]).unwrap();
+        assert!((grid[1][1] - shep.interp(&[0.3, 0.5]).unwrap()).abs() < 1e-14);
+        assert!((grid[2][0] - shep.interp(&[1.0, 0.2]).unwrap()).abs() < 1e-14);
+        let pts3 = Matrix::new(2, 3, vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let shep3 = Shep::new(&pts3, &vals[..2], 2.0);
+        assert!(shep3.interp_grid(&[0.5], &[0.5]).is_err());
+        // the dimension is checked even when there is nothing to evaluate
+        assert!(shep3.interp_grid(&[], &[0.5]).is_err());
+        assert!(shep.interp_many(&Matrix::new(0, 3, vec![])).is_err());
+    }
+
+    #[test]
+    fn test_local_shep() {
+        let n = 30;
+        let data: Vec<f64> = (0..(n * n))
+            .flat_map(|i| {
+                vec![
+                    (i / n) as f64 / (n - 1) as f64,
+                    (i % n) as f64 / (n - 1) as f64,
+                ]
+            })
+            .collect();
+        let pts = Matrix::new(n * n, 2, data);
+        let func = |p: &[f64]| (3.0 * p[0]).sin() + p[1] * p[1];
+        let vals: Vec<f64> = (0..pts.rows()).map(|i| func(&pts[i])).collect();
+        let shep = LocalShep::new(&pts, &vals, 12, 2.0).unwrap();
+        for i in [0, 17, 450, 899] {
+            assert_eq!(shep.interp(&pts[i]).unwrap(), vals[i]);
+        }
+        assert_eq!(shep.interp_many(&pts).unwrap(), vals);
+        let ticks = [0.05, 0.31, 0.47, 0.99];
+        let grid = shep.interp_grid(&ticks, &ticks).unwrap();
+        for (i, &a) in ticks.iter().enumerate() {
+            for (j, &b) in ticks.iter().enumerate() {
+                assert_eq!(grid[i][j], shep.interp(&[a, b]).unwrap());
+            }
+        }
+        for p in [[0.31, 0.47], [0.75, 0.05], [0.99, 0.99]] {
+            let res = shep.interp(&p).unwrap();
+            assert!((res - func(&p)).abs() < 2e-2, "p = {:?}, res = {}", p, res);
+        }
+        // constants are reproduced
+        let ones = vec![1.0; pts.rows()];
+        let shep = LocalShep::new(&pts, &ones, 5, 2.0).unwrap();
+        assert!((shep.interp(&[0.123, 0.456]).unwrap() - 1.0).abs() < 1e-14);
+        assert!(shep.interp(&[0.1]).is_err());
+        assert!(shep.interp_many(&Matrix::new(0, 1, vec![])).is_err());
+        let pts3 = Matrix::new(2, 3, vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let shep3 = LocalShep::new(&pts3, &ones[..2], 1, 2.0).unwrap();
+        assert!(shep3.interp_grid(&[], &[]).is_err());
+        assert!(LocalShep::new(&pts, &ones[1..], 5, 2.0).is_err());
     }
 }
@@ -0,0 +1,540 @@
+use anyhow::Result;
+
+use crate::{
+    interp::{
+        interp1d::window,
+        interp2d::{eval_rows, fill_grid},
+        linear::LinearInterp,
+        spline1d::Spline1D,
+        strictly_monotone, Cursor, Extrapolation, Interp, InterpError,
+    },
+    Matrix,
+};
+
+/// Interpolation scheme applied along every axis of a `GridInterp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GridMethod {
+    /// Multilinear over the 2^N corners of the cell.
+    #[default]
+    Linear,
+    /// Tensor product of the 4 point Lagrange polynomials around the cell.
+    Cubic,
+    /// Tensor product of natural cubic splines.
+    Spline,
+}
+
+/// Interpolation on a rectilinear grid of N axes. `values` are stored with the last axis
+/// varying fastest.
+#[derive(Debug, Clone)]
+pub struct GridInterp {
+    method: GridMethod,
+    axes: Vec<LinearInterp<f64>>,
+    work: Workspace,
+    strides: Vec<usize>,
+    /// For `Spline`, entry `mask` holds the values differentiated twice along each axis
+    /// whose bit is set in `mask`. Otherwise only the values themselves.
+    tables: Vec<Vec<f64>>,
+    extrapolation: Extrapolation<f64>,
+}
+
+/// Contribution of a node along one axis: (node, second derivative table, weight, d weight / dx).
+type Term = (usize, bool, f64, f64);
+
+/// Cursors and the per axis terms of the last point, reused from one point to the next.
+#[derive(Debug, Clone, Default)]
+struct Workspace {
+    cursors: Vec<Cursor>,
+    terms: Vec<[Term; 4]>,
+    lens: Vec<usize>,
+    idx: Vec<usize>,
+}
+
+impl Workspace {
+    fn new(axes: &[Vec<f64>]) -> Self {
+        let ndim = axes.len();
+        Self {
+            cursors: axes.iter().map(|ax| Cursor::new(ax.len())).collect(),
+            terms: vec![[(0, false, 0.0, 0.0); 4]; ndim],
+            lens: vec![0; ndim],
+            idx: vec![0; ndim],
+        }
+    }
+}
+
+impl GridInterp {
+    pub fn new(axes: &[Vec<f64>], values: &[f64], method: GridMethod) -> Result<Self> {
+        let ndim = axes.len();
+        if ndim == 0
+            || axes.iter().any(|ax| ax.len() < 2)
+            || axes.iter().map(|ax| ax.len()).product::<usize>() != values.len()
+        {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        if !axes.iter().all(|ax| strictly_monotone(ax)) {
+            return Err(InterpError::IdenticalX().into());
+        }
+        let mut strides = vec![1; ndim];
+        for d in (0..(ndim - 1)).rev() {
+            strides[d] = strides[d + 1] * axes[d + 1].len();
+        }
+        let mut tables = vec![values.to_vec()];
+        if method == GridMethod::Spline {
+            for mask in 1..(1usize << ndim) {
+                let d = mask.trailing_zeros() as usize;
+                let src = &tables[mask & !(1 << d)];
+                let table = second_derivatives(&axes[d], strides[d], src)?;
+                tables.push(table);
+            }
+        }
+        Ok(Self {
+            method,
+            axes: axes
+                .iter()
+                .map(|ax| LinearInterp::new(ax, ax))
+                .collect::<Result<_>>()?,
+            work: Workspace::new(axes),
+            strides,
+            tables,
+            extrapolation: Extrapolation::default(),
+        })
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.axes.len()
+    }
+
+    pub fn method(&self) -> GridMethod {
+        self.method
+    }
+
+    pub fn extrapolation(&self) -> Extrapolation<f64> {
+        self.extrapolation
+    }
+
+    /// `Natural` extends the border cells, `Linear` continues along each axis with the line
+    /// through the two outermost nodes.
+    pub fn set_extrapolation(&mut self, extrapolation: Extrapolation<f64>) {
+        self.extrapolation = extrapolation;
+    }
+
+    pub fn with_extrapolation(mut self, extrapolation: Extrapolation<f64>) -> Self {
+        self.set_extrapolation(extrapolation);
+        self
+    }
+
+    pub fn eval(&self, x: &[f64]) -> Result<f64> {
+        self.value_and_gradient(&mut self.work.clone(), x, None)
+    }
+
+    pub fn interp(&mut self, x: &[f64]) -> Result<f64> {
+        let mut work = std::mem::take(&mut self.work);
+        let res = self.value_and_gradient(&mut work, x, None);
+        self.work = work;
+        res
+    }
+
+    /// Value at each row of `pts`. Each point is bracketed by hunting from the previous one.
+    pub fn interp_many(&self, pts: &Matrix<f64>) -> Result<Vec<f64>> {
+        if pts.cols() != self.ndim() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        eval_rows(
+            pts,
+            || self.work.clone(),
+            |work, x| self.value_and_gradient(work, x, None),
+        )
+    }
+
+    /// Values at all the points of the rectilinear grid spanned by `axes`. Row k of the result
+    /// holds the points whose leading coordinates are the k-th combination of the leading axes
+    /// (the last of them varying fastest), and runs along the last axis; in two dimensions it is
+    /// the axes\[0\].len() x axes\[1\].len() matrix of values.
+    pub fn interp_grid(&self, axes: &[Vec<f64>]) -> Result<Matrix<f64>> {
+        let ndim = self.ndim();
+        if axes.len() != ndim {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let rows = axes[..(ndim - 1)].iter().map(|ax| ax.len()).product();
+        let last = &axes[ndim - 1];
+        fill_grid(rows, last.len(), |r, out| {
+            let mut x = vec![0.0; ndim];
+            let mut rest = r;
+            for d in (0..(ndim - 1)).rev() {
+                x[d] = axes[d][rest % axes[d].len()];
+                rest /= axes[d].len();
+            }
+            let mut work = self.work.clone();
+            for (y, &xl) in out.iter_mut().zip(last.iter()) {
+                x[ndim - 1] = xl;
+                *y = self.value_and_gradient(&mut work, &x, None)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Value and the partial derivatives along each axis.
+    pub fn gradient(&mut self, x: &[f64]) -> Result<(f64, Vec<f64>)> {
+        let mut work = std::mem::take(&mut self.work);
+        let mut grad = vec![0.0; self.ndim()];
+        let res = self.value_and_gradient(&mut work, x, Some(&mut grad));
+        self.work = work;
+        Ok((res?, grad))
+    }
+
+    /// Value at `x`, accumulating the partial derivatives into `grad` if given.
+    fn value_and_gradient(
+        &self,
+        work: &mut Workspace,
+        x: &[f64],
+        mut grad: Option<&mut [f64]>,
+    ) -> Result<f64> {
+        let ndim = self.ndim();
+        if x.len() != ndim {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let Workspace {
+            cursors,
+            terms,
+            lens,
+            idx,
+        } = work;
+        for d in 0..ndim {
+            match self.axis_terms(d, &mut cursors[d], x[d], &mut terms[d])? {
+                Some(len) => lens[d] = len,
+                None => {
+                    return match self.extrapolation {
+                        Extrapolation::Fill(val) => Ok(val),
+                        _ => unreachable!(),
+                    };
+                }
+            }
+        }
+
+        // odometer over the products of the per axis terms
+        let mut value = 0.0;
+        idx.iter_mut().for_each(|i| *i = 0);
+        loop {
+            let mut offset = 0;
+            let mut mask = 0;
+            let mut weight = 1.0;
+            for d in 0..ndim {
+                let (node, second, w, _) = terms[d][idx[d]];
+                offset += node * self.strides[d];
+                if second {
+                    mask |= 1 << d;
+                }
+                weight *= w;
+            }
+            let y = self.tables[mask][offset];
+            value += weight * y;
+            if let Some(grad) = grad.as_deref_mut() {
+                for (k, g) in grad.iter_mut().enumerate() {
+                    let w = (0..ndim).fold(1.0, |acc, d| {
+                        let term = terms[d][idx[d]];
+                        acc * if d == k { term.3 } else { term.2 }
+                    });
+                    *g += w * y;
+                }
+            }
+
+            let mut d = ndim;
+            loop {
+                if d == 0 {
+                    return Ok(value);
+                }
+                d -= 1;
+                idx[d] += 1;
+                if idx[d] < lens[d] {
+                    break;
+                }
+                idx[d] = 0;
+            }
+        }
+    }
+
+    /// Writes the nodes and weights along axis `d` to `out` and returns how many there are,
+    /// `None` when the point is filled by the policy.
+    fn axis_terms(
+        &self,
+        d: usize,
+        cursor: &mut Cursor,
+        x: f64,
+        out: &mut [Term; 4],
+    ) -> Result<Option<usize>> {
+        let axis = &self.axes[d];
+        let xx = axis.xx();
+        let n = xx.len();
+        let (first, last) = if xx[n - 1] >= xx[0] {
+            (0, n - 1)
+        } else {
+            (n - 1, 0)
+        };
+        let outer = if x < xx[first] {
+            Some(first)
+        } else if x > xx[last] {
+            Some(last)
+        } else {
+            None
+        };
+        let mut x = x;
+        if let Some(i) = outer {
+            match self.extrapolation {
+                Extrapolation::Natural => {}
+                Extrapolation::Error => return Err(InterpError::OutOfRange().into()),
+                Extrapolation::Fill(_) => return Ok(None),
+                Extrapolation::Clamp => x = xx[i],
+                Extrapolation::Linear => {
+                    let k = if i == 0 { 1 } else { n - 2 };
+                    let h = xx[k] - xx[i];
+                    let t = (x - xx[i]) / h;
+                    out[0] = (i, false, 1.0 - t, -1.0 / h);
+                    out[1] = (k, false, t, 1.0 / h);
+                    return Ok(Some(2));
+                }
+            }
+        }
+        let j = axis.hunt(cursor, x);
+        let h = xx[j + 1] - xx[j];
+        let len = match self.method {
+            GridMethod::Linear => {
+                let t = (x - xx[j]) / h;
+                out[0] = (j, false, 1.0 - t, -1.0 / h);
+                out[1] = (j + 1, false, t, 1.0 / h);
+                2
+            }
+            GridMethod::Cubic => {
+                let mm = std::cmp::min(4, n);
+                let lo = window(n, mm, j);
+                let xa = &xx[lo..(lo + mm)];
+                for (k, term) in out.iter_mut().take(mm).enumerate() {
+                    let (w, dw) = lagrange(xa, k, x);
+                    *term = (lo + k, false, w, dw);
+                }
+                mm
+            }
+            GridMethod::Spline => {
+                let a = (xx[j + 1] - x) / h;
+                let b = 1.0 - a;
+                let h6 = h * h / 6.0;
+                out[0] = (j, false, a, -1.0 / h);
+                out[1] = (j + 1, false, b, 1.0 / h);
+                out[2] = (
+                    j,
+                    true,
+                    (a * a * a - a) * h6,
+                    -(3.0 * a * a - 1.0) * h / 6.0,
+                );
+                out[3] = (
+                    j + 1,
+                    true,
+                    (b * b * b - b) * h6,
+                    (3.0 * b * b - 1.0) * h / 6.0,
+                );
+                4
+            }
+        };
+        Ok(Some(len))
+    }
+}
+
+/// Second derivatives of the natural splines along the axis with the given stride
+/// through every line of `src`.
+fn second_derivatives(axis: &[f64], stride: usize, src: &[f64]) -> Result<Vec<f64>> {
+    let n = axis.len();
+    let mut dst = vec![0.0; src.len()];
+    let block = stride * n;
+    for base in (0..src.len()).step_by(block) {
+        for off in 0..stride {
+            let start = base + off;
+            let line: Vec<f64> = (0..n).map(|k| src[start + k * stride]).collect();
+            let spl = Spline1D::new(axis, &line)?;
+            for (k, &y2) in spl.y2().iter().enumerate() {
+                dst[start + k * stride] = y2;
+            }
+        }
+    }
+    Ok(dst)
+}
+
+/// The k-th Lagrange basis polynomial through `xa` and its derivative at `x`.
+fn lagrange(xa: &[f64], k: usize, x: f64) -> (f64, f64) {
+    let mm = xa.len();
+    let denom: f64 = (0..mm).filter(|&m| m != k).map(|m| xa[k] - xa[m]).product();
+    let w: f64 = (0..mm).filter(|&m| m != k).map(|m| x - xa[m]).product();
+    let dw: f64 = (0..mm)
+        .filter(|&m| m != k)
+        .map(|m| {
+            (0..mm)
+                .filter(|&l| l != k && l != m)
+                .map(|l| x - xa[l])
+                .product::<f64>()
+        })
+        .sum();
+    (w / denom, dw / denom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interp::interp2d::bicubic_spline::Spline2D;
+    use crate::Matrix;
+
+    fn table(axes: &[Vec<f64>], func: impl Fn(&[f64]) -> f64) -> Vec<f64> {
+        let mut values = vec![];
+        let mut idx = vec![0; axes.len()];
+        loop {
+            let x: Vec<f64> = idx.iter().enumerate().map(|(d, &i)| axes[d][i]).collect();
+            values.push(func(&x));
+            let mut d = axes.len();
+            loop {
+                if d == 0 {
+                    return values;
+                }
+                d -= 1;
+                idx[d] += 1;
+                if idx[d] < axes[d].len() {
+                    break;
+                }
+                idx[d] = 0;
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid_linear() {
+        let axes = vec![
+            vec![0.0, 1.0, 3.0],
+            vec![-1.0, 0.0, 2.0, 4.0],
+            vec![10.0, 20.0],
+        ];
+        // multilinear functions are reproduced
+        let func = |x: &[f64]| 1.0 + x[0] * x[1] - 0.5 * x[2] + x[0] * x[1] * x[2] * 0.01;
+        let values = table(&axes, func);
+        let mut grid = GridInterp::new(&axes, &values, GridMethod::Linear).unwrap();
+        for x in [[0.5, 0.5, 15.0], [2.9, 3.5, 11.0], [1.0, -1.0, 20.0]] {
+            let res = grid.interp(&x).unwrap();
+            assert!((res - func(&x)).abs() < 1e-12, "x = {:?}, res = {}", x, res);
+        }
+        let (_, g) = grid.gradient(&[0.5, 0.5, 15.0]).unwrap();
+        let expected = [
+            0.5 + 0.5 * 15.0 * 0.01,
+            0.5 + 0.5 * 15.0 * 0.01,
+            -0.5 + 0.25 * 0.01,
+        ];
+        for (v, e) in g.iter().zip(expected.iter()) {
+            assert!((v - e).abs() < 1e-12, "g = {:?}", g);
+        }
+
+        assert!(grid.interp(&[0.5, 0.5]).is_err());
+        let mut grid = grid.with_extrapolation(Extrapolation::Clamp);
+        let res = grid.interp(&[-1.0, 0.5, 25.0]).unwrap();
+        assert!((res - func(&[0.0, 0.5, 20.0])).abs() < 1e-12);
+        grid.set_extrapolation(Extrapolation::Fill(-7.0));
+        assert_eq!(grid.interp(&[0.5, 5.0, 15.0]).unwrap(), -7.0);
+        grid.set_extrapolation(Extrapolation::Error);
+        assert!(grid.interp(&[0.5, 5.0, 15.0]).is_err());
+        grid.set_extrapolation(Extrapolation::Linear);
+        let res = grid.interp(&[0.5, 5.0, 15.0]).unwrap();
+        assert!((res - func(&[0.5, 5.0, 15.0])).abs() < 1e-12);
+
+        let ticks = vec![vec![0.2, 2.5], vec![-0.5, 1.0, 3.9], vec![12.0, 19.0]];
+        let values = grid.interp_grid(&ticks).unwrap();
+        assert_eq!((values.rows(), values.cols()), (6, 2));
+        let pts = Matrix::new(
+            12,
+            3,
+            ticks[0]
+                .iter()
+                .flat_map(|&a| ticks[1].iter().map(move |&b| (a, b)))
+                .flat_map(|(a, b)| ticks[2].iter().flat_map(move |&c| [a, b, c]))
+                .collect(),
+        );
+        let many = grid.interp_many(&pts).unwrap();
+        for (k, v) in many.iter().enumerate() {
+            assert!((v - func(&pts[k])).abs() < 1e-12);
+            assert_eq!(*v, values[k / 2][k % 2]);
+        }
+        assert!(grid.interp_grid(&ticks[..2]).is_err());
+        assert!(grid
+            .interp_many(&Matrix::new(1, 2, vec![0.5, 0.5]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_grid_cubic() {
+        let axes = vec![
+            (0..6).map(|i| i as f64 * 0.5).collect::<Vec<f64>>(),
+            (0..5).map(|i| (i * i) as f64 * 0.25).collect(),
+            (0..7).map(|i| i as f64).collect(),
+        ];
+        // cubic in each variable
+        let func = |x: &[f64]| x[0].powi(3) * x[1] - x[1].powi(2) * x[2].powi(3) + x[2];
+        let values = table(&axes, func);
+        let mut grid = GridInterp::new(&axes, &values, GridMethod::Cubic).unwrap();
+        let x = [1.3, 2.7, 4.4];
+        let (res, g) = grid.gradient(&x).unwrap();
+        assert!((res - func(&x)).abs() < 1e-9, "res = {}", res);
+        let expected = [
+            3.0 * x[0].powi(2) * x[1],
+            x[0].powi(3) - 2.0 * x[1] * x[2].powi(3),
+            -3.0 * x[1].powi(2) * x[2].powi(2) + 1.0,
+        ];
+        for (v, e) in g.iter().zip(expected.iter()) {
+            assert!((v - e).abs() < 1e-8, "g = {:?}", g);
+        }
+        // natural extrapolation extends the border polynomials
+        let x = [3.2, -0.3, 6.5];
+        assert!((grid.eval(&x).unwrap() - func(&x)).abs() < 1e-8);
+
+        // repeated and unsorted nodes
+        let values = vec![0.0; 4 * 2];
+        for ax in [vec![0.0, 1.0, 1.0, 2.0], vec![0.0, 2.0, 1.0, 3.0]] {
+            let res = GridInterp::new(&[ax, vec![0.0, 1.0]], &values, GridMethod::Cubic);
+            assert!(matches!(
+                res.unwrap_err().downcast_ref::<InterpError>(),
+                Some(InterpError::IdenticalX())
+            ));
+        }
+        // decreasing axes are fine
+        let axes = vec![vec![3.0, 2.0, 1.0, 0.0], vec![0.0, 1.0]];
+        let func = |x: &[f64]| x[0].powi(3) - 2.0 * x[1];
+        let values = table(&axes, func);
+        for method in [GridMethod::Linear, GridMethod::Cubic, GridMethod::Spline] {
+            let grid = GridInterp::new(&axes, &values, method).unwrap();
+            let res = grid.eval(&[2.0, 0.3]).unwrap();
+            assert!((res - func(&[2.0, 0.3])).abs() < 1e-12, "res = {}", res);
+        }
+        let grid = GridInterp::new(&axes, &values, GridMethod::Cubic).unwrap();
+        let res = grid.eval(&[1.5, 0.3]).unwrap();
+        assert!((res - func(&[1.5, 0.3])).abs() < 1e-12, "res = {}", res);
+    }
+
+    #[test]
+    fn test_grid_spline() {
+        // agrees with Spline2D in two dimensions
+        let x1 = vec![0.0, 1.0, 2.0, 4.0, 6.0, 10.0];
+        let x2 = vec![0.0, 2.0, 3.0, 5.0, 7.0, 10.0];
+        let axes = vec![x1.clone(), x2.clone()];
+        let func = |x: &[f64]| (0.3 * x[0]).sin() * (x[1] * x[1] + 1.0).sqrt();
+        let values = table(&axes, func);
+        let mut grid = GridInterp::new(&axes, &values, GridMethod::Spline).unwrap();
+        let mut sp2d = Spline2D::new(&x1, &x2, &Matrix::new(6, 6, values.clone())).unwrap();
+        for &(a, b) in &[(0.5, 0.5), (3.3, 6.1), (9.9, 2.5), (4.0, 3.0), (11.0, -1.0)] {
+            let (res, g) = grid.gradient(&[a, b]).unwrap();
+            let (expected, eg) = sp2d.gradient(a, b).unwrap();
+            assert!((res - expected).abs() < 1e-12, "res = {}", res);
+            assert!((g[0] - eg[0]).abs() < 1e-10 && (g[1] - eg[1]).abs() < 1e-10);
+        }
+
+        // 4 dimensions, smooth function
+        let axes: Vec<Vec<f64>> = (0..4)
+            .map(|_| (0..9).map(|i| i as f64 * 0.125).collect())
+            .collect();
+        let func = |x: &[f64]| (x[0] + 0.5 * x[1]).sin() * (x[2] - x[3] * x[3]).exp();
+        let values = table(&axes, func);
+        let grid = GridInterp::new(&axes, &values, GridMethod::Spline).unwrap();
+        let x = [0.41, 0.77, 0.23, 0.58];
+        assert!((grid.eval(&x).unwrap() - func(&x)).abs() < 1e-4);
+        assert!(GridInterp::new(&axes, &values[1..], GridMethod::Spline).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Result;
+
+use crate::{interp::InterpError, Matrix};
+
+/// Maximum number of points in a leaf.
+const BUCKET: usize = 8;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf {
+        start: usize,
+        end: usize,
+    },
+    Split {
+        dim: usize,
+        val: f64,
+        left: usize,
+        right: usize,
+    },
+}
+
+/// k-d tree over the rows of a point matrix \[n_point, n_dimension\].
+#[derive(Debug, Clone)]
+pub struct KdTree<'a> {
+    pts: &'a Matrix<f64>,
+    /// point indices, each leaf owns a contiguous range
+    idx: Vec<usize>,
+    nodes: Vec<Node>,
+}
+
+impl<'a> KdTree<'a> {
+    /// Fails with `NotFinite` if a coordinate is NaN or infinite.
+    pub fn new(pts: &'a Matrix<f64>) -> Result<Self> {
+        if (0..pts.rows()).any(|i| pts[i].iter().any(|v| !v.is_finite())) {
+            return Err(InterpError::NotFinite().into());
+        }
+        let mut tree = Self {
+            pts,
+            idx: (0..pts.rows()).collect(),
+            nodes: vec![],
+        };
+        if pts.rows() > 0 {
+            tree.build(0, pts.rows());
+        }
+        Ok(tree)
+    }
+
+    pub fn pts(&self) -> &Matrix<f64> {
+        self.pts
+    }
+
+    pub fn len(&self) -> usize {
+        self.idx.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.idx.is_empty()
+    }
+
+    /// Splits idx\[start..end\] at the median along the dimension of the largest spread.
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let id = self.nodes.len();
+        if end - start <= BUCKET {
+            self.nodes.push(Node::Leaf { start, end });
+            return id;
+        }
+        let pts = self.pts;
+        let dim = (0..pts.cols())
+            .map(|d| {
+                let (lo, hi) = self.idx[start..end]
+                    .iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &i| {
+                        (lo.min(pts[i][d]), hi.max(pts[i][d]))
+                    });
+                (d, hi - lo)
+            })
+            .fold(
+                (0, -1.0),
+                |best, cur| if cur.1 > best.1 { cur } else { best },
+            )
+            .0;
+        let mid = (start + end) / 2;
+        self.idx[start..end]
+            .select_nth_unstable_by(mid - start, |&a, &b| pts[a][dim].total_cmp(&pts[b][dim]));
+        let val = pts[self.idx[mid]][dim];
+        // placeholder, the children are pushed after this node
+        self.nodes.push(Node::Leaf { start, end });
+        let left = self.build(start, mid);
+        let right = self.build(mid, end);
+        self.nodes[id] = Node::Split {
+            dim,
+            val,
+            left,
+            right,
+        };
+        id
+    }
+
+    /// Nearest point as (index, distance).
+    pub fn nearest(&self, pt: &[f64]) -> Option<(usize, f64)> {
+        self.knn(pt, 1).into_iter().next()
+    }
+
+    /// The `k` nearest points as (index, distance), closest first.
+    pub fn knn(&self, pt: &[f64], k: usize) -> Vec<(usize, f64)> {
+        let mut found = Vec::with_capacity(k + 1);
+        if k > 0 && !self.is_empty() {
+            self.walk(0, pt, k, f64::INFINITY, &mut found);
+        }
+        found.into_iter().map(|(i, d2)| (i, d2.sqrt())).collect()
+    }
+
+    /// All the points within distance `r` as (index, distance), closest first.
+    pub fn within(&self, pt: &[f64], r: f64) -> Vec<(usize, f64)> {
+        let mut found = vec![];
+        if !self.is_empty() {
+            self.walk(0, pt, usize::MAX, r * r, &mut found);
+        }
+        found.sort_by(|a, b| a.1.total_cmp(&b.1));
+        found.into_iter().map(|(i, d2)| (i, d2.sqrt())).collect()
+    }
+
+    /// Collects the points within squared distance `r2`, keeping only the `k` closest in
+    /// ascending order when `k` is finite.
+    fn walk(&self, node: usize, pt: &[f64], k: usize, r2: f64, found: &mut Vec<(usize, f64)>) {
+        match self.nodes[node] {
+            Node::Leaf { start, end } => {
+                for &i in &self.idx[start..end] {
+                    let d2: f64 = self.pts[i]
+                        .iter()
+                        .zip(pt.iter())
+                        .map(|(a, b)| (a - b) * (a - b))
+                        .sum();
+                    // also skips every point for a NaN query
+                    if d2.is_nan() || d2 > bound(found, k, r2) {
+                        continue;
+                    }
+                    if k == usize::MAX {
+                        found.push((i, d2));
+                    } else {
+                        let pos = found.partition_point(|&(_, d)| d <= d2);
+                        found.insert(pos, (i, d2));
+                        found.truncate(k);
+                    }
+                }
+            }
+            Node::Split {
+                dim,
+                val,
+                left,
+                right,
+            } => {
+                let diff = pt[dim] - val;
+                let (near, far) = if diff < 0.0 {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+                self.walk(near, pt, k, r2, found);
+                if diff * diff <= bound(found, k, r2) {
+                    self.walk(far, pt, k, r2, found);
+                }
+            }
+        }
+    }
+}
+
+/// Squared distance beyond which points are not needed any more.
+fn bound(found: &[(usize, f64)], k: usize, r2: f64) -> f64 {
+    if k != usize::MAX && found.len() == k {
+        found[k - 1].1.min(r2)
+    } else {
+        r2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interp::testing::random_points;
+
+    #[test]
+    fn test_kdtree() {
+        let pts = random_points(500, 3, 42);
+        let tree = KdTree::new(&pts).unwrap();
+        assert_eq!(tree.len(), 500);
+        let queries = random_points(20, 3, 7);
+        for q in 0..queries.rows() {
+            let pt = &queries[q];
+            let mut brute: Vec<(usize, f64)> = (0..pts.rows())
+                .map(|i| {
+                    let d2: f64 = pts[i]
+                        .iter()
+                        .zip(pt.iter())
+                        .map(|(a, b)| (a - b) * (a - b))
+                        .sum();
+                    (i, d2.sqrt())
+                })
+                .collect();
+            brute.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+            let knn = tree.knn(pt, 10);
+            assert_eq!(knn.len(), 10);
+            for (a, b) in knn.iter().zip(brute.iter()) {
+                assert_eq!(a.0, b.0);
+                assert!((a.1 - b.1).abs() < 1e-15);
+            }
+            assert_eq!(tree.nearest(pt).unwrap().0, brute[0].0);
+
+            let within = tree.within(pt, 0.2);
+            let expected: Vec<usize> = brute.iter().filter(|b| b.1 <= 0.2).map(|b| b.0).collect();
+            let got: Vec<usize> = within.iter().map(|w| w.0).collect();
+            assert_eq!(got, expected);
+        }
+
+        let empty = Matrix::new(0, 2, vec![]);
+        assert!(KdTree::new(&empty).unwrap().nearest(&[0.0, 0.0]).is_none());
+        let few = random_points(3, 2, 1);
+        assert_eq!(KdTree::new(&few).unwrap().knn(&[0.5, 0.5], 5).len(), 3);
+
+        // non-finite points are rejected, non-finite queries find nothing
+        let mut bad = random_points(20, 2, 3);
+        bad[4][1] = f64::NAN;
+        assert!(KdTree::new(&bad).is_err());
+        assert!(tree.within(&[f64::NAN, 0.0, 0.5], 0.3).is_empty());
+        assert!(tree.knn(&[0.5, f64::NAN, 0.5], 3).is_empty());
+        assert_eq!(tree.within(&[0.5, 0.5, 0.5], f64::INFINITY).len(), 500);
+    }
+}
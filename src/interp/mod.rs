@@ -1,10 +1,25 @@
+use std::{cmp::Ordering, fmt::Debug};
+
+use num::{Float, FromPrimitive};
 use thiserror::Error;
 
+pub mod chebyshev;
+pub mod curve;
 pub mod interp1d;
 pub mod interp2d;
+pub mod interpnd;
+pub mod kdtree;
+pub mod rational;
+#[cfg(test)]
+pub(crate) mod testing;
 
+pub use chebyshev::*;
+pub use curve::*;
 pub use interp1d::*;
 pub use interp2d::*;
+pub use interpnd::*;
+pub use kdtree::*;
+pub use rational::*;
 
 #[derive(Error, Debug)]
 pub enum InterpError {
@@ -16,4 +31,60 @@ pub enum InterpError {
 
     #[error("SizeNotMatch")]
     SizeNotMatch(),
+
+    #[error("OutOfRange")]
+    OutOfRange(),
+
+    #[error("NotPeriodic")]
+    NotPeriodic(),
+
+    #[error("Pole")]
+    Pole(),
+
+    #[error("NotConverged")]
+    NotConverged(),
+
+    #[error("Collinear")]
+    Collinear(),
+
+    #[error("NotFinite")]
+    NotFinite(),
+}
+
+/// What to return for a point outside the range of the data.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Extrapolation<T> {
+    /// Fail with `InterpError::OutOfRange`.
+    Error,
+    /// Value at the nearest end of the data.
+    Clamp,
+    /// Constant value.
+    Fill(T),
+    /// Straight line through the two outermost points.
+    Linear,
+    /// Evaluate the interpolant of the nearest interval as is.
+    #[default]
+    Natural,
+}
+
+/// Floating point types the interpolators are generic over.
+pub trait InterpFloat: Float + FromPrimitive + Debug + Send + Sync + 'static {
+    /// Convert a literal constant.
+    fn lit(val: f64) -> Self {
+        Self::from_f64(val).unwrap()
+    }
+
+    /// Total order for sorting, with NaN after every number.
+    fn total_order(&self, other: &Self) -> Ordering {
+        self.partial_cmp(other)
+            .unwrap_or_else(|| self.is_nan().cmp(&other.is_nan()))
+    }
+}
+
+impl InterpFloat for f32 {}
+impl InterpFloat for f64 {}
+
+/// Strictly increasing or strictly decreasing, as required of the nodes along an axis.
+pub(crate) fn strictly_monotone<T: PartialOrd>(xx: &[T]) -> bool {
+    xx.windows(2).all(|w| w[0] < w[1]) || xx.windows(2).all(|w| w[0] > w[1])
 }
@@ -0,0 +1,681 @@
+use anyhow::Result;
+use num::{Complex, Float};
+
+use crate::{linalg::lu_decomposition::LUdcmp, MatLinAlgBound, Matrix};
+
+use super::{InterpError, InterpFloat};
+
+#[derive(Debug, Clone)]
+enum Form<T> {
+    /// P(t) / Q(t) with t = (x - shift) / scale.
+    Monomial {
+        num: Vec<T>,
+        den: Vec<T>,
+        shift: T,
+        scale: T,
+    },
+    /// sum_j w_j f_j / (x - z_j) / sum_j w_j / (x - z_j).
+    Barycentric {
+        support: Vec<T>,
+        values: Vec<T>,
+        weights: Vec<T>,
+    },
+}
+
+/// Global rational approximation built by `pade`, `minimax` or `aaa`.
+#[derive(Debug, Clone)]
+pub struct RationalFunction<T>
+where
+    T: InterpFloat,
+{
+    form: Form<T>,
+}
+
+impl<T> RationalFunction<T>
+where
+    T: InterpFloat,
+{
+    /// sum_k num\[k\] x^k / sum_k den\[k\] x^k.
+    pub fn new(num: Vec<T>, den: Vec<T>) -> Result<Self> {
+        if num.is_empty() || den.is_empty() {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        Ok(Self {
+            form: Form::Monomial {
+                num,
+                den,
+                shift: T::zero(),
+                scale: T::one(),
+            },
+        })
+    }
+
+    /// AAA algorithm (Nakatsukasa, Sete and Trefethen): greedily picks support points among
+    /// the samples (xx\[i\], yy\[i\]) until the barycentric interpolant matches all of them
+    /// within `tol` relative to max |yy|, or `mmax` support points are used.
+    pub fn aaa(xx: &[T], yy: &[T], tol: T, mmax: usize) -> Result<Self> {
+        let n = xx.len();
+        if n == 0 || yy.len() != n || mmax == 0 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let mut sorted = xx.to_vec();
+        sorted.sort_by(T::total_order);
+        if sorted.windows(2).any(|w| w[0] == w[1]) {
+            return Err(InterpError::IdenticalX().into());
+        }
+        let fmax = yy.iter().fold(T::zero(), |acc, &v| acc.max(v.abs()));
+        let mean = yy.iter().fold(T::zero(), |acc, &v| acc + v) / T::from_usize(n).unwrap();
+        let mut approx = vec![mean; n];
+        let mut rest: Vec<usize> = (0..n).collect();
+        let mut support = vec![];
+        let mut values = vec![];
+        let mut weights = vec![];
+        for _ in 0..std::cmp::min(mmax, n) {
+            let (pos, _) = rest
+                .iter()
+                .enumerate()
+                .fold((0, -T::one()), |acc, (pos, &i)| {
+                    let err = (yy[i] - approx[i]).abs();
+                    if err > acc.1 {
+                        (pos, err)
+                    } else {
+                        acc
+                    }
+                });
+            let j = rest.remove(pos);
+            support.push(xx[j]);
+            values.push(yy[j]);
+            approx[j] = yy[j];
+
+            // Loewner matrix over the remaining samples
+            let m = support.len();
+            let mut data = Vec::with_capacity(rest.len() * m);
+            for &i in &rest {
+                for k in 0..m {
+                    data.push((yy[i] - values[k]) / (xx[i] - support[k]));
+                }
+            }
+            weights = min_singular_vector(Matrix::new(rest.len(), m, data));
+
+            let mut err = T::zero();
+            for &i in &rest {
+                let (nn, dd) = (0..m).fold((T::zero(), T::zero()), |(nn, dd), k| {
+                    let c = weights[k] / (xx[i] - support[k]);
+                    (nn + c * values[k], dd + c)
+                });
+                approx[i] = nn / dd;
+                err = err.max((yy[i] - approx[i]).abs());
+            }
+            if err <= tol * fmax {
+                break;
+            }
+        }
+        Ok(Self {
+            form: Form::Barycentric {
+                support,
+                values,
+                weights,
+            },
+        })
+    }
+
+    /// Degrees of the numerator and the denominator.
+    pub fn degree(&self) -> (usize, usize) {
+        match &self.form {
+            Form::Monomial { num, den, .. } => (num.len() - 1, den.len() - 1),
+            Form::Barycentric { support, .. } => (support.len() - 1, support.len() - 1),
+        }
+    }
+
+    /// Support points of the barycentric form, empty for the other constructors.
+    pub fn support(&self) -> &[T] {
+        match &self.form {
+            Form::Monomial { .. } => &[],
+            Form::Barycentric { support, .. } => support,
+        }
+    }
+
+    pub fn eval(&self, x: T) -> T {
+        match &self.form {
+            Form::Monomial {
+                num,
+                den,
+                shift,
+                scale,
+            } => {
+                let t = (x - *shift) / *scale;
+                horner(num, t) / horner(den, t)
+            }
+            Form::Barycentric {
+                support,
+                values,
+                weights,
+            } => {
+                if let Some(k) = support.iter().position(|&z| z == x) {
+                    return values[k];
+                }
+                let (nn, dd) = support.iter().zip(values.iter()).zip(weights.iter()).fold(
+                    (T::zero(), T::zero()),
+                    |(nn, dd), ((&z, &f), &w)| {
+                        let c = w / (x - z);
+                        (nn + c * f, dd + c)
+                    },
+                );
+                nn / dd
+            }
+        }
+    }
+
+    /// Roots of the denominator.
+    pub fn poles(&self) -> Result<Vec<Complex<T>>> {
+        match &self.form {
+            Form::Monomial {
+                den, shift, scale, ..
+            } => Ok(roots(den)?
+                .into_iter()
+                .map(|t| t * *scale + *shift)
+                .collect()),
+            Form::Barycentric {
+                support, weights, ..
+            } => bary_roots(support, weights),
+        }
+    }
+
+    /// Roots of the numerator.
+    pub fn zeros(&self) -> Result<Vec<Complex<T>>> {
+        match &self.form {
+            Form::Monomial {
+                num, shift, scale, ..
+            } => Ok(roots(num)?
+                .into_iter()
+                .map(|t| t * *scale + *shift)
+                .collect()),
+            Form::Barycentric {
+                support,
+                values,
+                weights,
+            } => {
+                let wf: Vec<T> = weights
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(&w, &f)| w * f)
+                    .collect();
+                bary_roots(support, &wf)
+            }
+        }
+    }
+}
+
+impl<T> RationalFunction<T>
+where
+    T: InterpFloat + MatLinAlgBound,
+{
+    /// Pade approximant of numerator degree `l` and denominator degree `m` from the Taylor
+    /// coefficients `c` about 0, which needs c.len() >= l + m + 1.
+    pub fn pade(c: &[T], l: usize, m: usize) -> Result<Self> {
+        if c.len() < l + m + 1 {
+            return Err(InterpError::SizeNotMatch().into());
+        }
+        let coef = |k: isize| if k < 0 { T::zero() } else { c[k as usize] };
+        let mut q = vec![T::one(); m + 1];
+        if m > 0 {
+            let mut data = Vec::with_capacity(m * m);
+            let mut rhs = Vec::with_capacity(m);
+            for j in 1..=m {
+                for k in 1..=m {
+                    data.push(coef((l + j) as isize - k as isize));
+                }
+                rhs.push(-c[l + j]);
+            }
+            let a = Matrix::new(m, m, data);
+            LUdcmp::new(&a)?.solve(&rhs, &mut q[1..])?;
+        }
+        let p = (0..=l)
+            .map(|k| (0..=std::cmp::min(k, m)).fold(T::zero(), |acc, i| acc + q[i] * c[k - i]))
+            .collect();
+        Self::new(p, q)
+    }
+
+    /// Minimax rational approximation of `func` on \[a, b\] with numerator degree `l` and
+    /// denominator degree `m` by Remez exchange. Returns the approximation and its maximum
+    /// error, sampled on a grid of Chebyshev points over \[a, b\]. Fails with
+    /// `InterpError::NotConverged` if the error does not level out within 100 exchanges.
+    pub fn minimax<F>(func: F, a: T, b: T, l: usize, m: usize) -> Result<(Self, T)>
+    where
+        F: Fn(T) -> T,
+    {
+        let npts = l + m + 2;
+        let shift = T::lit(0.5) * (a + b);
+        let scale = T::lit(0.5) * (b - a);
+        let f = |t: T| func(t * scale + shift);
+        let pi = T::lit(std::f64::consts::PI);
+        let chebpts = |n: usize| -> Vec<T> {
+            (0..n)
+                .map(|i| -(pi * T::from_usize(i).unwrap() / T::from_usize(n - 1).unwrap()).cos())
+                .collect()
+        };
+        let grid = chebpts(std::cmp::max(50 * npts, 500));
+        let fgrid: Vec<T> = grid.iter().map(|&t| f(t)).collect();
+        let mut refs = chebpts(npts);
+
+        let mut num = vec![T::zero(); l + 1];
+        let mut den = vec![T::one(); m + 1];
+        let fmax = fgrid
+            .iter()
+            .fold(T::zero(), |acc, &v| acc.max(Float::abs(v)));
+        let mut emax = T::infinity();
+        let mut converged = false;
+        for _ in 0..100 {
+            let fref: Vec<T> = refs.iter().map(|&t| f(t)).collect();
+            let mut e = T::zero();
+            let mut x = vec![T::zero(); l + m + 2];
+            let mut levelled = false;
+            for _ in 0..100 {
+                let mut data = Vec::with_capacity(npts * npts);
+                let mut sign = T::one();
+                for (&t, &ft) in refs.iter().zip(fref.iter()) {
+                    let mut pw = T::one();
+                    for _ in 0..=l {
+                        data.push(pw);
+                        pw *= t;
+                    }
+                    let mut pw = t;
+                    for _ in 0..m {
+                        data.push(-(ft + sign * e) * pw);
+                        pw *= t;
+                    }
+                    data.push(-sign);
+                    sign = -sign;
+                }
+                let mat = Matrix::new(npts, npts, data);
+                LUdcmp::new(&mat)?.solve(&fref, &mut x)?;
+                let enew = x[l + m + 1];
+                // to rounding, far below the levelling tolerance of the exchange
+                let done = Float::abs(enew - e)
+                    <= T::lit(1e-10) * Float::abs(enew) + T::lit(100.0) * T::epsilon() * fmax;
+                e = enew;
+                if done {
+                    levelled = true;
+                    break;
+                }
+            }
+            if !levelled {
+                return Err(InterpError::NotConverged().into());
+            }
+            num.copy_from_slice(&x[..=l]);
+            den[1..].copy_from_slice(&x[(l + 1)..(l + m + 1)]);
+
+            let qgrid: Vec<T> = grid.iter().map(|&t| horner(&den, t)).collect();
+            if qgrid.iter().any(|&q| q <= T::zero()) {
+                return Err(InterpError::Pole().into());
+            }
+            let err: Vec<T> = grid
+                .iter()
+                .zip(qgrid.iter())
+                .zip(fgrid.iter())
+                .map(|((&t, &q), &ft)| horner(&num, t) / q - ft)
+                .collect();
+            emax = err.iter().fold(T::zero(), |acc, &v| acc.max(Float::abs(v)));
+            // equioscillation, or exact up to rounding
+            if emax - Float::abs(e) <= T::lit(1e-6) * emax
+                || emax <= T::lit(100.0) * T::epsilon() * fmax
+            {
+                converged = true;
+                break;
+            }
+            refs = exchange(&grid, &err, npts).ok_or(InterpError::NotConverged())?;
+        }
+        if !converged {
+            return Err(InterpError::NotConverged().into());
+        }
+        Ok((
+            Self {
+                form: Form::Monomial {
+                    num,
+                    den,
+                    shift,
+                    scale,
+                },
+            },
+            emax,
+        ))
+    }
+}
+
+/// New reference of `npts` alternating extrema of the error sampled on the grid.
+fn exchange<T>(grid: &[T], err: &[T], npts: usize) -> Option<Vec<T>>
+where
+    T: InterpFloat,
+{
+    // largest |err| in each run of constant sign
+    let mut ext: Vec<usize> = vec![];
+    for (i, &e) in err.iter().enumerate() {
+        match ext.last_mut() {
+            Some(last) if (err[*last] >= T::zero()) == (e >= T::zero()) => {
+                if e.abs() > err[*last].abs() {
+                    *last = i;
+                }
+            }
+            _ => ext.push(i),
+        }
+    }
+    if ext.len() < npts {
+        return None;
+    }
+    while ext.len() > npts {
+        if err[ext[0]].abs() < err[ext[ext.len() - 1]].abs() {
+            ext.remove(0);
+        } else {
+            ext.pop();
+        }
+    }
+    Some(ext.into_iter().map(|i| grid[i]).collect())
+}
+
+fn horner<T>(c: &[T], t: T) -> T
+where
+    T: InterpFloat,
+{
+    c.iter().rev().fold(T::zero(), |acc, &ck| acc * t + ck)
+}
+
+/// Right singular vector for the smallest singular value by one-sided Jacobi rotations.
+fn min_singular_vector<T>(mut a: Matrix<T>) -> Vec<T>
+where
+    T: InterpFloat,
+{
+    let (rows, n) = (a.rows(), a.cols());
+    let mut v = Matrix::new(n, n, vec![T::zero(); n * n]);
+    for i in 0..n {
+        v[i][i] = T::one();
+    }
+    for _ in 0..60 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let (mut alpha, mut beta, mut gamma) = (T::zero(), T::zero(), T::zero());
+                for i in 0..rows {
+                    alpha = alpha + a[i][p] * a[i][p];
+                    beta = beta + a[i][q] * a[i][q];
+                    gamma = gamma + a[i][p] * a[i][q];
+                }
+                if gamma.abs() <= T::epsilon() * (alpha * beta).sqrt() {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (T::lit(2.0) * gamma);
+                let t = zeta.signum() / (zeta.abs() + (T::one() + zeta * zeta).sqrt());
+                let c = T::one() / (T::one() + t * t).sqrt();
+                let s = c * t;
+                for i in 0..rows {
+                    let (ap, aq) = (a[i][p], a[i][q]);
+                    a[i][p] = c * ap - s * aq;
+                    a[i][q] = s * ap + c * aq;
+                }
+                for i in 0..n {
+                    let (vp, vq) = (v[i][p], v[i][q]);
+                    v[i][p] = c * vp - s * vq;
+                    v[i][q] = s * vp + c * vq;
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+    let norms: Vec<T> = (0..n)
+        .map(|j| (0..rows).fold(T::zero(), |acc, i| acc + a[i][j] * a[i][j]))
+        .collect();
+    let jmin = (0..n).fold(0, |jmin, j| if norms[j] < norms[jmin] { j } else { jmin });
+    (0..n).map(|i| v[i][jmin]).collect()
+}
+
+/// Roots of sum_j w_j / (z - z_j), polished by Newton iteration on the barycentric sum.
+fn bary_roots<T>(support: &[T], w: &[T]) -> Result<Vec<Complex<T>>>
+where
+    T: InterpFloat,
+{
+    // sum_j w_j prod_{k != j} (z - z_k)
+    let m = support.len();
+    let mut c = vec![T::zero(); m];
+    for (j, &wj) in w.iter().enumerate() {
+        let mut prod = vec![T::one()];
+        for (k, &zk) in support.iter().enumerate() {
+            if k == j {
+                continue;
+            }
+            let mut next = vec![T::zero(); prod.len() + 1];
+            for (i, &pi) in prod.iter().enumerate() {
+                next[i + 1] = next[i + 1] + pi;
+                next[i] = next[i] - zk * pi;
+            }
+            prod = next;
+        }
+        for (ci, &pi) in c.iter_mut().zip(prod.iter()) {
+            *ci = *ci + wj * pi;
+        }
+    }
+    let mut zs = roots(&c)?;
+    for z in zs.iter_mut() {
+        for _ in 0..5 {
+            let (d, dd) = support.iter().zip(w.iter()).fold(
+                (
+                    Complex::new(T::zero(), T::zero()),
+                    Complex::new(T::zero(), T::zero()),
+                ),
+                |(d, dd), (&zj, &wj)| {
+                    let r = (*z - zj).inv();
+                    (d + r * wj, dd - r * r * wj)
+                },
+            );
+            let step = d / dd;
+            if !(step.re.is_finite() && step.im.is_finite()) {
+                break;
+            }
+            *z = *z - step;
+        }
+    }
+    Ok(zs)
+}
+
+/// Roots of sum_k a\[k\] z^k by Laguerre's method with deflation and polishing.
+fn roots<T>(a: &[T]) -> Result<Vec<Complex<T>>>
+where
+    T: InterpFloat,
+{
+    let deg = match a.iter().rposition(|&v| v != T::zero()) {
+        Some(deg) => deg,
+        None => return Ok(vec![]),
+    };
+    let a: Vec<Complex<T>> = a[..=deg]
+        .iter()
+        .map(|&v| Complex::new(v, T::zero()))
+        .collect();
+    let mut ad = a.clone();
+    let mut zs = vec![Complex::new(T::zero(), T::zero()); deg];
+    for j in (0..deg).rev() {
+        let mut x = Complex::new(T::zero(), T::zero());
+        laguer(&ad[..(j + 2)], &mut x)?;
+        if x.im.abs() <= T::lit(2.0) * T::epsilon() * x.re.abs() {
+            x.im = T::zero();
+        }
+        zs[j] = x;
+        let mut b = ad[j + 1];
+        for jj in (0..=j).rev() {
+            let c = ad[jj];
+            ad[jj] = b;
+            b = x * b + c;
+        }
+    }
+    for z in zs.iter_mut() {
+        laguer(&a, z)?;
+    }
+    zs.sort_by(|u, v| u.re.total_order(&v.re));
+    Ok(zs)
+}
+
+fn laguer<T>(a: &[Complex<T>], x: &mut Complex<T>) -> Result<()>
+where
+    T: InterpFloat,
+{
+    const MR: usize = 8;
+    const MT: usize = 10;
+    let frac = [0.0, 0.5, 0.25, 0.75, 0.13, 0.38, 0.62, 0.88, 1.0];
+    let m = a.len() - 1;
+    let mf = T::from_usize(m).unwrap();
+    let zero = Complex::new(T::zero(), T::zero());
+    for iter in 1..=(MT * MR) {
+        let mut b = a[m];
+        let mut err = b.norm();
+        let (mut d, mut f) = (zero, zero);
+        let abx = x.norm();
+        for j in (0..m).rev() {
+            f = *x * f + d;
+            d = *x * d + b;
+            b = *x * b + a[j];
+            err = b.norm() + abx * err;
+        }
+        err = err * T::epsilon();
+        if b.norm() <= err {
+            return Ok(());
+        }
+        let g = d / b;
+        let g2 = g * g;
+        let h = g2 - f * T::lit(2.0) / b;
+        let sq = ((h * mf - g2) * (mf - T::one())).sqrt();
+        let mut gp = g + sq;
+        let gm = g - sq;
+        let abp = gp.norm();
+        let abm = gm.norm();
+        if abp < abm {
+            gp = gm;
+        }
+        let dx = if abp.max(abm) > T::zero() {
+            Complex::new(mf, T::zero()) / gp
+        } else {
+            Complex::from_polar(T::one() + abx, T::from_usize(iter).unwrap())
+        };
+        let x1 = *x - dx;
+        if *x == x1 {
+            return Ok(());
+        }
+        if iter % MT != 0 {
+            *x = x1;
+        } else {
+            *x = *x - dx * T::lit(frac[iter / MT]);
+        }
+    }
+    Err(InterpError::NotConverged().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pade() {
+        // [2/2] Pade approximant of exp is (12 + 6x + x^2) / (12 - 6x + x^2)
+        let c: Vec<f64> = vec![1.0, 1.0, 0.5, 1.0 / 6.0, 1.0 / 24.0];
+        let r = RationalFunction::pade(&c, 2, 2).unwrap();
+        assert_eq!(r.degree(), (2, 2));
+        for x in [-1.0, 0.3, 2.0] {
+            let expected = (12.0 + 6.0 * x + x * x) / (12.0 - 6.0 * x + x * x);
+            assert!((r.eval(x) - expected).abs() < 1e-12, "x = {}", x);
+        }
+        let poles = r.poles().unwrap();
+        assert_eq!(poles.len(), 2);
+        for p in poles {
+            assert!((p.re - 3.0).abs() < 1e-10 && (p.im.abs() - 3f64.sqrt()).abs() < 1e-10);
+        }
+        let zeros = r.zeros().unwrap();
+        for z in zeros {
+            assert!((z.re + 3.0).abs() < 1e-10 && (z.im.abs() - 3f64.sqrt()).abs() < 1e-10);
+        }
+
+        // ln(1 + x) = x - x^2/2 + x^3/3 - ..., [1/1] is x / (1 + x/2)
+        let c: Vec<f64> = vec![0.0, 1.0, -0.5];
+        let r = RationalFunction::pade(&c, 1, 1).unwrap();
+        assert!((r.eval(1.0) - 2.0 / 3.0).abs() < 1e-12);
+        assert!((r.poles().unwrap()[0].re + 2.0).abs() < 1e-12);
+        assert!(RationalFunction::pade(&c, 2, 1).is_err());
+    }
+
+    #[test]
+    fn test_minimax() {
+        // the best polynomial of degree 4 for exp on [-1, 1] has an error of about 5.5e-4
+        let (r, e) = RationalFunction::minimax(f64::exp, -1.0, 1.0, 4, 0).unwrap();
+        assert!(e > 5.0e-4 && e < 6.0e-4, "e = {}", e);
+        // a rational function of the same number of parameters does better
+        let (r2, e2) = RationalFunction::minimax(f64::exp, -1.0, 1.0, 2, 2).unwrap();
+        assert!(e2 < e, "e2 = {}", e2);
+        for i in 0..=100 {
+            let x = -1.0 + 0.02 * i as f64;
+            assert!((r.eval(x) - x.exp()).abs() <= e * (1.0 + 1e-3), "x = {}", x);
+            assert!(
+                (r2.eval(x) - x.exp()).abs() <= e2 * (1.0 + 1e-3),
+                "x = {}",
+                x
+            );
+        }
+        // equioscillation at the end points
+        assert!(((r2.eval(-1.0) - (-1f64).exp()).abs() - e2).abs() < 1e-3 * e2);
+        assert!(((r2.eval(1.0) - 1f64.exp()).abs() - e2).abs() < 1e-3 * e2);
+
+        // an exact fit needs no equioscillation
+        let (r3, e3) =
+            RationalFunction::minimax(|x: f64| 1.0 / (x + 3.0), -1.0, 1.0, 0, 1).unwrap();
+        assert!(
+            e3 < 1e-13 && (r3.eval(0.5) - 1.0 / 3.5).abs() < 1e-13,
+            "e3 = {}",
+            e3
+        );
+        // the error of a function undefined on part of the interval never levels out
+        let res = RationalFunction::minimax(|x: f64| (x - 0.3).sqrt(), -1.0, 1.0, 3, 0);
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<InterpError>(),
+            Some(InterpError::NotConverged())
+        ));
+    }
+
+    #[test]
+    fn test_aaa() {
+        let xx: Vec<f64> = (0..200).map(|i| -1.0 + 2.0 * i as f64 / 199.0).collect();
+        let yy: Vec<f64> = xx.iter().map(|&x| (5.0 * x).tanh()).collect();
+        let r = RationalFunction::aaa(&xx, &yy, 1e-13, 100).unwrap();
+        assert!(r.support().len() < 40, "m = {}", r.support().len());
+        for x in [-0.987, -0.3, 0.001, 0.55] {
+            assert!((r.eval(x) - (5.0 * x).tanh()).abs() < 1e-10, "x = {}", x);
+        }
+        // the poles of tanh(5x) closest to the interval are +-i pi / 10
+        let poles = r.poles().unwrap();
+        let near: Vec<&Complex<f64>> = poles.iter().filter(|p| p.norm() < 0.5).collect();
+        assert_eq!(near.len(), 2, "poles = {:?}", poles);
+        for p in near {
+            assert!(p.re.abs() < 1e-6, "p = {}", p);
+            assert!(
+                (p.im.abs() - std::f64::consts::PI / 10.0).abs() < 1e-6,
+                "p = {}",
+                p
+            );
+        }
+        assert!(r.zeros().unwrap().iter().any(|z| z.norm() < 1e-8));
+
+        // a rational function is recovered exactly with 3 support points
+        let yy: Vec<f64> = xx.iter().map(|&x| x / (x * x + 0.09)).collect();
+        let r = RationalFunction::aaa(&xx, &yy, 1e-13, 100).unwrap();
+        assert_eq!(r.degree(), (2, 2));
+        let poles = r.poles().unwrap();
+        assert_eq!(poles.len(), 2);
+        for p in poles {
+            assert!(
+                p.re.abs() < 1e-8 && (p.im.abs() - 0.3).abs() < 1e-8,
+                "p = {}",
+                p
+            );
+        }
+
+        let xs = [0.0, 0.5, 1.0, 0.5];
+        assert!(RationalFunction::aaa(&xs, &[1.0, 2.0, 3.0, 4.0], 1e-13, 10).is_err());
+        assert!(RationalFunction::aaa(&xx, &yy, 1e-13, 0).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+//! Point sets shared by the tests of the scattered data interpolators.
+
+use crate::Matrix;
+
+/// Pseudo random points in \[0, 1)^dim.
+pub(crate) fn random_points(n: usize, dim: usize, seed: u64) -> Matrix<f64> {
+    let mut state = seed;
+    let data = (0..(n * dim))
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        })
+        .collect();
+    Matrix::new(n, dim, data)
+}
+
+/// n x n grid on \[0, 1\]^2 with each node moved by up to `jitter` times the spacing.
+pub(crate) fn perturbed_grid(n: usize, jitter: f64) -> Matrix<f64> {
+    let mut data = vec![];
+    for i in 0..n {
+        for j in 0..n {
+            let x = (i as f64 + jitter * ((3 * j + i) % 5) as f64 / 5.0) / (n - 1) as f64;
+            let y = (j as f64 + jitter * ((2 * i + j) % 3) as f64 / 3.0) / (n - 1) as f64;
+            data.push(x.min(1.0));
+            data.push(y.min(1.0));
+        }
+    }
+    Matrix::new(n * n, 2, data)
+}
@@ -46,6 +46,11 @@ pub trait MatLinAlgBound:
         val.sqrt().into()
     }
 
+    /// Machine epsilon.
+    fn eps() -> Self {
+        f32::EPSILON.into()
+    }
+
     fn square(self) -> Self {
         (self * self).into()
     }
@@ -72,7 +77,15 @@ pub trait MatLinAlgBound:
 }
 
 impl MatLinAlgBound for f32 {}
-impl MatLinAlgBound for f64 {}
+impl MatLinAlgBound for f64 {
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn eps() -> Self {
+        f64::EPSILON
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum MatrixOpsError {
@@ -235,6 +248,37 @@ where
     }
 }
 
+impl<T> Sub for Matrix<T>
+where
+    T: Copy + Clone + SubAssign,
+{
+    type Output = Result<Self>;
+
+    fn sub(mut self, rhs: Self) -> Self::Output {
+        if self.nrows != rhs.rows() || self.ncols != rhs.cols() {
+            return Err(MatrixOpsError::InvalidMatrixSize(
+                "rhs".to_string(),
+                rhs.rows(),
+                rhs.cols(),
+            )
+            .into());
+        }
+        self -= rhs;
+        Ok(self)
+    }
+}
+
+impl<T> SubAssign for Matrix<T>
+where
+    T: Copy + Clone + SubAssign,
+{
+    fn sub_assign(&mut self, rhs: Self) {
+        for (lval, rval) in self.data.iter_mut().zip(rhs.data.iter()) {
+            *lval -= *rval;
+        }
+    }
+}
+
 impl<T> Mul for Matrix<T>
 where
     T: Copy + Clone + Mul + From<<T as Mul>::Output> + AddAssign + Zero,
@@ -342,6 +386,23 @@ mod tests {
         assert_eq!(out[2][3], 24);
     }
 
+    #[test]
+    fn test_matrix_sub() {
+        #[rustfmt::skip]
+        let lhs = Matrix::new(2, 3, vec![
+            1, 2, 3,
+            4, 5, 6,
+        ]);
+        let out = lhs.clone() - Matrix::new(1, 1, vec![1]);
+        assert!(out.is_err());
+
+        let out = (lhs.clone() - Matrix::new(2, 3, vec![1, 1, 1, 2, 2, 2])).unwrap();
+        assert_eq!(out[0][0], 0);
+        assert_eq!(out[0][2], 2);
+        assert_eq!(out[1][0], 2);
+        assert_eq!(out[1][2], 4);
+    }
+
     #[test]
     fn test_matrix() {
         let rows = 3;
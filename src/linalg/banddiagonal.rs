@@ -1,11 +1,15 @@
 use num::ToPrimitive;
 
+use anyhow::Result;
+
 use crate::{MatLinAlgBound, Matrix};
 
+use super::{sparse::NRsparseMat, LinAlgError};
+
 /// Matrix multipy b = Ax, where A is band-diagnoal
 /// with m1 rows below the diagnoal and m2 rows above.
 /// A is stored in a compact form.
-pub fn banmul<T>(a: &Matrix<T>, m1: usize, m2: usize, x: &Vec<T>, b: &mut Vec<T>)
+pub fn banmul<T>(a: &Matrix<T>, m1: usize, m2: usize, x: &[T], b: &mut [T])
 where
     T: MatLinAlgBound,
 {
@@ -21,6 +25,317 @@ where
     }
 }
 
+/// Band-diagonal matrix with `m1` subdiagonals and `m2` superdiagonals.
+/// Elements are kept in the compact form used by `banmul` and `Bandec`,
+/// i.e. a\[i\]\[j\] is stored at `data[i][j + m1 - i]`.
+#[derive(Debug, Clone)]
+pub struct BandMatrix<T>
+where
+    T: MatLinAlgBound,
+{
+    n: usize,
+    m1: usize,
+    m2: usize,
+    data: Matrix<T>,
+}
+
+impl<T> BandMatrix<T>
+where
+    T: MatLinAlgBound,
+{
+    /// Create a zero-filled n x n band matrix.
+    pub fn new(n: usize, m1: usize, m2: usize) -> Self {
+        let mm = m1 + m2 + 1;
+        BandMatrix {
+            n,
+            m1,
+            m2,
+            data: Matrix::new(n, mm, vec![T::zero(); n * mm]),
+        }
+    }
+
+    /// Wrap a matrix which is already in the compact form.
+    pub fn from_compact(a: Matrix<T>, m1: usize, m2: usize) -> Result<Self> {
+        if a.cols() != m1 + m2 + 1 {
+            return Err(LinAlgError::InvalidMatrixSize(a.rows(), a.cols()).into());
+        }
+        Ok(BandMatrix {
+            n: a.rows(),
+            m1,
+            m2,
+            data: a,
+        })
+    }
+
+    /// Convert a dense square matrix. The bandwidths are detected from the non-zero elements.
+    pub fn from_dense(a: &Matrix<T>) -> Result<Self> {
+        if a.rows() != a.cols() {
+            return Err(LinAlgError::InvalidMatrixSize(a.rows(), a.cols()).into());
+        }
+        let n = a.rows();
+        let (mut m1, mut m2) = (0, 0);
+        for i in 0..n {
+            for j in 0..n {
+                if a[i][j] != T::zero() {
+                    if i > j {
+                        m1 = m1.max(i - j);
+                    } else {
+                        m2 = m2.max(j - i);
+                    }
+                }
+            }
+        }
+        let mut band = Self::new(n, m1, m2);
+        for i in 0..n {
+            for j in band.row_range(i) {
+                band.data[i][j + m1 - i] = a[i][j];
+            }
+        }
+        Ok(band)
+    }
+
+    /// Convert a square sparse matrix. The bandwidths are detected from the non-zero elements.
+    pub fn from_sparse(a: &NRsparseMat<T>) -> Result<Self> {
+        if a.nrows != a.ncols {
+            return Err(LinAlgError::InvalidMatrixSize(a.nrows, a.ncols).into());
+        }
+        let n = a.nrows;
+        let (mut m1, mut m2) = (0, 0);
+        for j in 0..n {
+            for k in a.col_ptr[j]..a.col_ptr[j + 1] {
+                let i = a.row_ind[k];
+                if a.val[k] != T::zero() {
+                    if i > j {
+                        m1 = m1.max(i - j);
+                    } else {
+                        m2 = m2.max(j - i);
+                    }
+                }
+            }
+        }
+        let mut band = Self::new(n, m1, m2);
+        for j in 0..n {
+            for k in a.col_ptr[j]..a.col_ptr[j + 1] {
+                let i = a.row_ind[k];
+                if a.val[k] != T::zero() {
+                    band.data[i][j + m1 - i] += a.val[k];
+                }
+            }
+        }
+        Ok(band)
+    }
+
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    pub fn m1(&self) -> usize {
+        self.m1
+    }
+
+    pub fn m2(&self) -> usize {
+        self.m2
+    }
+
+    /// Reference to the compact storage.
+    pub fn compact(&self) -> &Matrix<T> {
+        &self.data
+    }
+
+    /// Column indices of row `i` which lie inside the band.
+    fn row_range(&self, i: usize) -> std::ops::Range<usize> {
+        i.saturating_sub(self.m1)..std::cmp::min(self.n, i + self.m2 + 1)
+    }
+
+    fn in_band(&self, i: usize, j: usize) -> bool {
+        i < self.n && j < self.n && j + self.m1 >= i && j <= i + self.m2
+    }
+
+    /// Element a\[i\]\[j\]. Elements outside the band are zero.
+    pub fn get(&self, i: usize, j: usize) -> T {
+        if self.in_band(i, j) {
+            self.data[i][j + self.m1 - i]
+        } else {
+            T::zero()
+        }
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, val: T) -> Result<()> {
+        if !self.in_band(i, j) {
+            return Err(LinAlgError::OutOfBand(i, j).into());
+        }
+        self.data[i][j + self.m1 - i] = val;
+        Ok(())
+    }
+
+    pub fn to_dense(&self) -> Matrix<T> {
+        let mut a = Matrix::new(self.n, self.n, vec![T::zero(); self.n * self.n]);
+        for i in 0..self.n {
+            for j in self.row_range(i) {
+                a[i][j] = self.data[i][j + self.m1 - i];
+            }
+        }
+        a
+    }
+
+    /// Convert to the compressed column storage, dropping explicit zeros.
+    pub fn to_sparse(&self) -> NRsparseMat<T> {
+        let mut col_ptr = vec![0; self.n + 1];
+        let mut row_ind = Vec::new();
+        let mut val = Vec::new();
+        for j in 0..self.n {
+            for i in j.saturating_sub(self.m2)..std::cmp::min(self.n, j + self.m1 + 1) {
+                let v = self.data[i][j + self.m1 - i];
+                if v != T::zero() {
+                    row_ind.push(i);
+                    val.push(v);
+                }
+            }
+            col_ptr[j + 1] = val.len();
+        }
+        NRsparseMat {
+            nrows: self.n,
+            ncols: self.n,
+            nvals: val.len(),
+            col_ptr,
+            row_ind,
+            val,
+        }
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut at = Self::new(self.n, self.m2, self.m1);
+        for i in 0..self.n {
+            for j in self.row_range(i) {
+                at.data[j][i + self.m2 - j] = self.data[i][j + self.m1 - i];
+            }
+        }
+        at
+    }
+
+    /// Multiply A by a vector x.
+    pub fn ax(&self, x: &[T]) -> Result<Vec<T>> {
+        if x.len() != self.n {
+            return Err(LinAlgError::InvalidVectorSize(x.len()).into());
+        }
+        let mut b = vec![T::zero(); self.n];
+        banmul(&self.data, self.m1, self.m2, x, &mut b);
+        Ok(b)
+    }
+
+    /// Multiply transpose of A by a vector x.
+    pub fn atx(&self, x: &[T]) -> Result<Vec<T>> {
+        self.transpose().ax(x)
+    }
+
+    /// Multiply A by each column of a dense matrix.
+    pub fn mul_mat(&self, x: &Matrix<T>) -> Result<Matrix<T>> {
+        if x.rows() != self.n {
+            return Err(LinAlgError::InvalidMatrixSize(x.rows(), x.cols()).into());
+        }
+        let mut b = Matrix::new(self.n, x.cols(), vec![T::zero(); self.n * x.cols()]);
+        for j in 0..x.cols() {
+            let col = self.ax(&x.get_col(j))?;
+            for (i, val) in col.into_iter().enumerate() {
+                b[i][j] = val;
+            }
+        }
+        Ok(b)
+    }
+
+    /// LU decomposition of the matrix. Keep it to solve for several right-hand sides
+    /// without factoring again.
+    pub fn bandec(&self) -> Bandec<T> {
+        Bandec::new(self.data.clone(), self.m1, self.m2)
+    }
+
+    /// Factors the matrix on each call, see `bandec`.
+    pub fn det(&self) -> T {
+        self.bandec().det()
+    }
+
+    /// Factors the matrix on each call, see `bandec`.
+    pub fn solve(&self, b: &[T], x: &mut [T]) -> Result<()> {
+        self.bandec().solve(b, x)
+    }
+
+    /// Factors the matrix on each call, see `bandec`.
+    pub fn solve_mat(&self, b: &Matrix<T>, x: &mut Matrix<T>) -> Result<()> {
+        self.bandec().solve_mat(b, x)
+    }
+
+    /// Cholesky decomposition of a symmetric positive definite matrix.
+    /// Only the diagonal and the `m1` subdiagonals are read.
+    pub fn cholesky(&self) -> Result<BandCholesky<T>> {
+        BandCholesky::new(self)
+    }
+}
+
+/// Cholesky decomposition A = L L' of a symmetric positive definite band matrix.
+/// L has the bandwidth of A and is kept in the compact form, l\[i\]\[d\] being L\[i\]\[i - d\].
+pub struct BandCholesky<T>
+where
+    T: MatLinAlgBound,
+{
+    n: usize,
+    m: usize,
+    l: Matrix<T>,
+}
+
+impl<T> BandCholesky<T>
+where
+    T: MatLinAlgBound,
+{
+    pub fn new(a: &BandMatrix<T>) -> Result<Self> {
+        let n = a.n();
+        let m = a.m1();
+        let mut l = Matrix::new(n, m + 1, vec![T::zero(); n * (m + 1)]);
+        for i in 0..n {
+            let lo = i.saturating_sub(m);
+            for j in lo..=i {
+                let mut sum = a.get(i, j);
+                for k in lo..j {
+                    sum -= (l[i][i - k] * l[j][j - k]).into();
+                }
+                if i == j {
+                    if sum <= T::zero() {
+                        return Err(LinAlgError::NegativeValueNotAllowed().into());
+                    }
+                    l[i][0] = sum.sqrt();
+                } else {
+                    l[i][i - j] = (sum / l[j][0]).into();
+                }
+            }
+        }
+        Ok(BandCholesky { n, m, l })
+    }
+
+    pub fn solve(&self, b: &[T], x: &mut [T]) -> Result<()> {
+        if b.len() != self.n {
+            return Err(LinAlgError::InvalidVectorSize(b.len()).into());
+        }
+        if x.len() != self.n {
+            return Err(LinAlgError::InvalidVectorSize(x.len()).into());
+        }
+        for i in 0..self.n {
+            let mut sum = b[i];
+            for (k, &xk) in x.iter().enumerate().take(i).skip(i.saturating_sub(self.m)) {
+                sum -= (self.l[i][i - k] * xk).into();
+            }
+            x[i] = (sum / self.l[i][0]).into();
+        }
+        for i in (0..self.n).rev() {
+            let mut sum = x[i];
+            for (k, &xk) in x.iter().enumerate().take(i + self.m + 1).skip(i + 1) {
+                sum -= (self.l[k][k - i] * xk).into();
+            }
+            x[i] = (sum / self.l[i][0]).into();
+        }
+        Ok(())
+    }
+}
+
+/// LU decomposition of a band-diagonal matrix stored in the compact form.
 pub struct Bandec<T>
 where
     T: MatLinAlgBound,
@@ -31,7 +346,8 @@ where
     au: Matrix<T>,
     al: Matrix<T>,
     indx: Vec<usize>,
-    _d: f32,
+    d: T,
+    singular: bool,
 }
 
 impl<T> Bandec<T>
@@ -39,12 +355,20 @@ where
     T: MatLinAlgBound,
 {
     pub fn new(a: Matrix<T>, m1: usize, m2: usize) -> Self {
-        let tiny = 1.0e-10;
+        let tiny: T = 1.0e-20.into();
         let n = a.rows();
         let mut au = a;
         let mut al = Matrix::new(au.rows(), au.cols(), vec![T::zero(); au.rows() * au.cols()]);
         let mut indx = Vec::with_capacity(n);
-        let mut d = 1.0;
+        let mut d: T = 1.0.into();
+        let mut singular = false;
+        // pivots below eps times the largest element are treated as zero
+        let scale = (0..n).fold(T::zero(), |acc, i| {
+            au[i]
+                .iter()
+                .fold(acc, |acc, v| if v.abs() > acc { v.abs() } else { acc })
+        });
+        let tol: T = (T::eps() * scale).into();
 
         let mm = m1 + m2 + 1;
         let mut l = m1;
@@ -60,33 +384,33 @@ where
 
         l = m1;
         for k in 0..n {
-            let mut dum = au[k][0].to_f32().unwrap();
+            let mut dum = au[k][0];
             let mut i = k;
             if l < n {
                 l += 1;
             }
             for j in (k + 1)..l {
-                if au[j][0].to_f32().unwrap().abs() > dum.abs() {
-                    dum = au[j][0].to_f32().unwrap();
+                if au[j][0].abs() > dum.abs() {
+                    dum = au[j][0];
                     i = j;
                 }
             }
-            indx.push(i + 1);
-            if dum.abs() < tiny {
-                // dum = TINY;
-            }
+            indx.push(i);
             if i != k {
-                d = -d;
+                d = (-d).into();
                 au.swap_rows(k, i);
             }
+            if dum.abs() <= tol {
+                singular = true;
+                au[k][0] = tiny;
+            }
             for i in (k + 1)..l {
-                dum = au[i][0].to_f32().unwrap() / au[k][0].to_f32().unwrap();
-                al[k][i - k - 1] = dum.into();
+                let dum: T = (au[i][0] / au[k][0]).into();
+                al[k][i - k - 1] = dum;
                 for j in 1..mm {
-                    au[i][j - 1] =
-                        (au[i][j].to_f32().unwrap() - dum * au[k][j].to_f32().unwrap()).into();
+                    au[i][j - 1] = (au[i][j] - T::from(dum * au[k][j])).into();
                 }
-                au[i][mm - 1] = 0.0.into();
+                au[i][mm - 1] = T::zero();
             }
         }
 
@@ -97,18 +421,26 @@ where
             au,
             al,
             indx,
-            _d: d,
+            d,
+            singular,
         }
     }
 
-    pub fn solve(&self, b: &Vec<T>, x: &mut Vec<T>) {
+    pub fn solve(&self, b: &[T], x: &mut [T]) -> Result<()> {
+        if b.len() != self.n {
+            return Err(LinAlgError::InvalidVectorSize(b.len()).into());
+        }
+        if x.len() != self.n {
+            return Err(LinAlgError::InvalidVectorSize(x.len()).into());
+        }
+        if self.singular {
+            return Err(LinAlgError::SingularMatrix("Bandec".to_string()).into());
+        }
         let mm = self.m1 + self.m2 + 1;
         let mut l = self.m1;
+        x.copy_from_slice(b);
         for k in 0..self.n {
-            x[k] = b[k];
-        }
-        for k in 0..self.n {
-            let j = self.indx[k] - 1;
+            let j = self.indx[k];
             if j != k {
                 x.swap(j, k);
             }
@@ -131,6 +463,38 @@ where
                 l += 1;
             }
         }
+        Ok(())
+    }
+
+    /// Solve for each column of `b`.
+    pub fn solve_mat(&self, b: &Matrix<T>, x: &mut Matrix<T>) -> Result<()> {
+        if b.rows() != self.n || x.rows() != self.n || b.cols() != x.cols() {
+            return Err(LinAlgError::InvalidMatrixSize(b.rows(), b.cols()).into());
+        }
+        let mut col = vec![T::zero(); self.n];
+        for j in 0..b.cols() {
+            self.solve(&b.get_col(j), &mut col)?;
+            for (i, val) in col.iter().enumerate() {
+                x[i][j] = *val;
+            }
+        }
+        Ok(())
+    }
+
+    /// A pivot vanished relative to the largest element of the matrix.
+    pub fn is_singular(&self) -> bool {
+        self.singular
+    }
+
+    pub fn det(&self) -> T {
+        if self.singular {
+            return T::zero();
+        }
+        let mut dd = self.d;
+        for i in 0..self.n {
+            dd *= self.au[i][0];
+        }
+        dd
     }
 }
 
@@ -151,11 +515,29 @@ mod tests {
 
         let b = vec![3.0, 6.0, 10.0, 9.0];
         let mut x = vec![1.0f32; 4];
-        bandec.solve(&b, &mut x);
+        let res = bandec.solve(&b, &mut x);
+        assert!(res.is_ok());
         assert!((x[0] - 1.0).abs() < 1e-10);
         assert!((x[1] - 2.0).abs() < 1e-10);
         assert!((x[2] - 3.0).abs() < 1e-10);
         assert!((x[3] - 4.0).abs() < 1e-10);
+
+        // a tiny pivot is singular relative to the other elements, not on its own
+        let a = Matrix::new(3, 3, vec![0.0, 1.0, 0.0, 0.0, 1e-300, 0.0, 0.0, 1.0, 0.0]);
+        let bandec = Bandec::new(a, 1, 1);
+        assert!(bandec.is_singular());
+        assert_eq!(bandec.det(), 0.0);
+        let mut x = vec![0.0; 3];
+        assert!(bandec.solve(&[1.0, 1.0, 1.0], &mut x).is_err());
+        let a = Matrix::new(
+            3,
+            3,
+            vec![0.0, 1e-300, 0.0, 0.0, 1e-300, 0.0, 0.0, 1e-300, 0.0],
+        );
+        let bandec = Bandec::new(a, 1, 1);
+        assert!(!bandec.is_singular());
+        bandec.solve(&[1e-300, 2e-300, 3e-300], &mut x).unwrap();
+        assert!((x[0] - 1.0).abs() < 1e-12 && (x[2] - 3.0).abs() < 1e-12);
     }
 
     #[test]
@@ -178,4 +560,78 @@ mod tests {
         assert!((b[2] - 10.0).abs() < 1e-10);
         assert!((b[3] - 9.0).abs() < 1e-10);
     }
+
+    #[test]
+    fn test_band_matrix() {
+        #[rustfmt::skip]
+        let dense = Matrix::new(4, 4, vec![
+            2.0, 1.0, 0.0, 0.0,
+            1.0, 3.0, 1.0, 0.0,
+            0.0, 1.0, 4.0, 1.0,
+            0.0, 0.0, 1.0, 5.0f64,
+        ]);
+        let band = BandMatrix::from_dense(&dense).unwrap();
+        assert_eq!(band.m1(), 1);
+        assert_eq!(band.m2(), 1);
+        assert!((band.get(2, 3) - 1.0).abs() < 1e-10);
+        assert!(band.get(0, 3).abs() < 1e-10);
+
+        let sparse = band.to_sparse();
+        assert_eq!(sparse.nvals, 10);
+        let band = BandMatrix::from_sparse(&sparse).unwrap();
+        let back = band.to_dense();
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((back[i][j] - dense[i][j]).abs() < 1e-10);
+            }
+        }
+
+        let b = band.ax(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert!((b[0] - 4.0).abs() < 1e-10);
+        assert!((b[1] - 10.0).abs() < 1e-10);
+        assert!((b[2] - 18.0).abs() < 1e-10);
+        assert!((b[3] - 23.0).abs() < 1e-10);
+        assert!(band.ax(&[1.0, 2.0]).is_err());
+
+        let mut x = vec![0.0; 4];
+        assert!(band.solve(&b, &mut x).is_ok());
+        assert!((x[0] - 1.0).abs() < 1e-10);
+        assert!((x[3] - 4.0).abs() < 1e-10);
+        assert!(band.solve(&b, &mut vec![0.0; 3]).is_err());
+        assert!((band.det() - 85.0).abs() < 1e-10, "det = {}", band.det());
+
+        let rhs = band
+            .mul_mat(&Matrix::new(
+                4,
+                2,
+                vec![1.0, 4.0, 2.0, 3.0, 3.0, 2.0, 4.0, 1.0],
+            ))
+            .unwrap();
+        let mut sol = Matrix::new(4, 2, vec![0.0; 8]);
+        assert!(band.solve_mat(&rhs, &mut sol).is_ok());
+        assert!((sol[0][1] - 4.0).abs() < 1e-10);
+        assert!((sol[3][1] - 1.0).abs() < 1e-10);
+
+        let chol = band.cholesky().unwrap();
+        let mut x = vec![0.0; 4];
+        assert!(chol.solve(&b, &mut x).is_ok());
+        for (i, v) in x.iter().enumerate() {
+            assert!((v - (i + 1) as f64).abs() < 1e-12, "x = {:?}", x);
+        }
+        let mut indefinite = band.clone();
+        indefinite.set(3, 3, 0.0).unwrap();
+        assert!(indefinite.cholesky().is_err());
+
+        let mut band = BandMatrix::new(3, 0, 2);
+        assert!(band.set(2, 0, 1.0).is_err());
+        assert!(band.set(0, 2, 1.0).is_ok());
+        let bt = band.transpose();
+        assert_eq!(bt.m1(), 2);
+        assert_eq!(bt.m2(), 0);
+        assert!((bt.get(2, 0) - 1.0).abs() < 1e-10);
+        let y = bt.ax(&[1.0, 0.0, 0.0]).unwrap();
+        assert!((y[2] - 1.0).abs() < 1e-10);
+        assert!(bt.det().abs() < 1e-10);
+        assert!(bt.solve(&[1.0, 1.0, 1.0], &mut vec![0.0; 3]).is_err());
+    }
 }
@@ -30,7 +30,7 @@ where
                     if sum <= T::zero() {
                         return Err(LinAlgError::NegativeValueNotAllowed())?;
                     }
-                    el[i][i] = sum.to_f32().unwrap().sqrt().into();
+                    el[i][i] = sum.sqrt();
                 } else {
                     el[j][i] = (sum / el[i][i]).into();
                 }
@@ -2,38 +2,79 @@ use anyhow::Result;
 
 use crate::{linalg::LinAlgError, MatLinAlgBound, Matrix};
 
+/// Pivot selection strategy of the elimination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pivoting {
+    /// Search the largest element of the whole remaining submatrix.
+    Full,
+    /// Search the largest element of the current column.
+    Partial,
+}
+
+/// Reduced row echelon form of a matrix.
+#[derive(Debug, Clone)]
+pub struct RowEchelon<T>
+where
+    T: MatLinAlgBound,
+{
+    pub mat: Matrix<T>,
+    pub rank: usize,
+    /// Column index of the pivot of each non-zero row.
+    pub pivots: Vec<usize>,
+}
+
 /// Linear equation (Ax = b) solution by Gauss-Jordan elimination.
 /// On output, `a` is replaced by its matrix inverse, and
 /// `b` is replaced by the corresponding set of solution vectors.
-pub fn gauss_jordan<T>(mut a: Matrix<T>, mut b: Matrix<T>) -> Result<(Matrix<T>, Matrix<T>)>
+pub fn gauss_jordan<T>(a: Matrix<T>, b: Matrix<T>) -> Result<(Matrix<T>, Matrix<T>)>
+where
+    T: MatLinAlgBound,
+{
+    gauss_jordan_with(a, b, Pivoting::Full, 1e-7.into())
+}
+
+/// Same as `gauss_jordan` with the given pivoting strategy.
+/// The matrix is regarded as singular when the magnitude of a pivot is not larger than `tol`.
+pub fn gauss_jordan_with<T>(
+    mut a: Matrix<T>,
+    mut b: Matrix<T>,
+    pivoting: Pivoting,
+    tol: T,
+) -> Result<(Matrix<T>, Matrix<T>)>
 where
     T: MatLinAlgBound,
 {
     let n = a.rows();
-    let m = b.cols();
+    if a.cols() != n {
+        return Err(LinAlgError::InvalidMatrixSize(a.rows(), a.cols()).into());
+    }
+    if b.rows() != n {
+        return Err(LinAlgError::InvalidMatrixSize(b.rows(), b.cols()).into());
+    }
 
     let mut indxc = Vec::with_capacity(n);
     let mut indxr = Vec::with_capacity(n);
-    let mut ipiv = vec![0; n];
+    let mut ipiv = vec![false; n];
 
-    let mut irow = 0;
-    let mut icol = 0;
     for i in 0..n {
-        let mut big: T = 0.0.into();
-        for j in 0..n {
-            if ipiv[j] != 1 {
-                for k in 0..n {
-                    if ipiv[k] == 0 {
-                        if a[j][k] >= big {
-                            big = a[j][k];
+        let (irow, icol) = match pivoting {
+            Pivoting::Full => {
+                let mut big = T::zero();
+                let (mut irow, mut icol) = (0, 0);
+                for j in (0..n).filter(|&j| !ipiv[j]) {
+                    for k in (0..n).filter(|&k| !ipiv[k]) {
+                        if a[j][k].abs() >= big {
+                            big = a[j][k].abs();
                             irow = j;
                             icol = k;
                         }
                     }
                 }
+                (irow, icol)
             }
-        }
-        ipiv[icol] += 1;
+            Pivoting::Partial => (max_row(&a, i, i), i),
+        };
+        ipiv[icol] = true;
         indxr.push(irow);
         indxc.push(icol);
         if irow != icol {
@@ -41,45 +82,116 @@ where
             b.swap_rows(irow, icol);
         }
 
-        if a[icol][icol].to_f32().unwrap().abs() < 1e-7 {
-            return Err(LinAlgError::SingularMatrix(
-                format!("gauss_jordan : i = {}, irow = {}, icol = {}", i, irow, icol).to_string(),
-            ))?;
+        if a[icol][icol].abs() <= tol {
+            return Err(LinAlgError::SingularMatrix(format!(
+                "gauss_jordan : i = {}, irow = {}, icol = {}",
+                i, irow, icol
+            ))
+            .into());
         }
+        eliminate(&mut a, &mut b, icol, icol, true);
+    }
 
-        let pivinv = 1.0 / a[icol][icol].to_f32().unwrap();
-        a[icol][icol] = 1.0f32.into();
-        for l in 0..n {
-            let val = a[icol][l].to_f32().unwrap() * pivinv;
-            a[icol][l] = val.into();
+    for i in (0..n).rev() {
+        if indxr[i] != indxc[i] {
+            a.swap_cols(indxr[i], indxc[i]);
         }
-        for l in 0..m {
-            let val = b[icol][l].to_f32().unwrap() * pivinv;
-            b[icol][l] = val.into();
+    }
+
+    Ok((a, b))
+}
+
+/// Reduced row echelon form of a (possibly rectangular) matrix by Gauss-Jordan elimination
+/// with partial pivoting. Columns whose remaining elements are not larger than `tol` are
+/// regarded as zero, which determines the rank.
+pub fn rref<T>(mut a: Matrix<T>, tol: T) -> RowEchelon<T>
+where
+    T: MatLinAlgBound,
+{
+    let mut b = Matrix::new(a.rows(), 0, vec![]);
+    let mut pivots = Vec::new();
+    let mut row = 0;
+    for col in 0..a.cols() {
+        if row == a.rows() {
+            break;
         }
-        for l in 0..n {
-            if l != icol {
-                let scale: f32 = a[l][icol].to_f32().unwrap();
-                a[l][icol] = 0f32.into();
-                for k in 0..n {
-                    let val = a[l][k].to_f32().unwrap() - scale * a[icol][k].to_f32().unwrap();
-                    a[l][k] = val.into();
-                }
-                for k in 0..m {
-                    let val = b[l][k].to_f32().unwrap() - scale * b[icol][k].to_f32().unwrap();
-                    b[l][k] = val.into();
-                }
+        let irow = max_row(&a, row, col);
+        if a[irow][col].abs() <= tol {
+            for i in row..a.rows() {
+                a[i][col] = T::zero();
             }
+            continue;
+        }
+        if irow != row {
+            a.swap_rows(irow, row);
         }
+        eliminate(&mut a, &mut b, row, col, false);
+        pivots.push(col);
+        row += 1;
     }
+    RowEchelon {
+        mat: a,
+        rank: row,
+        pivots,
+    }
+}
 
-    for i in (0..n).rev() {
-        if indxr[i] != indxc[i] {
-            a.swap_cols(indxr[i], indxc[i]);
+/// Numerical rank of a matrix (see `rref`).
+pub fn rank<T>(a: &Matrix<T>, tol: T) -> usize
+where
+    T: MatLinAlgBound,
+{
+    rref(a.clone(), tol).rank
+}
+
+/// Row index of the largest element in magnitude of column `col` at or below `row`.
+fn max_row<T>(a: &Matrix<T>, row: usize, col: usize) -> usize
+where
+    T: MatLinAlgBound,
+{
+    let mut irow = row;
+    for i in (row + 1)..a.rows() {
+        if a[i][col].abs() > a[irow][col].abs() {
+            irow = i;
         }
     }
+    irow
+}
 
-    Ok((a, b))
+/// Normalize the pivot row and eliminate the pivot column from the other rows.
+/// When `inverse` is true, the pivot column is overwritten by the corresponding
+/// column of the inverse (in-place inversion).
+fn eliminate<T>(a: &mut Matrix<T>, b: &mut Matrix<T>, row: usize, col: usize, inverse: bool)
+where
+    T: MatLinAlgBound,
+{
+    let pivinv: T = (T::from(1.0) / a[row][col]).into();
+    if inverse {
+        a[row][col] = 1.0.into();
+    }
+    for val in a[row].iter_mut().chain(b[row].iter_mut()) {
+        *val *= pivinv;
+    }
+    if !inverse {
+        a[row][col] = 1.0.into();
+    }
+    for l in (0..a.rows()).filter(|&l| l != row) {
+        let scale = a[l][col];
+        if scale == T::zero() {
+            continue;
+        }
+        a[l][col] = T::zero();
+        for k in 0..a.cols() {
+            if inverse || k != col {
+                let val = scale * a[row][k];
+                a[l][k] -= val.into();
+            }
+        }
+        for k in 0..b.cols() {
+            let val = scale * b[row][k];
+            b[l][k] -= val.into();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -132,4 +244,87 @@ mod tests {
         assert!((b[1][0] - 3.0).abs() < 1e-5);
         assert!((b[2][0] - 1.0).abs() < 1e-5);
     }
+
+    #[test]
+    fn test_gauss_jordan_pivoting() {
+        // the largest pivots are negative
+        #[rustfmt::skip]
+        let da: Vec<f64> = vec![
+            0.0, -1.0,
+            -1.0, 0.0,
+        ];
+        for pivoting in [Pivoting::Full, Pivoting::Partial] {
+            let a = Matrix::new(2, 2, da.clone());
+            let b = Matrix::new(2, 1, vec![2.0, 3.0]);
+            let (a, b) = gauss_jordan_with(a, b, pivoting, 1e-12).unwrap();
+            assert!(a[0][0].abs() < 1e-12);
+            assert!((a[0][1] + 1.0).abs() < 1e-12);
+            assert!((a[1][0] + 1.0).abs() < 1e-12);
+            assert!(a[1][1].abs() < 1e-12);
+            assert!((b[0][0] + 3.0).abs() < 1e-12);
+            assert!((b[1][0] + 2.0).abs() < 1e-12);
+        }
+
+        // full precision
+        #[rustfmt::skip]
+        let da: Vec<f64> = vec![
+            4.0, -2.0, 1.0,
+            -2.0, 4.0, -2.0,
+            1.0, -2.0, 3.0,
+        ];
+        let x = vec![1.0 / 3.0, -0.125, 2.0e-3];
+        let db: Vec<f64> = (0..3)
+            .map(|i| (0..3).map(|j| da[i * 3 + j] * x[j]).sum())
+            .collect();
+        for pivoting in [Pivoting::Full, Pivoting::Partial] {
+            let a = Matrix::new(3, 3, da.clone());
+            let b = Matrix::new(3, 1, db.clone());
+            let (ainv, b) = gauss_jordan_with(a.clone(), b, pivoting, 1e-12).unwrap();
+            let ident = (a * ainv).unwrap();
+            for i in 0..3 {
+                assert!((b[i][0] - x[i]).abs() < 1e-14, "b = {:?}", b);
+                for j in 0..3 {
+                    let expect = if i == j { 1.0 } else { 0.0 };
+                    assert!((ident[i][j] - expect).abs() < 1e-14);
+                }
+            }
+        }
+
+        // singular and tolerance
+        let a = Matrix::new(2, 2, vec![1.0, 2.0, 2.0, 4.0 + 1e-9]);
+        let b = Matrix::new(2, 1, vec![1.0, 1.0]);
+        assert!(gauss_jordan(a.clone(), b.clone()).is_err());
+        assert!(gauss_jordan_with(a.clone(), b.clone(), Pivoting::Partial, 1e-7).is_err());
+        assert!(gauss_jordan_with(a, b, Pivoting::Partial, 1e-12).is_ok());
+    }
+
+    #[test]
+    fn test_rref() {
+        #[rustfmt::skip]
+        let a = Matrix::new(3, 4, vec![
+            1.0, 2.0, 1.0, 4.0,
+            2.0, 4.0, 0.0, 6.0,
+            -1.0, -2.0, 1.0, -2.0f64,
+        ]);
+        let echelon = rref(a.clone(), 1e-12);
+        assert_eq!(echelon.rank, 2);
+        assert_eq!(echelon.pivots, vec![0, 2]);
+        #[rustfmt::skip]
+        let expect = [
+            1.0, 2.0, 0.0, 3.0,
+            0.0, 0.0, 1.0, 1.0,
+            0.0, 0.0, 0.0, 0.0,
+        ];
+        for i in 0..3 {
+            for j in 0..4 {
+                assert!(
+                    (echelon.mat[i][j] - expect[i * 4 + j]).abs() < 1e-12,
+                    "{:?}",
+                    echelon.mat
+                );
+            }
+        }
+        assert_eq!(rank(&a.transpose(), 1e-12), 2);
+        assert_eq!(rank(&Matrix::<f64>::identity(3), 1e-12), 3);
+    }
 }
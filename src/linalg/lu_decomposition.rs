@@ -92,8 +92,10 @@ where
             return Err(LinAlgError::InvalidVectorSize(x.len()))?;
         }
 
+        // indx records the row swaps in the order they were made
+        x.copy_from_slice(b);
         for i in 0..self.n {
-            x[i] = b[self.indx[i]];
+            x.swap(i, self.indx[i]);
         }
 
         let mut ii = 0;
@@ -126,7 +128,7 @@ where
 
         for j in 0..b.cols() {
             let mut col = x.get_col(j);
-            match self.solve(&b[j].to_vec(), &mut col) {
+            match self.solve(&b.get_col(j), &mut col) {
                 Ok(_) => {
                     for i in 0..x.rows() {
                         x[i][j] = col[i];
@@ -236,6 +238,19 @@ mod tests {
         assert!((x[2].to_f32().unwrap() + 3.0).abs() < 1e-7);
     }
 
+    #[test]
+    fn test_ludcmp_pivoting() {
+        // needs a cycle of row swaps
+        let mat = Matrix::new(3, 3, vec![1.0, 2.0, 4.0, 3.0, 1.0, 2.0, 9.0, 3.0, 1.0]);
+        let ludcmp = LUdcmp::new(&mat).unwrap();
+        let b = vec![17.0, 11.0, 18.0];
+        let mut x = vec![0.0; 3];
+        ludcmp.solve(&b, &mut x).unwrap();
+        for (v, e) in x.iter().zip([1.0, 2.0, 3.0].iter()) {
+            assert!((v - e).abs() < 1e-10, "x = {:?}", x);
+        }
+    }
+
     #[test]
     fn test_ludcmp_singular() {
         // Singular matrix
@@ -28,6 +28,9 @@ pub enum LinAlgError {
 
     #[error("Negative value not allowed")]
     NegativeValueNotAllowed(),
+
+    #[error("Element ({0}, {1}) lies outside the band")]
+    OutOfBand(usize, usize),
 }
 
 pub mod banddiagonal;
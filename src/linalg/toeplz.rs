@@ -1,9 +1,21 @@
 use anyhow::Result;
+use num::{complex::Complex, Float, NumCast};
 
-use crate::MatLinAlgBound;
+use crate::{MatLinAlgBound, Matrix};
 
 use super::LinAlgError;
 
+/// Result of the Levinson-Durbin recursion.
+#[derive(Debug, Clone)]
+pub struct LevinsonDurbin<T> {
+    /// Prediction coefficients a\[0..p\], i.e. x\[t\] ~ sum_j a\[j\] x\[t - j - 1\].
+    pub coeffs: Vec<T>,
+    /// Reflection (partial autocorrelation) coefficients of each order.
+    pub reflection: Vec<T>,
+    /// Prediction error variance of the final order.
+    pub error: T,
+}
+
 pub fn toeplz<T>(r: &Vec<T>, x: &mut Vec<T>, y: &Vec<T>) -> Result<()>
 where
     T: MatLinAlgBound,
@@ -76,6 +88,242 @@ where
     return Err(LinAlgError::ShouldNotArriveHere())?;
 }
 
+/// Levinson-Durbin recursion for the Yule-Walker equations of order `order`.
+/// `r[0..=order]` is the autocorrelation sequence, i.e. the first row of the symmetric Toeplitz matrix.
+pub fn levinson_durbin<T>(r: &[T], order: usize) -> Result<LevinsonDurbin<T>>
+where
+    T: MatLinAlgBound,
+{
+    if r.len() <= order {
+        return Err(LinAlgError::InvalidVectorSize(r.len()).into());
+    }
+    let mut coeffs = vec![T::zero(); order];
+    let mut reflection = Vec::with_capacity(order);
+    let mut error = r[0];
+    for m in 0..order {
+        if is_tiny(error, r[0]) {
+            return Err(LinAlgError::SingularPrincipleMinor().into());
+        }
+        let mut acc = r[m + 1];
+        for j in 0..m {
+            acc -= (coeffs[j] * r[m - j]).into();
+        }
+        let k: T = (acc / error).into();
+        let prev = coeffs[..m].to_vec();
+        for j in 0..m {
+            coeffs[j] = (prev[j] - T::from(k * prev[m - 1 - j])).into();
+        }
+        coeffs[m] = k;
+        reflection.push(k);
+        error *= (T::from(1.0) - T::from(k * k)).into();
+    }
+    Ok(LevinsonDurbin {
+        coeffs,
+        reflection,
+        error,
+    })
+}
+
+/// Solves the symmetric Toeplitz system sum_j r\[|i - j|\] x\[j\] = y\[i\] by the Levinson algorithm.
+/// `r[0..n]` is the first row of the matrix.
+pub fn sym_toeplz<T>(r: &[T], x: &mut [T], y: &[T]) -> Result<()>
+where
+    T: MatLinAlgBound,
+{
+    let n = y.len();
+    if n == 0 {
+        return Err(LinAlgError::InvalidVectorSize(0).into());
+    }
+    if r.len() < n {
+        return Err(LinAlgError::InvalidVectorSize(r.len()).into());
+    }
+    if x.len() != n {
+        return Err(LinAlgError::InvalidVectorSize(x.len()).into());
+    }
+    if is_tiny(r[0], r[0]) {
+        return Err(LinAlgError::SingularPrincipleMinor().into());
+    }
+    // normalized matrix elements and right-hand side
+    let t: Vec<T> = r[..n].iter().map(|&v| (v / r[0]).into()).collect();
+    let b: Vec<T> = y.iter().map(|&v| (v / r[0]).into()).collect();
+
+    // z solves the Yule-Walker system of the growing order.
+    let mut z = vec![T::zero(); n];
+    x[0] = b[0];
+    if n == 1 {
+        return Ok(());
+    }
+    z[0] = (-t[1]).into();
+    let mut alpha = z[0];
+    let mut beta: T = 1.0.into();
+    for k in 1..n {
+        beta *= (T::from(1.0) - T::from(alpha * alpha)).into();
+        if is_tiny(beta, 1.0.into()) {
+            return Err(LinAlgError::SingularPrincipleMinor().into());
+        }
+        let mut mu = b[k];
+        for i in 1..=k {
+            mu -= (t[i] * x[k - i]).into();
+        }
+        mu /= beta;
+        for i in 0..k {
+            x[i] += (mu * z[k - 1 - i]).into();
+        }
+        x[k] = mu;
+        if k < n - 1 {
+            let mut acc = t[k + 1];
+            for i in 1..=k {
+                acc += (t[i] * z[k - i]).into();
+            }
+            alpha = (-T::from(acc / beta)).into();
+            let prev = z[..k].to_vec();
+            for i in 0..k {
+                z[i] = prev[i] + T::from(alpha * prev[k - 1 - i]);
+            }
+            z[k] = alpha;
+        }
+    }
+    Ok(())
+}
+
+/// Inverse of the symmetric Toeplitz matrix whose first row is `r[0..n]` by the Trench algorithm.
+pub fn trench<T>(r: &[T]) -> Result<Matrix<T>>
+where
+    T: MatLinAlgBound,
+{
+    let n = r.len();
+    if n == 0 {
+        return Err(LinAlgError::InvalidVectorSize(n).into());
+    }
+    if is_tiny(r[0], r[0]) {
+        return Err(LinAlgError::SingularPrincipleMinor().into());
+    }
+    let mut inv = Matrix::new(n, n, vec![T::zero(); n * n]);
+    if n == 1 {
+        inv[0][0] = (T::from(1.0) / r[0]).into();
+        return Ok(inv);
+    }
+
+    // gamma = 1 / (1 + t^T y) where y solves the normalized Yule-Walker system (y = -a).
+    let ld = levinson_durbin(r, n - 1)?;
+    if is_tiny(ld.error, r[0]) {
+        return Err(LinAlgError::SingularPrincipleMinor().into());
+    }
+    let gamma: T = (r[0] / ld.error).into();
+    let nu: Vec<T> = (0..(n - 1))
+        .map(|i| (-T::from(gamma * ld.coeffs[n - 2 - i])).into())
+        .collect();
+
+    inv[0][0] = gamma;
+    for j in 1..n {
+        inv[0][j] = nu[n - 1 - j];
+    }
+    for i in 1..=((n - 1) / 2) {
+        for j in i..(n - i) {
+            let num = T::from(nu[n - 1 - j] * nu[n - 1 - i]) - T::from(nu[i - 1] * nu[j - 1]);
+            inv[i][j] = inv[i - 1][j - 1] + T::from(T::from(num) / gamma);
+        }
+    }
+    // fill the rest by symmetry and persymmetry
+    for i in 0..=((n - 1) / 2) {
+        for j in i..(n - i) {
+            let val = inv[i][j];
+            inv[j][i] = val;
+            inv[n - 1 - j][n - 1 - i] = val;
+            inv[n - 1 - i][n - 1 - j] = val;
+        }
+    }
+    for i in 0..n {
+        for j in 0..n {
+            inv[i][j] /= r[0];
+        }
+    }
+    Ok(inv)
+}
+
+/// Toeplitz matrix-vector product sum_j r\[n - 1 + i - j\] x\[j\] (same layout as `toeplz`)
+/// computed by embedding the matrix into a circulant one and using the FFT.
+pub fn toeplz_mul<T>(r: &[T], x: &[T]) -> Result<Vec<T>>
+where
+    T: MatLinAlgBound + Float,
+{
+    let n = x.len();
+    if n == 0 || r.len() != 2 * n - 1 {
+        return Err(LinAlgError::InvalidVectorSize(r.len()).into());
+    }
+    let size = (2 * n - 1).next_power_of_two();
+    let mut c = vec![Complex::new(T::zero(), T::zero()); size];
+    let mut xx = c.clone();
+    for k in 0..n {
+        c[k].re = r[n - 1 + k];
+        xx[k].re = x[k];
+    }
+    for k in 1..n {
+        c[size - k].re = r[n - 1 - k];
+    }
+    fft(&mut c, false);
+    fft(&mut xx, false);
+    for (cv, xv) in c.iter_mut().zip(xx.iter()) {
+        *cv = *cv * *xv;
+    }
+    fft(&mut c, true);
+    Ok(c.iter().take(n).map(|v| v.re).collect())
+}
+
+/// In-place radix-2 FFT. The length of `a` must be a power of two.
+fn fft<T>(a: &mut [Complex<T>], invert: bool)
+where
+    T: Float,
+{
+    let n = a.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            a.swap(i, j);
+        }
+    }
+    let two_pi = <T as NumCast>::from(2.0 * std::f64::consts::PI).unwrap();
+    let mut len = 2;
+    while len <= n {
+        let mut ang = two_pi / <T as NumCast>::from(len).unwrap();
+        if !invert {
+            ang = -ang;
+        }
+        let wlen = Complex::new(ang.cos(), ang.sin());
+        for start in (0..n).step_by(len) {
+            let mut w = Complex::new(T::one(), T::zero());
+            for k in 0..(len / 2) {
+                let u = a[start + k];
+                let v = a[start + k + len / 2] * w;
+                a[start + k] = u + v;
+                a[start + k + len / 2] = u - v;
+                w = w * wlen;
+            }
+        }
+        len <<= 1;
+    }
+    if invert {
+        let scale = <T as NumCast>::from(n).unwrap();
+        for v in a.iter_mut() {
+            *v = *v / scale;
+        }
+    }
+}
+
+/// Whether `val` is negligible compared to `scale`, i.e. at the rounding level of `T`.
+fn is_tiny<T>(val: T, scale: T) -> bool
+where
+    T: MatLinAlgBound,
+{
+    val.abs() <= T::from(T::eps() * scale.abs())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,4 +340,121 @@ mod tests {
         assert!((x[1] - 2.0).abs() < 1e-7);
         assert!((x[2] - 3.0).abs() < 1e-7);
     }
+
+    #[test]
+    fn test_levinson_durbin() {
+        // AR(1) process with coefficient 0.5
+        let r = vec![1.0, 0.5, 0.25, 0.125f64];
+        let ld = levinson_durbin(&r, 3).unwrap();
+        assert!((ld.coeffs[0] - 0.5).abs() < 1e-12, "{:?}", ld);
+        assert!(ld.coeffs[1].abs() < 1e-12, "{:?}", ld);
+        assert!(ld.coeffs[2].abs() < 1e-12, "{:?}", ld);
+        assert!((ld.reflection[0] - 0.5).abs() < 1e-12, "{:?}", ld);
+        assert!(ld.reflection[1].abs() < 1e-12, "{:?}", ld);
+        assert!((ld.error - 0.75).abs() < 1e-12, "{:?}", ld);
+
+        let r = vec![2.0, 1.0, 0.5f64];
+        let ld = levinson_durbin(&r, 2).unwrap();
+        // [[2, 1], [1, 2]] a = [1, 0.5]
+        assert!((ld.coeffs[0] - 0.5).abs() < 1e-12, "{:?}", ld);
+        assert!(ld.coeffs[1].abs() < 1e-12, "{:?}", ld);
+        assert!((ld.error - 1.5).abs() < 1e-12, "{:?}", ld);
+
+        // singular principal minor
+        let res = levinson_durbin(&[1.0, 1.0, 1.0f64], 2);
+        assert!(res.is_err());
+        assert!(levinson_durbin(&[1.0, 0.5f64], 2).is_err());
+    }
+
+    #[test]
+    fn test_sym_toeplz() {
+        let r = vec![4.0, 1.0, 0.5, 0.25f64];
+        let xt = vec![1.0, -2.0, 3.0, 0.5];
+        let y: Vec<f64> = (0..4)
+            .map(|i| {
+                (0..4)
+                    .map(|j| r[(i as isize - j as isize).unsigned_abs()] * xt[j])
+                    .sum()
+            })
+            .collect();
+        let mut x = vec![0.0; 4];
+        let res = sym_toeplz(&r, &mut x, &y);
+        assert!(res.is_ok());
+        for i in 0..4 {
+            assert!((x[i] - xt[i]).abs() < 1e-10, "x = {:?}", x);
+        }
+
+        let res = sym_toeplz(&[1.0, 1.0, 1.0f64], &mut x[..3], &y[..3]);
+        assert!(res.is_err());
+        let res = sym_toeplz(&r, &mut x[..3], &y);
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<LinAlgError>(),
+            Some(LinAlgError::InvalidVectorSize(3))
+        ));
+        let res = sym_toeplz::<f64>(&[], &mut [], &[]);
+        assert!(matches!(
+            res.unwrap_err().downcast_ref::<LinAlgError>(),
+            Some(LinAlgError::InvalidVectorSize(0))
+        ));
+
+        // ill-conditioned, but solvable in double precision
+        let r = [1.0, 1.0 - 1e-8, 1.0 - 3e-8f64];
+        let xt = [1.0, 2.0, -1.0];
+        let y: Vec<f64> = (0..3)
+            .map(|i| {
+                (0..3)
+                    .map(|j| r[(i as isize - j as isize).unsigned_abs()] * xt[j])
+                    .sum()
+            })
+            .collect();
+        let mut x = vec![0.0; 3];
+        sym_toeplz(&r, &mut x, &y).unwrap();
+        for i in 0..3 {
+            assert!((x[i] - xt[i]).abs() < 1e-5, "x = {:?}", x);
+        }
+    }
+
+    #[test]
+    fn test_trench() {
+        for r in [
+            vec![4.0, 1.0, 0.5, 0.25f64],
+            vec![3.0, -1.0, 0.5, 0.2, 0.1],
+            vec![2.0],
+        ] {
+            let n = r.len();
+            let inv = trench(&r).unwrap();
+            for i in 0..n {
+                for j in 0..n {
+                    let val: f64 = (0..n)
+                        .map(|k| r[(i as isize - k as isize).unsigned_abs()] * inv[k][j])
+                        .sum();
+                    let expect = if i == j { 1.0 } else { 0.0 };
+                    assert!((val - expect).abs() < 1e-10, "inv = {:?}", inv);
+                }
+            }
+        }
+        assert!(trench(&[1.0, 1.0, 0.0f64]).is_err());
+    }
+
+    #[test]
+    fn test_toeplz_mul() {
+        let r = vec![1.0, 2.0, 3.0, 4.0, 0.0f64];
+        let x = vec![1.0, 2.0, 3.0];
+        let y = toeplz_mul(&r, &x).unwrap();
+        assert!((y[0] - 10.0).abs() < 1e-10, "y = {:?}", y);
+        assert!((y[1] - 16.0).abs() < 1e-10, "y = {:?}", y);
+        assert!((y[2] - 17.0).abs() < 1e-10, "y = {:?}", y);
+
+        let n = 13;
+        let r: Vec<f64> = (0..(2 * n - 1))
+            .map(|i| ((i * 7) % 5) as f64 - 1.5)
+            .collect();
+        let x: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+        let y = toeplz_mul(&r, &x).unwrap();
+        for i in 0..n {
+            let expect: f64 = (0..n).map(|j| r[n - 1 + i - j] * x[j]).sum();
+            assert!((y[i] - expect).abs() < 1e-10);
+        }
+        assert!(toeplz_mul(&r[1..], &x).is_err());
+    }
 }
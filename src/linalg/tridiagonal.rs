@@ -1,83 +1,243 @@
 use anyhow::Result;
 
-use crate::MatLinAlgBound;
+use crate::{MatLinAlgBound, Matrix};
 
-use super::LinAlgError;
+use super::{lu_decomposition::LUdcmp, LinAlgError};
 
 /// Solves for a vector u[0..n-1] the tridiagonal linear set given by the equation:
-pub fn tridiag<T>(a: &Vec<T>, b: &Vec<T>, c: &Vec<T>, r: &Vec<T>, u: &mut Vec<f32>) -> Result<()>
+/// a\[j\] u\[j-1\] + b\[j\] u\[j\] + c\[j\] u\[j+1\] = r\[j\].
+/// `a[0]` and `c[n-1]` are not referenced.
+pub fn tridiag<T>(a: &[T], b: &[T], c: &[T], r: &[T], u: &mut [T]) -> Result<()>
 where
     T: MatLinAlgBound,
 {
+    let n = a.len();
+    for len in [b.len(), c.len(), r.len(), u.len()] {
+        if len != n {
+            return Err(LinAlgError::InvalidVectorSize(len).into());
+        }
+    }
     if b[0] == T::zero() {
-        return Err(LinAlgError::ZeroDiagonalElemement(0))?;
+        return Err(LinAlgError::ZeroDiagonalElemement(0).into());
     }
-    let n = a.len();
-    let mut bet = b[0].to_f32().unwrap();
-    let mut gam = vec![0.0; n];
-    u[0] = (r[0].to_f32().unwrap() / bet).into();
+    let mut bet = b[0];
+    let mut gam = vec![T::zero(); n];
+    u[0] = (r[0] / bet).into();
 
     for j in 1..n {
-        gam[j] = c[j - 1].to_f32().unwrap() / bet;
-        bet = b[j].to_f32().unwrap() - a[j].to_f32().unwrap() * gam[j];
-        if bet.abs() < 1e-10 {
-            return Err(LinAlgError::ZeroDivision())?;
+        gam[j] = (c[j - 1] / bet).into();
+        bet = (b[j] - T::from(a[j] * gam[j])).into();
+        if bet.abs() < 1e-10.into() {
+            return Err(LinAlgError::ZeroDivision().into());
         }
-        let temp = a[j].to_f32().unwrap() * u[j - 1];
-        u[j] = (r[j].to_f32().unwrap() - temp) / bet;
+        let temp: T = (a[j] * u[j - 1]).into();
+        u[j] = (T::from(r[j] - temp) / bet).into();
     }
 
     for j in (0..n - 1).rev() {
         let temp = gam[j + 1] * u[j + 1];
-        u[j] -= temp;
+        u[j] -= temp.into();
     }
 
     Ok(())
 }
 
-pub fn cyclic<T>(
-    a: &Vec<T>,
-    b: &Vec<T>,
-    c: &Vec<T>,
-    alpha: T,
-    beta: T,
-    r: &Vec<T>,
-    x: &mut Vec<f32>,
-) -> Result<()>
+/// Solves the periodic tridiagonal system, where `alpha` and `beta` are
+/// the corner entries in the bottom-left and the top-right of the matrix.
+pub fn cyclic<T>(a: &[T], b: &[T], c: &[T], alpha: T, beta: T, r: &[T], x: &mut [T]) -> Result<()>
 where
     T: MatLinAlgBound,
 {
     let n = a.len();
     if n <= 2 {
-        return Err(LinAlgError::InvalidVectorSize(n))?;
+        return Err(LinAlgError::InvalidVectorSize(n).into());
     }
 
-    let mut bb = vec![T::zero(); n];
+    let mut bb = b.to_vec();
     let gamma: T = (-b[0]).into();
     bb[0] = (b[0] - gamma).into();
-    bb[n - 1] = (b[n - 1] - (T::from(alpha * beta) / gamma).into()).into();
+    bb[n - 1] = (b[n - 1] - T::from(T::from(alpha * beta) / gamma)).into();
+    tridiag(a, &bb, c, r, x)?;
 
-    for i in 1..(n - 1) {
-        bb[i] = b[i];
-    }
-    if let Err(err) = tridiag(a, &bb, c, r, x) {
-        return Err(err);
-    }
     let mut u = vec![T::zero(); n];
     u[0] = gamma;
     u[n - 1] = alpha;
-    let mut z = vec![0.0f32; n];
-    if let Err(err) = tridiag(a, &bb, c, &u, &mut z) {
-        return Err(err);
+    let mut z = vec![T::zero(); n];
+    tridiag(a, &bb, c, &u, &mut z)?;
+
+    let mut nume = x[0];
+    nume += T::from(T::from(beta * x[n - 1]) / gamma);
+    let mut deno: T = 1.0.into();
+    deno += z[0];
+    deno += T::from(T::from(beta * z[n - 1]) / gamma);
+    let fact: T = (nume / deno).into();
+    for (xval, zval) in x.iter_mut().zip(z.iter()) {
+        *xval -= (fact * *zval).into();
+    }
+    Ok(())
+}
+
+/// Solves the block tridiagonal linear set
+/// a\[j\] x\[j-1\] + b\[j\] x\[j\] + c\[j\] x\[j+1\] = r\[j\]
+/// by the block Thomas algorithm. Each block is a dense matrix and each diagonal block
+/// is factorized with `LUdcmp`. `a[0]` and `c[n-1]` are not referenced.
+pub fn block_tridiag<T>(
+    a: &[Matrix<T>],
+    b: &[Matrix<T>],
+    c: &[Matrix<T>],
+    r: &[Vec<T>],
+    x: &mut [Vec<T>],
+) -> Result<()>
+where
+    T: MatLinAlgBound,
+{
+    if x.len() != r.len() {
+        return Err(LinAlgError::InvalidVectorSize(x.len()).into());
+    }
+    let rhs: Vec<Matrix<T>> = r
+        .iter()
+        .map(|ri| Matrix::new(ri.len(), 1, ri.clone()))
+        .collect();
+    let sol = block_thomas(a, b, c, &rhs)?;
+    for (xi, si) in x.iter_mut().zip(sol.iter()) {
+        *xi = si.get_col(0);
+    }
+    Ok(())
+}
+
+/// Solves the periodic block tridiagonal linear set, where `alpha` is the block
+/// in the bottom-left corner and `beta` the block in the top-right corner (cf. `cyclic`).
+/// The last unknown block is eliminated and recovered from a dense system of its own size.
+pub fn block_cyclic<T>(
+    a: &[Matrix<T>],
+    b: &[Matrix<T>],
+    c: &[Matrix<T>],
+    alpha: &Matrix<T>,
+    beta: &Matrix<T>,
+    r: &[Vec<T>],
+    x: &mut [Vec<T>],
+) -> Result<()>
+where
+    T: MatLinAlgBound,
+{
+    let n = b.len();
+    if n <= 2 {
+        return Err(LinAlgError::InvalidVectorSize(n).into());
+    }
+    for len in [a.len(), c.len(), r.len(), x.len()] {
+        if len != n {
+            return Err(LinAlgError::InvalidVectorSize(len).into());
+        }
+    }
+    let nn = n - 1;
+    let m = b[nn].rows();
+    if alpha.rows() != m || beta.cols() != m {
+        return Err(LinAlgError::InvalidMatrixSize(alpha.rows(), beta.cols()).into());
+    }
+
+    // Right-hand sides of the reduced system : the first column is r, the rest are the
+    // couplings to the last unknown block (x[n-1]) moved to the right-hand side.
+    let mut rhs = Vec::with_capacity(nn);
+    for (i, ri) in r.iter().take(nn).enumerate() {
+        let mi = ri.len();
+        let mut mat = Matrix::new(mi, m + 1, vec![T::zero(); mi * (m + 1)]);
+        for k in 0..mi {
+            mat[k][0] = ri[k];
+            for j in 0..m {
+                if i == 0 {
+                    mat[k][j + 1] -= beta[k][j];
+                }
+                if i == nn - 1 {
+                    mat[k][j + 1] -= c[i][k][j];
+                }
+            }
+        }
+        rhs.push(mat);
     }
-    let fact = (x[0] + beta.to_f32().unwrap() * x[n - 1] / gamma.to_f32().unwrap())
-        / (1.0 + z[0] + beta.to_f32().unwrap() * z[n - 1] / gamma.to_f32().unwrap());
-    for i in 0..n {
-        x[i] -= fact * z[i];
+    let sol = block_thomas(&a[..nn], &b[..nn], &c[..nn], &rhs)?;
+
+    // Schur complement for the last block.
+    let (p0, q0) = split_first_col(&sol[0]);
+    let (pl, ql) = split_first_col(&sol[nn - 1]);
+    let s = ((b[nn].clone() + (a[nn].clone() * ql)?)? + (alpha.clone() * q0)?)?;
+    let mut rl = Matrix::new(m, 1, r[nn].clone());
+    rl -= (a[nn].clone() * pl)?;
+    rl -= (alpha.clone() * p0)?;
+    let lu = LUdcmp::new(&s)?;
+    let mut y = vec![T::zero(); m];
+    lu.solve(&rl.get_col(0), &mut y)?;
+
+    let ym = Matrix::new(m, 1, y.clone());
+    for (xi, si) in x.iter_mut().zip(sol.iter()) {
+        let (pi, qi) = split_first_col(si);
+        *xi = (pi + (qi * ym.clone())?)?.get_col(0);
     }
+    x[nn] = y;
     Ok(())
 }
 
+/// Block Thomas algorithm for the block tridiagonal system with (possibly) multiple
+/// right-hand side columns stored in each block of `r`.
+fn block_thomas<T>(
+    a: &[Matrix<T>],
+    b: &[Matrix<T>],
+    c: &[Matrix<T>],
+    r: &[Matrix<T>],
+) -> Result<Vec<Matrix<T>>>
+where
+    T: MatLinAlgBound,
+{
+    let n = b.len();
+    for len in [a.len(), c.len(), r.len()] {
+        if len != n {
+            return Err(LinAlgError::InvalidVectorSize(len).into());
+        }
+    }
+    if n == 0 {
+        return Err(LinAlgError::InvalidVectorSize(n).into());
+    }
+
+    let mut gam: Vec<Matrix<T>> = Vec::with_capacity(n);
+    let mut d: Vec<Matrix<T>> = Vec::with_capacity(n);
+    for j in 0..n {
+        let (bet, rhs) = if j == 0 {
+            (b[0].clone(), r[0].clone())
+        } else {
+            (
+                (b[j].clone() - (a[j].clone() * gam[j - 1].clone())?)?,
+                (r[j].clone() - (a[j].clone() * d[j - 1].clone())?)?,
+            )
+        };
+        let lu = LUdcmp::new(&bet)?;
+        let mut dj = rhs.clone();
+        lu.solve_mat(&rhs, &mut dj)?;
+        d.push(dj);
+        if j + 1 < n {
+            let mut gj = c[j].clone();
+            lu.solve_mat(&c[j], &mut gj)?;
+            gam.push(gj);
+        }
+    }
+
+    for j in (0..n - 1).rev() {
+        let dj = (d[j].clone() - (gam[j].clone() * d[j + 1].clone())?)?;
+        d[j] = dj;
+    }
+    Ok(d)
+}
+
+/// Split a matrix into its first column and the remaining columns.
+fn split_first_col<T>(mat: &Matrix<T>) -> (Matrix<T>, Matrix<T>)
+where
+    T: MatLinAlgBound,
+{
+    let rows = mat.rows();
+    let cols = mat.cols() - 1;
+    let first = Matrix::new(rows, 1, mat.get_col(0));
+    let rest = (0..rows).flat_map(|i| mat[i][1..].to_vec()).collect();
+    (first, Matrix::new(rows, cols, rest))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,6 +269,17 @@ mod tests {
         let b = vec![0.0, 1.0, 1.0, 1.0];
         let res = tridiag(&a, &b, &c, &r, &mut u);
         assert!(res.is_err());
+
+        // Case : double precision
+        let a = vec![0.0, 1.0, 1.0, 1.0];
+        let b = vec![2.0, 2.0, 2.0, 2.0];
+        let c = vec![1.0, 1.0, 1.0, 0.0];
+        let r = vec![4.0, 8.0, 12.0, 11.0];
+        let mut u = vec![0.0f64; 4];
+        let res = tridiag(&a, &b, &c, &r, &mut u);
+        assert!(res.is_ok());
+        assert!((u[0] - 1.0).abs() < 1e-12, "u[0] = {}", u[0]);
+        assert!((u[3] - 4.0).abs() < 1e-12, "u[3] = {}", u[3]);
     }
 
     #[test]
@@ -129,4 +300,127 @@ mod tests {
         assert!((x[2] - 3.0).abs() < 1e-5, "x[0] = {}", x[2]);
         assert!((x[3] - 4.0).abs() < 1e-5, "x[0] = {}", x[3]);
     }
+
+    /// Multiply the (periodic) block tridiagonal matrix by the blocks of `x`.
+    fn block_mul(
+        a: &[Matrix<f64>],
+        b: &[Matrix<f64>],
+        c: &[Matrix<f64>],
+        corner: Option<(&Matrix<f64>, &Matrix<f64>)>,
+        x: &[Vec<f64>],
+    ) -> Vec<Vec<f64>> {
+        let n = b.len();
+        let col = |v: &Vec<f64>| Matrix::new(v.len(), 1, v.clone());
+        (0..n)
+            .map(|i| {
+                let mut y = (b[i].clone() * col(&x[i])).unwrap();
+                if i > 0 {
+                    y += (a[i].clone() * col(&x[i - 1])).unwrap();
+                }
+                if i + 1 < n {
+                    y += (c[i].clone() * col(&x[i + 1])).unwrap();
+                }
+                if let Some((alpha, beta)) = corner {
+                    if i == 0 {
+                        y += (beta.clone() * col(&x[n - 1])).unwrap();
+                    }
+                    if i == n - 1 {
+                        y += (alpha.clone() * col(&x[0])).unwrap();
+                    }
+                }
+                y.get_col(0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_block_tridiag() {
+        let n = 4;
+        let a: Vec<Matrix<f64>> = (0..n)
+            .map(|i| Matrix::new(2, 2, vec![-1.0, 0.5, 0.0, -1.0 + 0.1 * i as f64]))
+            .collect();
+        let b: Vec<Matrix<f64>> = (0..n)
+            .map(|i| Matrix::new(2, 2, vec![4.0 + i as f64, 1.0, -1.0, 5.0]))
+            .collect();
+        let c: Vec<Matrix<f64>> = (0..n)
+            .map(|_| Matrix::new(2, 2, vec![-1.0, 0.0, 0.25, -1.0]))
+            .collect();
+        let xt: Vec<Vec<f64>> = (0..n)
+            .map(|i| vec![i as f64 + 1.0, 2.0 - i as f64])
+            .collect();
+
+        let r = block_mul(&a, &b, &c, None, &xt);
+        let mut x = vec![vec![0.0; 2]; n];
+        let res = block_tridiag(&a, &b, &c, &r, &mut x);
+        assert!(res.is_ok(), "{:?}", res);
+        for i in 0..n {
+            for k in 0..2 {
+                assert!((x[i][k] - xt[i][k]).abs() < 1e-10, "x = {:?}", x);
+            }
+        }
+
+        let alpha = Matrix::new(2, 2, vec![0.5, 0.0, 0.0, 0.5]);
+        let beta = Matrix::new(2, 2, vec![0.0, -0.5, 1.0, 0.0]);
+        let r = block_mul(&a, &b, &c, Some((&alpha, &beta)), &xt);
+        let mut x = vec![vec![0.0; 2]; n];
+        let res = block_cyclic(&a, &b, &c, &alpha, &beta, &r, &mut x);
+        assert!(res.is_ok(), "{:?}", res);
+        for i in 0..n {
+            for k in 0..2 {
+                assert!((x[i][k] - xt[i][k]).abs() < 1e-10, "x = {:?}", x);
+            }
+        }
+
+        let res = block_cyclic(
+            &a[..2],
+            &b[..2],
+            &c[..2],
+            &alpha,
+            &beta,
+            &r[..2],
+            &mut x[..2],
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_block_tridiag_pivoting() {
+        // the LU factorization of each diagonal block needs a cycle of row swaps
+        let n = 3;
+        let a: Vec<Matrix<f64>> = (0..n)
+            .map(|_| Matrix::new(3, 3, vec![0.1, 0.0, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1]))
+            .collect();
+        let b: Vec<Matrix<f64>> = (0..n)
+            .map(|i| {
+                let d = i as f64;
+                Matrix::new(3, 3, vec![1.0, 2.0, 4.0, 3.0, 1.0 + d, 2.0, 9.0, 3.0, 1.0])
+            })
+            .collect();
+        let c: Vec<Matrix<f64>> = (0..n)
+            .map(|_| Matrix::new(3, 3, vec![0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.1, 0.0, 0.0]))
+            .collect();
+        let xt: Vec<Vec<f64>> = (0..n)
+            .map(|i| vec![1.0, 2.0 - i as f64, 3.0 * i as f64])
+            .collect();
+
+        let r = block_mul(&a, &b, &c, None, &xt);
+        let mut x = vec![vec![0.0; 3]; n];
+        block_tridiag(&a, &b, &c, &r, &mut x).unwrap();
+        for i in 0..n {
+            for k in 0..3 {
+                assert!((x[i][k] - xt[i][k]).abs() < 1e-10, "x = {:?}", x);
+            }
+        }
+
+        let alpha = Matrix::new(3, 3, vec![0.0, 0.0, 0.2, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0]);
+        let beta = Matrix::new(3, 3, vec![0.1, 0.0, 0.0, 0.0, 0.0, 0.2, 0.0, 0.1, 0.0]);
+        let r = block_mul(&a, &b, &c, Some((&alpha, &beta)), &xt);
+        let mut x = vec![vec![0.0; 3]; n];
+        block_cyclic(&a, &b, &c, &alpha, &beta, &r, &mut x).unwrap();
+        for i in 0..n {
+            for k in 0..3 {
+                assert!((x[i][k] - xt[i][k]).abs() < 1e-10, "x = {:?}", x);
+            }
+        }
+    }
 }
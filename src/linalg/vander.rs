@@ -1,10 +1,19 @@
-use crate::MatLinAlgBound;
+use anyhow::Result;
 
-pub fn vander<T>(x: &Vec<T>, w: &mut Vec<T>, q: &Vec<T>)
+use crate::{interp::InterpError, MatLinAlgBound};
+
+use super::LinAlgError;
+
+/// Solves the Vandermonde system sum_i x\[i\]^k w\[i\] = q\[k\] (k = 0..n-1).
+pub fn vander<T>(x: &[T], w: &mut [T], q: &[T]) -> Result<()>
 where
     T: MatLinAlgBound,
 {
     let n = q.len();
+    if x.len() != n || w.len() != n {
+        return Err(LinAlgError::InvalidVectorSize(x.len()).into());
+    }
+    check_distinct(x)?;
     let mut c = vec![T::zero(); n];
     for i in 0..n {
         let xx: T = (-x[i]).into();
@@ -26,6 +35,168 @@ where
         }
         w[i] = (s / t).into();
     }
+    Ok(())
+}
+
+/// Björck-Pereyra algorithm for the primal system sum_j x\[j\]^i z\[j\] = b\[i\],
+/// i.e. the same system as `vander`.
+pub fn bjorck_pereyra_primal<T>(x: &[T], b: &[T]) -> Result<Vec<T>>
+where
+    T: MatLinAlgBound,
+{
+    let n = x.len();
+    if b.len() != n {
+        return Err(LinAlgError::InvalidVectorSize(b.len()).into());
+    }
+    check_distinct(x)?;
+    let mut z = b.to_vec();
+    for (k, &xk) in x.iter().enumerate().take(n.saturating_sub(1)) {
+        for i in ((k + 1)..n).rev() {
+            let tmp = xk * z[i - 1];
+            z[i] -= tmp.into();
+        }
+    }
+    for k in (0..n.saturating_sub(1)).rev() {
+        for i in (k + 1)..n {
+            z[i] /= (x[i] - x[i - k - 1]).into();
+        }
+        for i in k..(n - 1) {
+            let tmp = z[i + 1];
+            z[i] -= tmp;
+        }
+    }
+    Ok(z)
+}
+
+/// Björck-Pereyra algorithm for the dual system sum_j x\[i\]^j a\[j\] = f\[i\],
+/// i.e. the monomial coefficients of the polynomial interpolating (x\[i\], f\[i\]).
+pub fn bjorck_pereyra_dual<T>(x: &[T], f: &[T]) -> Result<Vec<T>>
+where
+    T: MatLinAlgBound,
+{
+    if f.len() != x.len() {
+        return Err(LinAlgError::InvalidVectorSize(f.len()).into());
+    }
+    check_distinct(x)?;
+    let mut a = f.to_vec();
+    newton_coeffs(x, &mut a, |_, _| T::zero());
+    newton_to_monomial(x, &mut a);
+    Ok(a)
+}
+
+/// Solves the confluent Vandermonde system of Hermite interpolation.
+/// Repeated nodes must be adjacent in `x`. For a node repeated m times,
+/// the corresponding entries of `f` are f(x), f'(x), ..., f^(m-1)(x).
+/// Returns the monomial coefficients of the interpolating polynomial.
+pub fn confluent_vander<T>(x: &[T], f: &[T]) -> Result<Vec<T>>
+where
+    T: MatLinAlgBound,
+{
+    let n = x.len();
+    if f.len() != n {
+        return Err(LinAlgError::InvalidVectorSize(f.len()).into());
+    }
+    // index of the first occurrence of each node
+    let mut start = vec![0; n];
+    for i in 1..n {
+        start[i] = if x[i] == x[i - 1] { start[i - 1] } else { i };
+    }
+    for i in 0..n {
+        for j in 0..start[i] {
+            if x[i] == x[j] {
+                return Err(InterpError::IdenticalX().into());
+            }
+        }
+    }
+    let mut a: Vec<T> = (0..n).map(|i| f[start[i]]).collect();
+    newton_coeffs(x, &mut a, |i, k| {
+        let mut fact: T = 1.0.into();
+        for m in 2..=k {
+            fact *= (m as f32).into();
+        }
+        (f[start[i] + k] / fact).into()
+    });
+    newton_to_monomial(x, &mut a);
+    Ok(a)
+}
+
+/// Monomial coefficients of the polynomial interpolating (x\[i\], y\[i\]).
+pub fn polyfit<T>(x: &[T], y: &[T]) -> Result<Vec<T>>
+where
+    T: MatLinAlgBound,
+{
+    bjorck_pereyra_dual(x, y)
+}
+
+/// Monomial coefficients of the Hermite polynomial matching the values `y` and
+/// the first derivatives `dy` at the distinct nodes `x`.
+pub fn hermite_fit<T>(x: &[T], y: &[T], dy: &[T]) -> Result<Vec<T>>
+where
+    T: MatLinAlgBound,
+{
+    if y.len() != x.len() || dy.len() != x.len() {
+        return Err(LinAlgError::InvalidVectorSize(y.len()).into());
+    }
+    check_distinct(x)?;
+    let xx: Vec<T> = x.iter().flat_map(|&v| [v, v]).collect();
+    let ff: Vec<T> = y.iter().zip(dy).flat_map(|(&v, &d)| [v, d]).collect();
+    confluent_vander(&xx, &ff)
+}
+
+/// Evaluate the polynomial sum_i coeffs\[i\] x^i by the Horner's method.
+pub fn polyval<T>(coeffs: &[T], x: T) -> T
+where
+    T: MatLinAlgBound,
+{
+    coeffs
+        .iter()
+        .rev()
+        .fold(T::zero(), |acc, &c| T::from(acc * x) + c)
+}
+
+fn check_distinct<T>(x: &[T]) -> Result<()>
+where
+    T: MatLinAlgBound,
+{
+    for i in 0..x.len() {
+        if x[(i + 1)..].contains(&x[i]) {
+            return Err(InterpError::IdenticalX().into());
+        }
+    }
+    Ok(())
+}
+
+/// Overwrite `a` by the Newton divided differences. `confluent(i, k)` gives
+/// the k-th order difference when x\[i - k\] == x\[i\].
+fn newton_coeffs<T, F>(x: &[T], a: &mut [T], confluent: F)
+where
+    T: MatLinAlgBound,
+    F: Fn(usize, usize) -> T,
+{
+    let n = x.len();
+    for k in 1..n {
+        for i in (k..n).rev() {
+            if x[i] == x[i - k] {
+                a[i] = confluent(i, k);
+            } else {
+                a[i] = (T::from(a[i] - a[i - 1]) / T::from(x[i] - x[i - k])).into();
+            }
+        }
+    }
+}
+
+/// Convert the coefficients of the Newton form into the monomial ones.
+fn newton_to_monomial<T>(x: &[T], a: &mut [T])
+where
+    T: MatLinAlgBound,
+{
+    let n = x.len();
+    for k in (0..n.saturating_sub(1)).rev() {
+        for i in k..(n - 1) {
+            let tmp = a[i + 1] * x[k];
+            a[i] -= tmp.into();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -38,10 +209,71 @@ mod tests {
         let mut w = vec![0.0, 0.0, 0.0];
         let q = vec![9.0, 20.0, 50.0];
         // let q = vec![3.0, 6.0, 14.0];
-        vander(&x, &mut w, &q);
+        let res = vander(&x, &mut w, &q);
+        assert!(res.is_ok());
         println!("{:?}", w);
         assert!((w[0] - 2.0).abs() < 1e-5);
         assert!((w[1] - 3.0).abs() < 1e-5);
         assert!((w[2] - 4.0).abs() < 1e-5);
+
+        let res = vander(&[1.0, 2.0, 1.0], &mut w, &q);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_bjorck_pereyra() {
+        let x: Vec<f64> = vec![1.0, 2.0, 3.0];
+        let z = bjorck_pereyra_primal(&x, &[9.0, 20.0, 50.0]).unwrap();
+        assert!((z[0] - 2.0).abs() < 1e-10, "z = {:?}", z);
+        assert!((z[1] - 3.0).abs() < 1e-10, "z = {:?}", z);
+        assert!((z[2] - 4.0).abs() < 1e-10, "z = {:?}", z);
+
+        // p(x) = 1 - 2x + 0.5x^3
+        let p = |x: f64| 1.0 - 2.0 * x + 0.5 * x * x * x;
+        let x = vec![-1.0, 0.5, 2.0, 3.0];
+        let f: Vec<f64> = x.iter().map(|&v| p(v)).collect();
+        let a = bjorck_pereyra_dual(&x, &f).unwrap();
+        let expect = [1.0, -2.0, 0.0, 0.5];
+        for i in 0..4 {
+            assert!((a[i] - expect[i]).abs() < 1e-10, "a = {:?}", a);
+        }
+        let a = polyfit(&x, &f).unwrap();
+        assert!((polyval(&a, 1.5) - p(1.5)).abs() < 1e-10);
+
+        let res = bjorck_pereyra_dual(&[0.0, 1.0, 0.0], &[1.0, 2.0, 3.0]);
+        assert!(res.is_err());
+        let res = bjorck_pereyra_primal(&[0.0, 1.0, 1.0], &[1.0, 2.0, 3.0]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_confluent_vander() {
+        // p(x) = x^4 - 2x^3 + x + 3
+        let p = |x: f64| x.powi(4) - 2.0 * x.powi(3) + x + 3.0;
+        let dp = |x: f64| 4.0 * x.powi(3) - 6.0 * x * x + 1.0;
+        let ddp = |x: f64| 12.0 * x * x - 12.0 * x;
+        let x = vec![0.0, 0.0, 0.0, 1.0, 2.0];
+        let f = vec![p(0.0), dp(0.0), ddp(0.0), p(1.0), p(2.0)];
+        let a = confluent_vander(&x, &f).unwrap();
+        let expect = [3.0, 1.0, 0.0, -2.0, 1.0];
+        for i in 0..5 {
+            assert!((a[i] - expect[i]).abs() < 1e-10, "a = {:?}", a);
+        }
+
+        let x = vec![-1.0, 1.0, 2.0];
+        let y: Vec<f64> = x.iter().map(|&v| p(v)).collect();
+        let dy: Vec<f64> = x.iter().map(|&v| dp(v)).collect();
+        let a = hermite_fit(&x, &y, &dy).unwrap();
+        assert_eq!(a.len(), 6);
+        for i in 0..5 {
+            assert!((a[i] - expect[i]).abs() < 1e-10, "a = {:?}", a);
+        }
+        assert!(a[5].abs() < 1e-10, "a = {:?}", a);
+
+        // repeated nodes which are not adjacent
+        let res = confluent_vander(&[0.0, 1.0, 0.0], &[1.0, 2.0, 3.0]);
+        assert!(res.is_err());
+        let res = hermite_fit(&[0.0, 0.0], &[1.0, 1.0], &[0.0, 0.0]);
+        assert!(res.is_err());
     }
 }